@@ -0,0 +1,101 @@
+use bytes::BytesMut;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{BulkString, RespDecode, RespEncode, RespError, RespFrame, RespParser};
+
+const VALUE_SIZE: usize = 1024 * 1024;
+
+// SET key <1 MB value>, as read from a client
+fn set_command() -> BytesMut {
+    let mut buf = BytesMut::from(&b"*3\r\n$3\r\nset\r\n$3\r\nkey\r\n"[..]);
+    BulkString::new(vec![b'x'; VALUE_SIZE]).encode_to(&mut buf);
+    buf
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let input = set_command();
+    let mut group = c.benchmark_group("decode_1mb");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_function(BenchmarkId::new("bytes", "split"), |b| {
+        b.iter_batched(
+            || input.clone(),
+            |mut buf| black_box(RespFrame::decode(&mut buf).unwrap()),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let frame: RespFrame = BulkString::new(vec![b'x'; VALUE_SIZE]).into();
+    let mut group = c.benchmark_group("encode_1mb");
+    group.throughput(Throughput::Bytes(VALUE_SIZE as u64));
+
+    group.bench_function(BenchmarkId::new("bytes", "encode_to"), |b| {
+        b.iter_batched(
+            || (frame.clone(), BytesMut::new()),
+            |(frame, mut dst)| {
+                frame.encode_to(&mut dst);
+                black_box(dst)
+            },
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    // the frame encoded into its own Vec first, then copied into the codec buffer
+    group.bench_function(BenchmarkId::new("vec", "encode"), |b| {
+        b.iter_batched(
+            || (frame.clone(), BytesMut::new()),
+            |(frame, mut dst)| {
+                dst.extend_from_slice(&frame.encode());
+                black_box(dst)
+            },
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+// RPUSH with 20k small elements, about 300 KB read 4 KB at a time like from a socket
+fn bench_large_array(c: &mut Criterion) {
+    let count = 20_000;
+    let mut input =
+        BytesMut::from(format!("*{}\r\n$5\r\nrpush\r\n$4\r\nlist\r\n", count + 2).as_bytes());
+    for i in 0..count {
+        BulkString::new(format!("element:{}", i)).encode_to(&mut input);
+    }
+    let mut group = c.benchmark_group("large_array_4kb_reads");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+
+    group.bench_function("parser", |b| {
+        b.iter(|| {
+            let mut parser = RespParser::default();
+            let mut buf = BytesMut::new();
+            for chunk in input.chunks(4096) {
+                buf.extend_from_slice(chunk);
+                while let Some(frame) = parser.parse(&mut buf).unwrap() {
+                    black_box(frame);
+                }
+            }
+        })
+    });
+    // every read rescans the whole pending frame from its start
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            let mut buf = BytesMut::new();
+            for chunk in input.chunks(4096) {
+                buf.extend_from_slice(chunk);
+                loop {
+                    match RespFrame::decode(&mut buf) {
+                        Ok(frame) => black_box(frame),
+                        Err(RespError::NotComplete) => break,
+                        Err(e) => panic!("{}", e),
+                    };
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_encode, bench_large_array);
+criterion_main!(benches);
@@ -0,0 +1,815 @@
+use bytes::Bytes;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+
+use bytes::BytesMut;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::backend::dirty;
+use crate::{
+    is_write_command, now_millis, AppendFsync, Backend, BulkString, Client, Command,
+    CommandExecutor, RespArray, RespEncode, RespFrame, RespParser, SimpleError, SimpleString,
+    StringValue, Value,
+};
+
+// same as redis, a rewritten RPUSH or SADD carries at most this many elements
+const AOF_REWRITE_ITEMS_PER_CMD: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum AofError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Bad file format reading the append only file: {0}")]
+    InvalidFormat(String),
+    #[error("Background append only file rewriting already in progress")]
+    RewriteInProgress,
+    #[error("append only file is disabled")]
+    Disabled,
+}
+
+/// Append only file, every write command is appended in RESP encoding
+#[derive(Debug)]
+pub struct Aof {
+    path: PathBuf,
+    fsync: AppendFsync,
+    state: Arc<Mutex<AofState>>,
+    // commands hold it shared while executing and appending, a rewrite takes it exclusively so
+    // that every command ends up either in the rewrite snapshot or in the rewrite buffer
+    gate: RwLock<()>,
+    rewriting: AtomicBool,
+}
+
+#[derive(Debug)]
+struct AofState {
+    file: File,
+    // commands appended while a rewrite is running, copied to the new file once it is done
+    rewrite_buf: Option<Vec<u8>>,
+    dirty: bool,
+    // the database the commands at the end of the file run in, None when it is not known
+    db: Option<usize>,
+}
+
+// a point-in-time copy of one key for the rewrite
+enum SnapshotValue {
+    String(StringValue),
+    Hash(Vec<(Bytes, RespFrame)>),
+    List(Vec<RespFrame>),
+    Set(Vec<Vec<u8>>),
+    ZSet(Vec<(Vec<u8>, f64)>),
+}
+
+struct KeySnapshot {
+    db: usize,
+    key: Bytes,
+    value: SnapshotValue,
+    deadline: Option<i64>,
+}
+
+impl Aof {
+    pub fn open(path: &Path, fsync: AppendFsync) -> Result<Self, AofError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let state = Arc::new(Mutex::new(AofState {
+            file,
+            rewrite_buf: None,
+            dirty: false,
+            db: None,
+        }));
+
+        if fsync == AppendFsync::EverySec {
+            spawn_fsync_every_sec(Arc::downgrade(&state));
+        }
+
+        Ok(Aof {
+            path: path.to_path_buf(),
+            fsync,
+            state,
+            gate: RwLock::new(()),
+            rewriting: AtomicBool::new(false),
+        })
+    }
+
+    /// Execute a write command and append it to the file before the reply is sent
+    pub fn execute(&self, cmd: Command, args: RespArray, backend: &Backend) -> RespFrame {
+        let _gate = self.gate.read().unwrap_or_else(|e| e.into_inner());
+
+        let mut commands = Vec::new();
+        let ret = self.execute_logged(cmd, args, backend, &mut commands);
+        self.append_logged(commands);
+        ret
+    }
+
+    /// Run the queue of an EXEC, each command with the database it was queued in and its
+    /// original frame. The writes are appended at once between MULTI and EXEC, so a crash
+    /// never leaves half of the transaction in the file.
+    pub(crate) fn execute_transaction(
+        &self,
+        backend: &Backend,
+        queued: Vec<(usize, Command, Option<RespArray>)>,
+    ) -> Vec<RespFrame> {
+        let mut rewrite = false;
+        let replies = {
+            let _gate = self.gate.read().unwrap_or_else(|e| e.into_inner());
+
+            let mut commands = Vec::new();
+            let replies = queued
+                .into_iter()
+                .map(|(db, cmd, args)| {
+                    let backend = backend.select(db);
+                    match (cmd, args) {
+                        // a rewrite takes the gate held here, it starts once the transaction
+                        // is in the file
+                        (Command::BgRewriteAof(_), _) => self.schedule_rewrite(&mut rewrite),
+                        // blocking commands never wait inside a transaction
+                        (cmd, Some(args)) if is_write_command(&args) => {
+                            self.execute_logged(cmd, args, &backend, &mut commands)
+                        }
+                        (cmd, _) => cmd.execute(&backend),
+                    }
+                })
+                .collect();
+            if let (Some(&(first, _)), Some(&(last, _))) = (commands.first(), commands.last()) {
+                commands.insert(0, (first, encode(aof_command(vec![b"multi".to_vec()]))));
+                commands.push((last, encode(aof_command(vec![b"exec".to_vec()]))));
+            }
+            self.append_logged(commands);
+            replies
+        };
+        if rewrite {
+            if let Err(e) = self.rewrite_background(backend) {
+                warn!("Background AOF rewrite error: {}", e);
+            }
+        }
+        replies
+    }
+
+    // BGREWRITEAOF inside EXEC, the rewrite is started after the transaction like redis does
+    fn schedule_rewrite(&self, scheduled: &mut bool) -> RespFrame {
+        if *scheduled || self.rewriting.load(Ordering::Acquire) {
+            return SimpleError::new(format!("ERR {}", AofError::RewriteInProgress)).into();
+        }
+        *scheduled = true;
+        SimpleString::new("Background append only file rewriting scheduled").into()
+    }
+
+    // Execute a write command, `commands` gets what replicates it in the file, each with the
+    // database it runs in
+    fn execute_logged(
+        &self,
+        cmd: Command,
+        args: RespArray,
+        backend: &Backend,
+        commands: &mut Vec<(usize, BytesMut)>,
+    ) -> RespFrame {
+        let cmd = match cmd {
+            // SPOP removes random members, a replay has to remove the same ones
+            Command::SPop(cmd) => {
+                let mut propagated = Vec::new();
+                let ret = cmd.execute_propagated(backend, &mut propagated);
+                commands.extend(propagated.into_iter().map(|(db, cmd)| (db, encode(cmd))));
+                return ret;
+            }
+            cmd => cmd,
+        };
+
+        let ttl_key = cmd.relative_ttl_key().map(|key| key.to_vec());
+        let changes = dirty();
+        let ret = cmd.execute(backend);
+        // a command that failed or found nothing to do, like a SET NX of an existing key,
+        // leaves nothing to replay
+        if dirty() == changes {
+            return ret;
+        }
+
+        let mut buf = encode(args);
+        // relative expire times are pinned to the deadline computed now, otherwise a replay
+        // would give the key a fresh ttl
+        if let Some(key) = ttl_key {
+            if let Some(deadline) = backend.db().expires.get(&key[..]).map(|v| *v.value()) {
+                command_frame(
+                    &mut buf,
+                    &[
+                        b"pexpireat".to_vec(),
+                        key,
+                        deadline.to_string().into_bytes(),
+                    ],
+                );
+            }
+        }
+        commands.push((backend.db_index(), buf));
+        ret
+    }
+
+    /// Run `f` like a write command and append the commands it replicates its effect with,
+    /// for writes that are not a client command as is, like elements handed to blocked clients
+    /// `f` gives each command with the database it runs in.
+    pub(crate) fn propagate<T>(&self, f: impl FnOnce(&mut Vec<(usize, RespArray)>) -> T) -> T {
+        let _gate = self.gate.read().unwrap_or_else(|e| e.into_inner());
+
+        let mut commands = Vec::new();
+        let ret = f(&mut commands);
+        self.append_logged(
+            commands
+                .into_iter()
+                .map(|(db, cmd)| (db, encode(cmd)))
+                .collect(),
+        );
+        ret
+    }
+
+    // a write failing is logged, the command already ran
+    fn append_logged(&self, commands: Vec<(usize, BytesMut)>) {
+        if commands.is_empty() {
+            return;
+        }
+        if let Err(e) = self.append(commands) {
+            warn!("write to append only file error: {}", e);
+        }
+    }
+
+    // `commands` are encoded, each with the database it ran in. A SELECT goes before the first
+    // one running in another database than the previous command of the file.
+    fn append(&self, commands: Vec<(usize, BytesMut)>) -> Result<(), AofError> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut buf = BytesMut::new();
+        let mut selected = state.db;
+        for (db, cmd) in commands {
+            if selected != Some(db) {
+                select_frame(&mut buf, db);
+                selected = Some(db);
+            }
+            buf.extend_from_slice(&cmd);
+        }
+        // the file may end with a partial write
+        state.db = None;
+        state.file.write_all(&buf)?;
+        state.db = selected;
+        if let Some(rewrite_buf) = state.rewrite_buf.as_mut() {
+            rewrite_buf.extend_from_slice(&buf);
+        }
+
+        match self.fsync {
+            AppendFsync::Always => state.file.sync_data()?,
+            AppendFsync::EverySec => state.dirty = true,
+            AppendFsync::No => {}
+        }
+        Ok(())
+    }
+
+    /// BGREWRITEAOF: write the smallest set of commands rebuilding the current dataset
+    /// to a new file on a dedicated thread, then swap it with the current file
+    pub fn rewrite_background(&self, backend: &Backend) -> Result<(), AofError> {
+        if self
+            .rewriting
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(AofError::RewriteInProgress);
+        }
+
+        let snapshot = {
+            let _gate = self.gate.write().unwrap_or_else(|e| e.into_inner());
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.rewrite_buf = Some(Vec::new());
+            // the buffer follows the rewritten commands, whatever database they end with
+            state.db = None;
+            snapshot(backend)
+        };
+
+        let backend = backend.clone();
+        std::thread::spawn(move || {
+            let Some(aof) = backend.aof() else {
+                return;
+            };
+            match aof.finish_rewrite(snapshot) {
+                Ok(_) => info!("Background AOF rewrite finished successfully"),
+                Err(e) => {
+                    warn!("Background AOF rewrite error: {}", e);
+                    let mut state = aof.state.lock().unwrap_or_else(|e| e.into_inner());
+                    state.rewrite_buf = None;
+                }
+            }
+            aof.rewriting.store(false, Ordering::Release);
+        });
+        Ok(())
+    }
+
+    fn finish_rewrite(&self, snapshot: Vec<KeySnapshot>) -> Result<(), AofError> {
+        let tmp = self
+            .path
+            .with_file_name(format!("temp-rewriteaof-bg-{}.aof", std::process::id()));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&rewrite_commands(snapshot))?;
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(rewrite_buf) = state.rewrite_buf.take() {
+            file.write_all(&rewrite_buf)?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&tmp, &self.path)?;
+
+        state.file = OpenOptions::new().append(true).open(&self.path)?;
+        state.dirty = false;
+        Ok(())
+    }
+
+    /// Rewrite in the foreground, used to seed the file when it is enabled on a loaded dataset
+    pub fn rewrite(&self, backend: &Backend) -> Result<(), AofError> {
+        let _gate = self.gate.write().unwrap_or_else(|e| e.into_inner());
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let tmp = self
+            .path
+            .with_file_name(format!("temp-rewriteaof-{}.aof", std::process::id()));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&rewrite_commands(snapshot(backend)))?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &self.path)?;
+
+        state.file = OpenOptions::new().append(true).open(&self.path)?;
+        state.db = None;
+        Ok(())
+    }
+}
+
+fn spawn_fsync_every_sec(state: Weak<Mutex<AofState>>) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_secs(1));
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+
+        // fsync a duplicated handle, so appends are not blocked by a slow disk
+        let file = {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            if !state.dirty {
+                continue;
+            }
+            state.dirty = false;
+            state.file.try_clone()
+        };
+        if let Err(e) = file.and_then(|file| file.sync_data()) {
+            warn!("fsync append only file error: {}", e);
+        }
+    });
+}
+
+fn snapshot(backend: &Backend) -> Vec<KeySnapshot> {
+    let now = now_millis();
+    let mut keys = Vec::new();
+
+    for index in 0..backend.databases() {
+        let db = backend.select(index);
+        let deadline = |key: &[u8]| db.db().expires.get(key).map(|v| *v.value());
+        for entry in db.db().keyspace.iter() {
+            let value = match entry.value() {
+                Value::String(value) => SnapshotValue::String(value.clone()),
+                Value::Hash(hash) => SnapshotValue::Hash(
+                    hash.iter()
+                        .map(|(field, value)| (field.clone(), value.clone()))
+                        .collect(),
+                ),
+                Value::List(list) => SnapshotValue::List(list.iter().cloned().collect()),
+                Value::Set(set) => SnapshotValue::Set(set.iter().cloned().collect()),
+                Value::ZSet(zset) => SnapshotValue::ZSet(
+                    zset.iter()
+                        .map(|(member, score)| (member.to_vec(), score))
+                        .collect(),
+                ),
+            };
+            keys.push(KeySnapshot {
+                db: index,
+                key: entry.key().clone(),
+                value,
+                deadline: deadline(entry.key()),
+            });
+        }
+    }
+
+    keys.retain(|v| v.deadline.is_none_or(|deadline| deadline > now));
+    keys
+}
+
+/// A command replicating a write in the aof, for `Aof::propagate`
+pub(crate) fn aof_command(args: Vec<Vec<u8>>) -> RespArray {
+    RespArray::new(
+        args.into_iter()
+            .map(|arg| BulkString::new(arg).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
+
+fn encode(cmd: RespArray) -> BytesMut {
+    let mut buf = BytesMut::new();
+    RespFrame::Array(cmd).encode_to(&mut buf);
+    buf
+}
+
+fn command_frame(buf: &mut BytesMut, args: &[Vec<u8>]) {
+    let frames = args
+        .iter()
+        .map(|arg| BulkString::new(arg.clone()).into())
+        .collect::<Vec<RespFrame>>();
+    RespFrame::Array(RespArray::new(frames)).encode_to(buf);
+}
+
+fn select_frame(buf: &mut BytesMut, db: usize) {
+    command_frame(buf, &[b"select".to_vec(), db.to_string().into_bytes()]);
+}
+
+fn rewrite_commands(snapshot: Vec<KeySnapshot>) -> BytesMut {
+    let mut buf = BytesMut::new();
+    let mut selected = None;
+    for KeySnapshot {
+        db,
+        key,
+        value,
+        deadline,
+    } in snapshot
+    {
+        if selected != Some(db) {
+            select_frame(&mut buf, db);
+            selected = Some(db);
+        }
+        let name = || BulkString::new(key.clone()).into();
+        match value {
+            SnapshotValue::String(value) => {
+                let frames: Vec<RespFrame> =
+                    vec![BulkString::new("set").into(), name(), value.into()];
+                RespFrame::Array(RespArray::new(frames)).encode_to(&mut buf);
+            }
+            SnapshotValue::Hash(fields) => {
+                for (field, value) in fields {
+                    let frames: Vec<RespFrame> = vec![
+                        BulkString::new("hset").into(),
+                        name(),
+                        BulkString::new(field).into(),
+                        value,
+                    ];
+                    RespFrame::Array(RespArray::new(frames)).encode_to(&mut buf);
+                }
+            }
+            SnapshotValue::List(values) => {
+                // keep every command reasonably small for big collections
+                for chunk in values.chunks(AOF_REWRITE_ITEMS_PER_CMD) {
+                    let mut frames: Vec<RespFrame> = vec![BulkString::new("rpush").into(), name()];
+                    frames.extend_from_slice(chunk);
+                    RespFrame::Array(RespArray::new(frames)).encode_to(&mut buf);
+                }
+            }
+            SnapshotValue::Set(members) => {
+                for chunk in members.chunks(AOF_REWRITE_ITEMS_PER_CMD) {
+                    let mut args = vec![b"sadd".to_vec(), key.to_vec()];
+                    args.extend_from_slice(chunk);
+                    command_frame(&mut buf, &args);
+                }
+            }
+            SnapshotValue::ZSet(members) => {
+                for chunk in members.chunks(AOF_REWRITE_ITEMS_PER_CMD) {
+                    let mut args = vec![b"zadd".to_vec(), key.to_vec()];
+                    for (member, score) in chunk {
+                        // shortest form that parses back to the same double
+                        args.push(score.to_string().into_bytes());
+                        args.push(member.clone());
+                    }
+                    command_frame(&mut buf, &args);
+                }
+            }
+        }
+        if let Some(deadline) = deadline {
+            command_frame(
+                &mut buf,
+                &[
+                    b"pexpireat".to_vec(),
+                    key.to_vec(),
+                    deadline.to_string().into_bytes(),
+                ],
+            );
+        }
+    }
+    buf
+}
+
+/// Replay the append only file, returns the number of commands executed.
+/// A truncated command at the end of the file, left by a crash, is dropped.
+pub fn load(backend: &Backend, path: &Path) -> Result<usize, AofError> {
+    let data = std::fs::read(path)?;
+    let total = data.len();
+    let mut buf = BytesMut::from(&data[..]);
+    let mut parser = RespParser::new(backend.config().protocol_limits());
+    let mut replayed = 0;
+    let mut client = Client::new();
+    // the offset of a MULTI whose EXEC was not read yet, and the commands after it with
+    // the database each runs in
+    let mut transaction: Option<(usize, Vec<(usize, Command)>)> = None;
+
+    while !buf.is_empty() {
+        let offset = total - buf.len();
+        let frame = match parser.parse(&mut buf) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => {
+                warn!(
+                    "!!! Warning: short read while loading the AOF file, truncate it at offset {} !!!",
+                    offset
+                );
+                truncate(path, offset)?;
+                break;
+            }
+            Err(e) => return Err(AofError::InvalidFormat(e.to_string())),
+        };
+
+        let cmd = Command::try_from(frame).map_err(|e| AofError::InvalidFormat(e.to_string()))?;
+        match cmd {
+            Command::Multi(_) => transaction = Some((offset, Vec::new())),
+            Command::Exec(_) => {
+                let (_, queued) = transaction
+                    .take()
+                    .ok_or_else(|| AofError::InvalidFormat("EXEC without MULTI".to_string()))?;
+                for (db, cmd) in queued {
+                    cmd.execute(&backend.select(db));
+                    replayed += 1;
+                }
+            }
+            // SELECT switches the database the following commands run in, it is not counted
+            cmd if cmd.is_connection() => {
+                cmd.execute_connection(backend, &mut client);
+            }
+            cmd => match transaction.as_mut() {
+                Some((_, queued)) => queued.push((client.db(), cmd)),
+                None => {
+                    cmd.execute(&backend.select(client.db()));
+                    replayed += 1;
+                }
+            },
+        }
+    }
+
+    // none of a transaction cut short by a crash is applied
+    if let Some((offset, _)) = transaction {
+        warn!(
+            "!!! Warning: revert incomplete MULTI/EXEC transaction in the AOF file, truncate it at offset {} !!!",
+            offset
+        );
+        truncate(path, offset)?;
+    }
+
+    info!("DB loaded from append only file: {} commands", replayed);
+    Ok(replayed)
+}
+
+fn truncate(path: &Path, offset: usize) -> Result<(), AofError> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_len(offset as u64)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::cmd::command;
+    use crate::{Config, KeyExpiry};
+
+    fn temp_config(name: &str) -> Config {
+        let dir =
+            std::env::temp_dir().join(format!("simple-redis-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Config {
+            dir,
+            appendonly: true,
+            appendfsync: AppendFsync::Always,
+            ..Default::default()
+        }
+    }
+
+    fn execute(backend: &Backend, args: &[&str]) -> RespFrame {
+        let cmd = Command::try_from(command(args)).unwrap();
+        backend.aof().unwrap().execute(cmd, command(args), backend)
+    }
+
+    #[test]
+    fn test_aof_append_and_replay() -> Result<(), AofError> {
+        let config = temp_config("replay");
+        let path = config.aof_path();
+        let _ = std::fs::remove_file(&path);
+
+        let backend = Backend::with_config(config.clone());
+        backend.enable_aof()?;
+        execute(&backend, &["set", "key", "value"]);
+        execute(&backend, &["hset", "hash", "field", "value"]);
+        execute(&backend, &["expire", "key", "100"]);
+
+        // simulate a crash in the middle of a write
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(b"*3\r\n$3\r\nset\r\n$3\r\nfoo")?;
+
+        let restored = Backend::with_config(config);
+        assert_eq!(load(&restored, &path)?, 4);
+        assert_eq!(
+            restored.get(b"key"),
+            Ok(Some(BulkString::new("value").into()))
+        );
+        assert_eq!(
+            restored.hget(b"hash", b"field"),
+            Ok(Some(BulkString::new("value").into()))
+        );
+        assert_eq!(restored.expiry(b"key"), backend.expiry(b"key"));
+        assert!(matches!(restored.expiry(b"key"), KeyExpiry::At(_)));
+
+        // the broken tail was truncated
+        assert!(std::fs::read(&path)?.ends_with(b"\r\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_rewrite() -> Result<(), AofError> {
+        let config = temp_config("rewrite");
+        let path = config.aof_path();
+        let _ = std::fs::remove_file(&path);
+
+        let backend = Backend::with_config(config.clone());
+        backend.enable_aof()?;
+        for i in 0..10 {
+            execute(&backend, &["set", "key", &i.to_string()]);
+        }
+        let before = std::fs::metadata(&path)?.len();
+        backend.aof().unwrap().rewrite(&backend)?;
+        assert!(std::fs::metadata(&path)?.len() < before);
+
+        execute(&backend, &["set", "other", "value"]);
+        let restored = Backend::with_config(config);
+        assert_eq!(load(&restored, &path)?, 2);
+        assert_eq!(restored.get(b"key"), Ok(Some(BulkString::new("9").into())));
+        assert_eq!(
+            restored.get(b"other"),
+            Ok(Some(BulkString::new("value").into()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_skips_unchanged() -> Result<(), AofError> {
+        let config = temp_config("unchanged");
+        let path = config.aof_path();
+        let _ = std::fs::remove_file(&path);
+
+        let backend = Backend::with_config(config);
+        backend.enable_aof()?;
+        execute(&backend, &["set", "key", "value"]);
+        let len = std::fs::metadata(&path)?.len();
+
+        execute(&backend, &["set", "key", "other", "nx"]);
+        execute(&backend, &["lpush", "key", "a"]);
+        execute(&backend, &["del", "missing"]);
+        execute(&backend, &["srem", "set", "a"]);
+        assert_eq!(std::fs::metadata(&path)?.len(), len);
+
+        execute(&backend, &["del", "key"]);
+        assert!(std::fs::metadata(&path)?.len() > len);
+        Ok(())
+    }
+
+    // SPOP picks random members, the replay has to remove the ones it picked
+    #[test]
+    fn test_aof_spop() -> Result<(), AofError> {
+        let config = temp_config("spop");
+        let path = config.aof_path();
+        let _ = std::fs::remove_file(&path);
+
+        let backend = Backend::with_config(config.clone());
+        backend.enable_aof()?;
+        let members = (0..50).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut args = vec!["sadd", "set"];
+        args.extend(members.iter().map(|m| m.as_str()));
+        execute(&backend, &args);
+        execute(&backend, &["spop", "set", "10"]);
+        execute(&backend, &["spop", "set"]);
+        execute(&backend, &["spop", "missing"]);
+
+        let restored = Backend::with_config(config);
+        load(&restored, &path)?;
+        let mut expected = backend.smembers(b"set").unwrap();
+        expected.sort();
+        let mut actual = restored.smembers(b"set").unwrap();
+        actual.sort();
+        assert_eq!(expected.len(), 39);
+        assert_eq!(actual, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_transaction() -> Result<(), AofError> {
+        let config = temp_config("transaction");
+        let path = config.aof_path();
+        let _ = std::fs::remove_file(&path);
+
+        let backend = Backend::with_config(config.clone());
+        backend.enable_aof()?;
+        let queued = [&["set", "a", "1"][..], &["get", "a"], &["set", "b", "2"]]
+            .iter()
+            .map(|args| {
+                let cmd = Command::try_from(command(args)).unwrap();
+                (0, cmd, Some(command(args)))
+            })
+            .collect();
+        backend.aof().unwrap().execute_transaction(&backend, queued);
+        let complete = std::fs::metadata(&path)?.len();
+
+        // a crash in the middle of the next transaction
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        for args in [&["multi"][..], &["set", "a", "3"], &["set", "c", "3"]] {
+            let mut buf = BytesMut::new();
+            RespFrame::Array(command(args)).encode_to(&mut buf);
+            file.write_all(&buf)?;
+        }
+
+        let restored = Backend::with_config(config);
+        assert_eq!(load(&restored, &path)?, 2);
+        assert_eq!(restored.get(b"a"), Ok(Some(BulkString::new("1").into())));
+        assert_eq!(restored.get(b"b"), Ok(Some(BulkString::new("2").into())));
+        assert!(!restored.contains_key(b"c"));
+        // the incomplete transaction was cut from the file
+        assert_eq!(std::fs::metadata(&path)?.len(), complete);
+        Ok(())
+    }
+
+    // the rewrite needs the gate the transaction holds, it starts once EXEC is done
+    #[test]
+    fn test_aof_rewrite_in_transaction() -> Result<(), AofError> {
+        let config = temp_config("rewrite-transaction");
+        let path = config.aof_path();
+        let _ = std::fs::remove_file(&path);
+
+        let backend = Backend::with_config(config.clone());
+        backend.enable_aof()?;
+        for i in 0..10 {
+            execute(&backend, &["set", "key", &i.to_string()]);
+        }
+        let before = std::fs::metadata(&path)?.len();
+        let queued = [
+            &["set", "other", "1"][..],
+            &["bgrewriteaof"],
+            &["bgrewriteaof"],
+        ]
+        .iter()
+        .map(|args| {
+            let cmd = Command::try_from(command(args)).unwrap();
+            (0, cmd, Some(command(args)))
+        })
+        .collect();
+        let replies = backend.aof().unwrap().execute_transaction(&backend, queued);
+        assert_eq!(
+            replies[1],
+            SimpleString::new("Background append only file rewriting scheduled").into()
+        );
+        assert_eq!(
+            replies[2],
+            SimpleError::new("ERR Background append only file rewriting already in progress")
+                .into()
+        );
+
+        while backend.aof().unwrap().rewriting.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(std::fs::metadata(&path)?.len() < before);
+        let restored = Backend::with_config(config);
+        load(&restored, &path)?;
+        assert_eq!(restored.get(b"key"), Ok(Some(BulkString::new("9").into())));
+        assert_eq!(
+            restored.get(b"other"),
+            Ok(Some(BulkString::new("1").into()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_aof_databases() -> Result<(), AofError> {
+        let config = temp_config("databases");
+        let path = config.aof_path();
+        let _ = std::fs::remove_file(&path);
+
+        let backend = Backend::with_config(config.clone());
+        backend.enable_aof()?;
+        execute(&backend.select(2), &["set", "key", "2"]);
+        execute(&backend, &["set", "key", "0"]);
+        backend.aof().unwrap().rewrite(&backend)?;
+        // after a rewrite the next command selects its database again
+        execute(&backend.select(2), &["set", "other", "2"]);
+
+        let restored = Backend::with_config(config);
+        assert_eq!(load(&restored, &path)?, 3);
+        assert_eq!(restored.get(b"key"), Ok(Some(BulkString::new("0").into())));
+        let db = restored.select(2);
+        assert_eq!(db.get(b"key"), Ok(Some(BulkString::new("2").into())));
+        assert_eq!(db.get(b"other"), Ok(Some(BulkString::new("2").into())));
+        assert!(!restored.contains_key(b"other"));
+        Ok(())
+    }
+}
@@ -0,0 +1,410 @@
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::aof::aof_command;
+use crate::{Backend, BackendError, ListEnd, RespArray, RespFrame};
+
+/// What a blocked client does with the first list that gets elements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockingOp {
+    /// BLPOP / BRPOP / BLMPOP
+    Pop { end: ListEnd, count: usize },
+    /// BLMOVE
+    Move {
+        destination: Bytes,
+        from: ListEnd,
+        to: ListEnd,
+    },
+}
+
+/// The key served and the elements popped from it
+pub type Served = (Bytes, Vec<RespFrame>);
+
+type Reply = Result<Served, BackendError>;
+
+#[derive(Debug)]
+struct Waiter {
+    db: usize,
+    keys: Vec<Bytes>,
+    op: BlockingOp,
+    // taken by whoever finishes the wait first: a push serving it, its timeout or its cancellation
+    reply: Mutex<Option<oneshot::Sender<Reply>>>,
+}
+
+// a key of one of the databases
+type DbKey = (usize, Bytes);
+
+/// Clients blocked on list keys
+#[derive(Debug, Default)]
+pub struct BlockingState {
+    // key -> clients blocked on it, served in the order they blocked
+    waiters: HashMap<DbKey, VecDeque<Arc<Waiter>>>,
+    // keys that received elements since clients were last served
+    ready: VecDeque<DbKey>,
+}
+
+impl BlockingState {
+    fn mark_ready(&mut self, db: usize, key: &[u8]) {
+        let key = (db, Bytes::copy_from_slice(key));
+        if self.waiters.contains_key(&key) && !self.ready.contains(&key) {
+            self.ready.push_back(key);
+        }
+    }
+
+    /// Every key some client waits for in `db` may have elements, after a SWAPDB
+    pub(crate) fn mark_db_ready(&mut self, db: usize) {
+        let keys = self
+            .waiters
+            .keys()
+            .filter(|(d, _)| *d == db)
+            .map(|(_, key)| key.clone())
+            .collect::<Vec<_>>();
+        for key in keys {
+            self.mark_ready(db, &key);
+        }
+    }
+
+    fn remove_waiter(&mut self, waiter: &Arc<Waiter>) {
+        for key in &waiter.keys {
+            let key = (waiter.db, key.clone());
+            if let Some(queue) = self.waiters.get_mut(&key) {
+                queue.retain(|w| !Arc::ptr_eq(w, waiter));
+                if queue.is_empty() {
+                    self.waiters.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+// unregisters the client when the wait ends, including when the connection is dropped
+struct WaitGuard {
+    backend: Backend,
+    waiter: Arc<Waiter>,
+    receiver: oneshot::Receiver<Reply>,
+}
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        self.backend.lock_blocking().remove_waiter(&self.waiter);
+        lock(&self.waiter.reply).take();
+    }
+}
+
+enum Attempt {
+    Served(Served),
+    Blocked(WaitGuard),
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn end_name(end: ListEnd) -> Vec<u8> {
+    match end {
+        ListEnd::Left => b"left".to_vec(),
+        ListEnd::Right => b"right".to_vec(),
+    }
+}
+
+impl Backend {
+    pub(crate) fn lock_blocking(&self) -> std::sync::MutexGuard<'_, BlockingState> {
+        lock(&self.blocking)
+    }
+
+    /// Called when `key` may have received elements, clients blocked on it are served
+    /// by the next `serve_blocked`
+    pub(crate) fn signal_ready(&self, key: &[u8]) {
+        self.lock_blocking().mark_ready(self.db_index(), key);
+    }
+
+    /// Pop from the first non empty list of `keys` without blocking.
+    /// This is how blocking commands behave when replayed from the aof.
+    pub fn pop_first(
+        &self,
+        keys: &[Bytes],
+        op: &BlockingOp,
+    ) -> Result<Option<Served>, BackendError> {
+        let mut state = self.lock_blocking();
+        self.pop_first_locked(&mut state, keys, op, &mut Vec::new())
+    }
+
+    /// BLPOP / BRPOP / BLMOVE / BLMPOP: pop from the first non empty list of `keys`, or wait for
+    /// a push to one of them. Returns None once `timeout` is elapsed, a None timeout waits forever.
+    pub async fn blocking_pop(
+        &self,
+        keys: Vec<Bytes>,
+        op: BlockingOp,
+        timeout: Option<Duration>,
+    ) -> Result<Option<Served>, BackendError> {
+        // checking the lists and registering happen under the same lock as the serving,
+        // so a push in between can not be missed
+        let attempt = self.with_propagation(|propagated| {
+            let mut state = self.lock_blocking();
+            if let Some(served) = self.pop_first_locked(&mut state, &keys, &op, propagated)? {
+                return Ok(Attempt::Served(served));
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let waiter = Arc::new(Waiter {
+                db: self.db_index(),
+                keys,
+                op,
+                reply: Mutex::new(Some(sender)),
+            });
+            for key in &waiter.keys {
+                state
+                    .waiters
+                    .entry((waiter.db, key.clone()))
+                    .or_default()
+                    .push_back(waiter.clone());
+            }
+            Ok::<_, BackendError>(Attempt::Blocked(WaitGuard {
+                backend: self.clone(),
+                waiter,
+                receiver,
+            }))
+        })?;
+
+        let mut guard = match attempt {
+            Attempt::Served(served) => return Ok(Some(served)),
+            Attempt::Blocked(guard) => guard,
+        };
+        let reply = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, &mut guard.receiver).await,
+            None => Ok((&mut guard.receiver).await),
+        };
+        match reply {
+            Ok(Ok(reply)) => reply.map(Some),
+            Ok(Err(_)) => Ok(None),
+            Err(_) => {
+                // the client may have been served right before the timer fired
+                if lock(&guard.waiter.reply).take().is_some() {
+                    return Ok(None);
+                }
+                match guard.receiver.try_recv() {
+                    Ok(reply) => reply.map(Some),
+                    Err(_) => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Hand the elements pushed to ready keys to the clients blocked on them, the longest
+    /// waiting client first. Runs after every write command.
+    pub fn serve_blocked(&self) {
+        if self.lock_blocking().ready.is_empty() {
+            return;
+        }
+
+        self.with_propagation(|propagated| {
+            let mut state = self.lock_blocking();
+            while let Some((db, key)) = state.ready.pop_front() {
+                let backend = self.select(db);
+                while let Some(waiter) = state
+                    .waiters
+                    .get(&(db, key.clone()))
+                    .and_then(|queue| queue.front().cloned())
+                {
+                    let mut reply = lock(&waiter.reply);
+                    let Some(sender) = reply.take() else {
+                        // timed out or gone, it unregisters itself
+                        drop(reply);
+                        state.remove_waiter(&waiter);
+                        continue;
+                    };
+                    let ret = match backend.pop_locked(&mut state, &key, &waiter.op, propagated) {
+                        Ok(Some(values)) => Ok((key.clone(), values)),
+                        Ok(None) => {
+                            *reply = Some(sender);
+                            break;
+                        }
+                        Err(e) => Err(e),
+                    };
+                    let _ = sender.send(ret);
+                    drop(reply);
+                    state.remove_waiter(&waiter);
+                }
+            }
+        });
+    }
+
+    fn pop_first_locked(
+        &self,
+        state: &mut BlockingState,
+        keys: &[Bytes],
+        op: &BlockingOp,
+        propagated: &mut Vec<(usize, RespArray)>,
+    ) -> Result<Option<Served>, BackendError> {
+        for key in keys {
+            if let Some(values) = self.pop_locked(state, key, op, propagated)? {
+                return Ok(Some((key.clone(), values)));
+            }
+        }
+        Ok(None)
+    }
+
+    // pop for one client, `propagated` gets the commands replicating it in the aof
+    fn pop_locked(
+        &self,
+        state: &mut BlockingState,
+        key: &[u8],
+        op: &BlockingOp,
+        propagated: &mut Vec<(usize, RespArray)>,
+    ) -> Result<Option<Vec<RespFrame>>, BackendError> {
+        match op {
+            BlockingOp::Pop { end, count } => {
+                let Some(values) = self.list_pop(key, *end, *count)? else {
+                    return Ok(None);
+                };
+                let name = match end {
+                    ListEnd::Left => b"lpop".to_vec(),
+                    ListEnd::Right => b"rpop".to_vec(),
+                };
+                propagated.push((
+                    self.db_index(),
+                    aof_command(vec![
+                        name,
+                        key.to_vec(),
+                        values.len().to_string().into_bytes(),
+                    ]),
+                ));
+                Ok(Some(values))
+            }
+            BlockingOp::Move {
+                destination,
+                from,
+                to,
+            } => {
+                let Some(value) = self.move_element(key, destination, *from, *to)? else {
+                    return Ok(None);
+                };
+                state.mark_ready(self.db_index(), destination);
+                propagated.push((
+                    self.db_index(),
+                    aof_command(vec![
+                        b"lmove".to_vec(),
+                        key.to_vec(),
+                        destination.to_vec(),
+                        end_name(*from),
+                        end_name(*to),
+                    ]),
+                ));
+                Ok(Some(vec![value]))
+            }
+        }
+    }
+
+    fn with_propagation<T>(&self, f: impl FnOnce(&mut Vec<(usize, RespArray)>) -> T) -> T {
+        // not in the middle of an EXEC
+        let _shared = self.lock_shared();
+        match self.aof() {
+            Some(aof) => aof.propagate(f),
+            None => f(&mut Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn values(items: &[&str]) -> Vec<RespFrame> {
+        items
+            .iter()
+            .map(|v| BulkString::new(v.to_string()).into())
+            .collect()
+    }
+
+    fn keys(items: &[&str]) -> Vec<Bytes> {
+        items.iter().map(|v| Bytes::from(v.to_string())).collect()
+    }
+
+    const POP_LEFT: BlockingOp = BlockingOp::Pop {
+        end: ListEnd::Left,
+        count: 1,
+    };
+
+    #[tokio::test]
+    async fn test_blocking_pop_fifo() -> Result<(), BackendError> {
+        let backend = Backend::new();
+
+        let first = tokio::spawn({
+            let backend = backend.clone();
+            async move {
+                backend
+                    .blocking_pop(keys(&["a", "b"]), POP_LEFT, None)
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let second = tokio::spawn({
+            let backend = backend.clone();
+            async move { backend.blocking_pop(keys(&["b"]), POP_LEFT, None).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        backend.list_push(b"b", ListEnd::Right, values(&["1", "2"]), false)?;
+        backend.serve_blocked();
+
+        let first = first.await.unwrap()?;
+        let second = second.await.unwrap()?;
+        assert_eq!(first, Some((Bytes::from("b"), values(&["1"]))));
+        assert_eq!(second, Some((Bytes::from("b"), values(&["2"]))));
+        assert!(backend.lock_blocking().waiters.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocking_pop_timeout() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let ret = backend
+            .blocking_pop(keys(&["a"]), POP_LEFT, Some(Duration::from_millis(10)))
+            .await?;
+        assert_eq!(ret, None);
+        assert!(backend.lock_blocking().waiters.is_empty());
+
+        // the element is not lost to the timed out client
+        backend.list_push(b"a", ListEnd::Right, values(&["1"]), false)?;
+        backend.serve_blocked();
+        assert_eq!(backend.llen(b"a")?, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocking_move_chain() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let op = BlockingOp::Move {
+            destination: Bytes::from("b"),
+            from: ListEnd::Right,
+            to: ListEnd::Left,
+        };
+
+        let mover = tokio::spawn({
+            let backend = backend.clone();
+            async move { backend.blocking_pop(keys(&["a"]), op, None).await }
+        });
+        let popper = tokio::spawn({
+            let backend = backend.clone();
+            async move { backend.blocking_pop(keys(&["b"]), POP_LEFT, None).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        backend.list_push(b"a", ListEnd::Right, values(&["x"]), false)?;
+        backend.serve_blocked();
+        assert_eq!(
+            mover.await.unwrap()?,
+            Some((Bytes::from("a"), values(&["x"])))
+        );
+        assert_eq!(
+            popper.await.unwrap()?,
+            Some((Bytes::from("b"), values(&["x"])))
+        );
+        Ok(())
+    }
+}
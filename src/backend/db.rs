@@ -0,0 +1,213 @@
+use bytes::Bytes;
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+
+use crate::{Backend, Value};
+
+/// One of the numbered databases
+#[derive(Debug, Default)]
+pub struct Db {
+    pub(crate) keyspace: DashMap<Bytes, Value>,
+    // key -> absolute deadline in unix milliseconds
+    pub(crate) expires: DashMap<Bytes, i64>,
+}
+
+impl Backend {
+    /// The same backend with the database `index` selected, `index` has to be below
+    /// `databases`
+    pub fn select(&self, index: usize) -> Backend {
+        assert!(index < self.databases(), "DB index is out of range");
+        Backend {
+            inner: self.inner.clone(),
+            db: index,
+        }
+    }
+
+    /// The index of the selected database
+    pub fn db_index(&self) -> usize {
+        self.db
+    }
+
+    pub fn databases(&self) -> usize {
+        self.dbs.len()
+    }
+
+    // the keys of the selected database
+    pub(crate) fn db(&self) -> &Db {
+        let slot = self.read_slots()[self.db];
+        &self.dbs[slot]
+    }
+
+    fn read_slots(&self) -> RwLockReadGuard<'_, Vec<usize>> {
+        self.db_slots.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_slots(&self) -> RwLockWriteGuard<'_, Vec<usize>> {
+        self.db_slots.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// DBSIZE, keys that expired but were not evicted yet are counted
+    pub fn dbsize(&self) -> usize {
+        self.db().keyspace.len()
+    }
+
+    /// SWAPDB: the clients connected to one database see the data of the other right away
+    pub fn swapdb(&self, first: usize, second: usize) {
+        let (a, b) = (self.select(first), self.select(second));
+        // a watched key changes if it exists on either side
+        self.touch_watched(&[first, second], |key| {
+            a.contains_key(key) || b.contains_key(key)
+        });
+        self.write_slots().swap(first, second);
+        // clients blocked in one database may find their lists in the other now
+        for index in [first, second] {
+            self.lock_blocking().mark_db_ready(index);
+        }
+    }
+
+    /// MOVE, false when the key does not exist or `destination` holds it already.
+    /// The key keeps its time to live.
+    pub fn move_key(&self, key: &[u8], destination: usize) -> bool {
+        let target = self.select(destination);
+        if !self.contains_key(key) || target.contains_key(key) {
+            return false;
+        }
+        let Some((key, value)) = self.db().keyspace.remove(key) else {
+            return false;
+        };
+        let deadline = self.db().expires.remove(&key).map(|(_, deadline)| deadline);
+
+        // never hold a shard of both databases at once, a MOVE the other way could wait on it
+        match target.db().keyspace.entry(key.clone()) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                if let Some(deadline) = deadline {
+                    target.db().expires.insert(key.clone(), deadline);
+                }
+            }
+            Entry::Occupied(_) => {
+                // created in between, the key stays where it was
+                self.db().keyspace.insert(key.clone(), value);
+                if let Some(deadline) = deadline {
+                    self.db().expires.insert(key, deadline);
+                }
+                return false;
+            }
+        }
+        self.touch_key(&key);
+        target.touch_key(&key);
+        target.signal_ready(&key);
+        true
+    }
+
+    /// FLUSHDB, with `lazy` (ASYNC) the values are freed in the background
+    pub fn flushdb(&self, lazy: bool) {
+        self.touch_watched(&[self.db], |key| self.contains_key(key));
+        let db = self.db();
+        db.expires.clear();
+        if !lazy {
+            db.keyspace.clear();
+            return;
+        }
+        let keys = db
+            .keyspace
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        let values = keys
+            .iter()
+            .filter_map(|key| db.keyspace.remove(key).map(|(_, value)| value))
+            .collect();
+        self.free_lazily(values);
+    }
+
+    /// FLUSHALL, `lazy` like FLUSHDB
+    pub fn flushall(&self, lazy: bool) {
+        for index in 0..self.databases() {
+            self.select(index).flushdb(lazy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::now_millis;
+    use crate::{BulkString, ExpireCondition, KeyExpiry, StringValue, WatchedKeys};
+
+    fn value(v: &str) -> StringValue {
+        BulkString::new(v.to_string()).into()
+    }
+
+    #[test]
+    fn test_databases_are_separate() {
+        let backend = Backend::new();
+        let other = backend.select(1);
+        backend.set(b"key", value("0"));
+        assert!(!other.contains_key(b"key"));
+
+        other.set(b"key", value("1"));
+        assert_eq!(backend.get(b"key"), Ok(Some(value("0"))));
+        assert_eq!(other.get(b"key"), Ok(Some(value("1"))));
+        assert_eq!(backend.dbsize(), 1);
+        assert_eq!(backend.select(2).dbsize(), 0);
+    }
+
+    #[test]
+    fn test_move_key() {
+        let backend = Backend::new();
+        let other = backend.select(1);
+        let deadline = now_millis() + 60_000;
+        backend.set(b"key", value("v"));
+        backend.expire_at(b"key", deadline, ExpireCondition::Always);
+
+        assert!(backend.move_key(b"key", 1));
+        assert!(!backend.contains_key(b"key"));
+        assert_eq!(other.get(b"key"), Ok(Some(value("v"))));
+        assert_eq!(other.expiry(b"key"), KeyExpiry::At(deadline));
+
+        // the destination holds the key already
+        backend.set(b"key", value("again"));
+        assert!(!backend.move_key(b"key", 1));
+        assert_eq!(backend.get(b"key"), Ok(Some(value("again"))));
+        assert!(!backend.move_key(b"missing", 1));
+    }
+
+    #[test]
+    fn test_swapdb() {
+        let backend = Backend::new();
+        let other = backend.select(1);
+        backend.set(b"a", value("0"));
+        other.set(b"b", value("1"));
+
+        let mut watched = WatchedKeys::new(backend.clone());
+        watched.watch(0, Bytes::from("b"));
+        backend.swapdb(0, 1);
+        // the key appeared in the watched database
+        assert!(watched.is_dirty());
+        assert_eq!(backend.get(b"b"), Ok(Some(value("1"))));
+        assert_eq!(other.get(b"a"), Ok(Some(value("0"))));
+        assert!(!backend.contains_key(b"a"));
+    }
+
+    #[test]
+    fn test_flush() {
+        let backend = Backend::new();
+        let other = backend.select(1);
+        for i in 0..100 {
+            backend.set(format!("key{}", i).as_bytes(), value("v"));
+        }
+        other.set(b"key", value("v"));
+
+        backend.flushdb(true);
+        assert_eq!(backend.dbsize(), 0);
+        assert_eq!(other.dbsize(), 1);
+
+        backend.set(b"key", value("v"));
+        backend.flushall(false);
+        assert_eq!(backend.dbsize(), 0);
+        assert_eq!(other.dbsize(), 0);
+    }
+}
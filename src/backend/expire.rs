@@ -0,0 +1,343 @@
+use bytes::Bytes;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use dashmap::mapref::entry::Entry;
+use rand::Rng;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use crate::Backend;
+
+// same defaults as redis: run 10 times per second, sample 20 keys per loop and keep
+// looping while more than 25% of the sampled keys were expired
+const ACTIVE_EXPIRE_CYCLE_INTERVAL: Duration = Duration::from_millis(100);
+const ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP: usize = 20;
+const ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE: usize = 25;
+const ACTIVE_EXPIRE_CYCLE_TIME_LIMIT: Duration = Duration::from_millis(25);
+// how far into a shard a sample may start
+const ACTIVE_EXPIRE_CYCLE_MAX_SKIP: usize = 400;
+
+/// NX / XX / GT / LT modifiers of the EXPIRE command family
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpireCondition {
+    #[default]
+    Always,
+    Nx,
+    Xx,
+    Gt,
+    Lt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyExpiry {
+    NotFound,
+    Persistent,
+    /// absolute deadline in unix milliseconds
+    At(i64),
+}
+
+pub(crate) fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+impl Backend {
+    /// Set the deadline of `key`, returns false if the key does not exist or the condition is not met.
+    /// A deadline in the past deletes the key right away.
+    pub fn expire_at(&self, key: &[u8], deadline: i64, condition: ExpireCondition) -> bool {
+        self.expire_if_needed(key);
+        let db = self.db();
+        // the deadline is set under the lock of the key's shard, a DEL can not remove the key
+        // in between and leave the deadline behind
+        let Entry::Occupied(entry) = db.keyspace.entry(Bytes::copy_from_slice(key)) else {
+            return false;
+        };
+
+        let current = db.expires.get(key).map(|v| *v.value());
+        // a key without ttl is treated as an infinite ttl for GT and LT
+        let allowed = match condition {
+            ExpireCondition::Always => true,
+            ExpireCondition::Nx => current.is_none(),
+            ExpireCondition::Xx => current.is_some(),
+            ExpireCondition::Gt => current.is_some_and(|current| deadline > current),
+            ExpireCondition::Lt => current.is_none_or(|current| deadline < current),
+        };
+        if !allowed {
+            return false;
+        }
+
+        if deadline <= now_millis() {
+            entry.remove();
+            db.expires.remove(key);
+        } else {
+            db.expires.insert(Bytes::copy_from_slice(key), deadline);
+            drop(entry);
+        }
+        self.touch_key(key);
+        true
+    }
+
+    pub fn expiry(&self, key: &[u8]) -> KeyExpiry {
+        if !self.contains_key(key) {
+            return KeyExpiry::NotFound;
+        }
+
+        match self.db().expires.get(key) {
+            Some(deadline) => KeyExpiry::At(*deadline.value()),
+            None => KeyExpiry::Persistent,
+        }
+    }
+
+    pub fn persist(&self, key: &[u8]) -> bool {
+        let persisted = self.contains_key(key) && self.db().expires.remove(key).is_some();
+        if persisted {
+            self.touch_key(key);
+        }
+        persisted
+    }
+
+    /// Lazy expiration: drop the key if its deadline has passed, returns true if it was dropped.
+    pub(crate) fn expire_if_needed(&self, key: &[u8]) -> bool {
+        let db = self.db();
+        let Some(deadline) = db.expires.get(key).map(|deadline| *deadline.value()) else {
+            return false;
+        };
+        if deadline > now_millis() {
+            return false;
+        }
+        // the deadline is checked again under the lock of the key's shard, a SET or PERSIST that
+        // ran since it was read keeps the key
+        let entry = db.keyspace.entry(Bytes::copy_from_slice(key));
+        if db
+            .expires
+            .remove_if(key, |_, current| *current == deadline)
+            .is_none()
+        {
+            return false;
+        }
+        let Entry::Occupied(entry) = entry else {
+            return false;
+        };
+        entry.remove();
+        self.touch_key(key);
+        true
+    }
+
+    /// Spawn the background task that samples volatile keys and evicts the expired ones.
+    pub fn spawn_active_expire(&self) -> JoinHandle<()> {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ACTIVE_EXPIRE_CYCLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let expired = {
+                    let _shared = backend.lock_shared();
+                    backend.active_expire_cycle()
+                };
+                if expired > 0 {
+                    debug!("active expire cycle evicted {} keys", expired);
+                }
+            }
+        })
+    }
+
+    /// One cycle over every database, returns the number of keys evicted
+    pub(crate) fn active_expire_cycle(&self) -> usize {
+        let start = Instant::now();
+        (0..self.databases())
+            .map(|index| self.select(index).active_expire_db(start))
+            .sum()
+    }
+
+    // the cycle for the selected database, the time limit counts from `start`
+    fn active_expire_db(&self, start: Instant) -> usize {
+        let mut rng = rand::thread_rng();
+        let mut total = 0;
+
+        loop {
+            let now = now_millis();
+            let (sampled, candidates) =
+                self.sample_expired(&mut rng, ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP, now);
+            // no volatile keys
+            if sampled == 0 {
+                break;
+            }
+
+            let expired = candidates
+                .iter()
+                .filter(|key| self.expire_if_needed(key))
+                .count();
+            total += expired;
+
+            if expired * 100 <= sampled * ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE
+                || start.elapsed() > ACTIVE_EXPIRE_CYCLE_TIME_LIMIT
+            {
+                break;
+            }
+        }
+
+        total
+    }
+
+    // Sample up to `count` volatile keys, returns how many were sampled and those expired by
+    // `now`. Dashmap has no random access: the walk starts in a random shard, past a random
+    // number of its entries, and goes on through the next shards. It visits at most
+    // ACTIVE_EXPIRE_CYCLE_MAX_SKIP entries more than it samples, however many keys have a
+    // ttl, and locks one shard at a time.
+    fn sample_expired(&self, rng: &mut impl Rng, count: usize, now: i64) -> (usize, Vec<Bytes>) {
+        let shards = self.db().expires.shards();
+        let first = rng.gen_range(0..shards.len());
+        let len = shards[first].read().len();
+        let skip = rng.gen_range(0..=len.min(ACTIVE_EXPIRE_CYCLE_MAX_SKIP));
+        // (shard, entries to skip, entries to take), the skipped entries come last
+        let walk = std::iter::once((first, skip, usize::MAX))
+            .chain((first + 1..shards.len()).map(|index| (index, 0, usize::MAX)))
+            .chain((0..first).map(|index| (index, 0, usize::MAX)))
+            .chain(std::iter::once((first, 0, skip)));
+
+        let (mut sampled, mut expired) = (0, Vec::new());
+        for (index, skip, take) in walk {
+            let shard = shards[index].read();
+            for (key, deadline) in shard.iter().skip(skip).take(take.min(count - sampled)) {
+                sampled += 1;
+                if *deadline.get() <= now {
+                    expired.push(key.clone());
+                }
+            }
+            if sampled == count {
+                break;
+            }
+        }
+        (sampled, expired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, StringValue};
+
+    use super::*;
+
+    fn value() -> StringValue {
+        BulkString::new("value").into()
+    }
+
+    #[test]
+    fn test_lazy_expire_on_get() {
+        let backend = Backend::new();
+        backend.set(b"key", value());
+        backend
+            .hset(Bytes::from("hash"), Bytes::from("field"), value().into())
+            .unwrap();
+
+        assert!(backend.expire_at(b"key", now_millis() + 60_000, ExpireCondition::Always));
+        assert_eq!(backend.get(b"key"), Ok(Some(value())));
+
+        backend
+            .db()
+            .expires
+            .insert(Bytes::from("key"), now_millis() - 1);
+        backend
+            .db()
+            .expires
+            .insert(Bytes::from("hash"), now_millis() - 1);
+        assert_eq!(backend.get(b"key"), Ok(None));
+        assert_eq!(backend.hget(b"hash", b"field"), Ok(None));
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::NotFound);
+    }
+
+    // a deadline left behind by a key that went away does not apply to a new key
+    #[test]
+    fn test_new_key_has_no_ttl() {
+        let backend = Backend::new();
+        backend
+            .db()
+            .expires
+            .insert(Bytes::from("hash"), now_millis() + 60_000);
+        backend
+            .hset(Bytes::from("hash"), Bytes::from("field"), value().into())
+            .unwrap();
+        assert_eq!(backend.expiry(b"hash"), KeyExpiry::Persistent);
+
+        backend
+            .db()
+            .expires
+            .insert(Bytes::from("counter"), now_millis() + 60_000);
+        backend.incr_by(b"counter", 1).unwrap();
+        assert_eq!(backend.expiry(b"counter"), KeyExpiry::Persistent);
+
+        // and a key emptied by a command takes its deadline with it
+        backend.sadd(b"set", vec![b"a".to_vec()]).unwrap();
+        assert!(backend.expire_at(b"set", now_millis() + 60_000, ExpireCondition::Always));
+        backend.srem(b"set", &[b"a".to_vec()]).unwrap();
+        assert!(!backend.db().expires.contains_key(&b"set"[..]));
+        assert!(!backend.expire_at(b"set", now_millis() + 60_000, ExpireCondition::Always));
+    }
+
+    #[test]
+    fn test_expire_conditions() {
+        let backend = Backend::new();
+        backend.set(b"key", value());
+        let deadline = now_millis() + 60_000;
+
+        assert!(!backend.expire_at(b"key", deadline, ExpireCondition::Xx));
+        assert!(!backend.expire_at(b"key", deadline, ExpireCondition::Gt));
+        assert!(backend.expire_at(b"key", deadline, ExpireCondition::Nx));
+        assert!(!backend.expire_at(b"key", deadline + 1, ExpireCondition::Nx));
+        assert!(!backend.expire_at(b"key", deadline - 1, ExpireCondition::Gt));
+        assert!(backend.expire_at(b"key", deadline - 1, ExpireCondition::Lt));
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::At(deadline - 1));
+
+        assert!(backend.persist(b"key"));
+        assert!(!backend.persist(b"key"));
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::Persistent);
+
+        assert!(backend.expire_at(b"key", now_millis() - 1, ExpireCondition::Always));
+        assert!(!backend.contains_key(b"key"));
+    }
+
+    #[test]
+    fn test_sample_expired() {
+        let backend = Backend::new();
+        let now = now_millis();
+        let mut rng = rand::thread_rng();
+        assert_eq!(backend.sample_expired(&mut rng, 20, now), (0, vec![]));
+
+        backend.set(b"expired", value());
+        backend.db().expires.insert(Bytes::from("expired"), now - 1);
+        for i in 0..1000 {
+            let key = Bytes::from(format!("key:{}", i));
+            backend.set(&key, value());
+            backend.db().expires.insert(key, now + 60_000);
+        }
+        let (sampled, expired) = backend.sample_expired(&mut rng, 20, now);
+        assert_eq!(sampled, 20);
+        assert!(expired.len() <= 1);
+
+        // a sample as large as the keys with a ttl sees them all, wherever it starts
+        for _ in 0..20 {
+            let (sampled, expired) = backend.sample_expired(&mut rng, 1001, now);
+            assert_eq!((sampled, expired), (1001, vec![Bytes::from("expired")]));
+        }
+    }
+
+    #[test]
+    fn test_active_expire_cycle() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            let key = Bytes::from(format!("key:{}", i));
+            backend.set(&key, value());
+            backend.db().expires.insert(key, now_millis() - 1);
+        }
+        backend.set(b"alive", value());
+        backend.expire_at(b"alive", now_millis() + 60_000, ExpireCondition::Always);
+
+        assert!(backend.active_expire_cycle() > 0);
+        while backend.active_expire_cycle() > 0 {}
+
+        assert_eq!(backend.db().keyspace.len(), 1);
+        assert_eq!(backend.db().expires.len(), 1);
+    }
+}
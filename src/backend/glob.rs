@@ -0,0 +1,132 @@
+/// Redis style glob matching: `*`, `?`, `[abc]`, `[^a-z]` and `\` to escape
+pub(crate) fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // the token after the last star and where its match starts: a mismatch past it lets the
+    // star take one more byte instead of retrying every earlier star, so matching is linear
+    // in the pattern times the string
+    let mut star = None;
+    while s < string.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            star = Some((p, s));
+            continue;
+        }
+        match match_one(pattern, p, string[s]) {
+            Some(next) => {
+                p = next;
+                s += 1;
+            }
+            None => match star {
+                Some((after, start)) => {
+                    star = Some((after, start + 1));
+                    p = after;
+                    s = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+// Match the byte `c` against the token of `pattern` at `p`, returns where the next token
+// starts
+fn match_one(pattern: &[u8], mut p: usize, c: u8) -> Option<usize> {
+    let matched = match *pattern.get(p)? {
+        b'?' => true,
+        b'[' => {
+            p += 1;
+            let negate = pattern.get(p) == Some(&b'^');
+            if negate {
+                p += 1;
+            }
+            let mut matched = false;
+            loop {
+                match pattern.get(p) {
+                    // an unterminated class ends with the pattern
+                    None => {
+                        p -= 1;
+                        break;
+                    }
+                    Some(b']') => break,
+                    Some(b'\\') if p + 1 < pattern.len() => {
+                        p += 1;
+                        matched |= pattern[p] == c;
+                    }
+                    Some(&start) if pattern.get(p + 1) == Some(&b'-') && p + 2 < pattern.len() => {
+                        let end = pattern[p + 2];
+                        let (low, high) = if start <= end {
+                            (start, end)
+                        } else {
+                            (end, start)
+                        };
+                        matched |= (low..=high).contains(&c);
+                        p += 2;
+                    }
+                    Some(&v) => matched |= v == c,
+                }
+                p += 1;
+            }
+            matched != negate
+        }
+        b'\\' if p + 1 < pattern.len() => {
+            p += 1;
+            pattern[p] == c
+        }
+        v => v == c,
+    };
+    matched.then_some(p + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let cases: &[(&str, &str, bool)] = &[
+            ("*", "", true),
+            ("h?llo", "hello", true),
+            ("h?llo", "hllo", false),
+            ("h*llo", "heeeello", true),
+            ("h*llo", "hellox", false),
+            ("news.*", "news.tech", true),
+            ("h[ae]llo", "hallo", true),
+            ("h[ae]llo", "hillo", false),
+            ("h[^e]llo", "hallo", true),
+            ("h[^e]llo", "hello", false),
+            ("h[a-b]llo", "hbllo", true),
+            ("h[b-a]llo", "hbllo", true),
+            ("h\\*llo", "h*llo", true),
+            ("h\\*llo", "hello", false),
+            ("a*b*c", "aXXbYYc", true),
+            ("a*b*c", "aXXbYY", false),
+            ("\\?", "?", true),
+            ("\\?", "a", false),
+            ("[a\\-z]", "-", true),
+            ("[a\\-z]", "b", false),
+            ("key:[0-9]*", "key:7x", true),
+            ("*a*", "bab", true),
+            ("a*", "", false),
+            ("h[a", "ha", true),
+            ("**", "", true),
+        ];
+        for (pattern, string, expected) in cases {
+            assert_eq!(
+                glob_match(pattern.as_bytes(), string.as_bytes()),
+                *expected,
+                "{} {}",
+                pattern,
+                string
+            );
+        }
+    }
+
+    // each star used to retry the rest of the pattern at every position
+    #[test]
+    fn test_glob_match_stars() {
+        let string = "a".repeat(10_000);
+        assert!(!glob_match(b"*a*a*a*a*a*a*a*a*b", string.as_bytes()));
+        assert!(glob_match(b"*a*a*a*a*a*a*a*a*", string.as_bytes()));
+    }
+}
@@ -0,0 +1,302 @@
+use bytes::Bytes;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::thread;
+
+use dashmap::mapref::entry::Entry;
+use dashmap::mapref::one::MappedRef;
+use hashbrown::{HashMap, HashSet};
+use tracing::warn;
+
+use crate::{Backend, BackendError, BulkString, RespFrame, SortedSet, StringValue};
+
+// same as redis: values with more elements than this are freed in the background by UNLINK
+const LAZYFREE_THRESHOLD: usize = 64;
+// same as redis: arguments at least this big are stored as they were read instead of copied
+const PROTO_MBULK_BIG_ARG: usize = 32 * 1024;
+
+// hashbrown tables with the std hasher, HSCAN and SSCAN walk their buckets
+pub type HashValue = HashMap<Bytes, RespFrame, RandomState>;
+pub type ListValue = VecDeque<RespFrame>;
+pub type SetValue = HashSet<Vec<u8>, RandomState>;
+
+/// Decoded arguments are slices of the connection's read buffer, copy the small ones before
+/// storing them so a stored value does not keep the whole buffer alive
+pub(crate) fn compact(bytes: Bytes) -> Bytes {
+    if bytes.len() < PROTO_MBULK_BIG_ARG {
+        Bytes::copy_from_slice(&bytes)
+    } else {
+        bytes
+    }
+}
+
+/// Compact the payload of a bulk string before it is stored in a hash or list
+pub(crate) fn compact_frame(frame: RespFrame) -> RespFrame {
+    match frame {
+        RespFrame::BulkString(BulkString(bytes, null)) => {
+            RespFrame::BulkString(BulkString(compact(bytes), null))
+        }
+        frame => frame,
+    }
+}
+
+/// What a key holds
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(StringValue),
+    Hash(HashValue),
+    List(ListValue),
+    Set(SetValue),
+    ZSet(SortedSet),
+}
+
+impl Value {
+    /// The name TYPE replies with
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::ZSet(_) => "zset",
+        }
+    }
+
+    // the number of allocations freeing the value takes, roughly
+    fn free_effort(&self) -> usize {
+        match self {
+            Value::String(_) => 1,
+            Value::Hash(hash) => hash.len(),
+            Value::List(list) => list.len(),
+            Value::Set(set) => set.len(),
+            Value::ZSet(zset) => zset.len(),
+        }
+    }
+}
+
+/// The aggregate types a key may hold, a key goes away with the last element of its value
+pub(crate) trait ValueType: Default {
+    fn from_value(value: &Value) -> Option<&Self>;
+    fn from_value_mut(value: &mut Value) -> Option<&mut Self>;
+    fn into_value(self) -> Value;
+    fn is_empty(&self) -> bool;
+}
+
+impl ValueType for HashValue {
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Hash(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::Hash(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Hash(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        HashValue::is_empty(self)
+    }
+}
+
+impl ValueType for ListValue {
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::List(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        ListValue::is_empty(self)
+    }
+}
+
+impl ValueType for SetValue {
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::Set(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::Set(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::Set(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        SetValue::is_empty(self)
+    }
+}
+
+impl ValueType for SortedSet {
+    fn from_value(value: &Value) -> Option<&Self> {
+        match value {
+            Value::ZSet(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn from_value_mut(value: &mut Value) -> Option<&mut Self> {
+        match value {
+            Value::ZSet(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        Value::ZSet(self)
+    }
+
+    fn is_empty(&self) -> bool {
+        SortedSet::is_empty(self)
+    }
+}
+
+impl Backend {
+    /// The value of `key` if it is a `T`, the shard of the key stays read locked while it is held
+    pub(crate) fn read<T: ValueType>(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<MappedRef<'_, Bytes, Value, T>>, BackendError> {
+        self.expire_if_needed(key);
+        match self.db().keyspace.get(key) {
+            Some(value) => value
+                .try_map(T::from_value)
+                .map(Some)
+                .map_err(|_| BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    /// Run `f` on the value of `key` if it is a `T`, creating an empty one first when `create`
+    /// is set. Returns None when the key does not exist and was not created. An empty value is
+    /// removed afterwards.
+    pub(crate) fn update<T: ValueType, R>(
+        &self,
+        key: &[u8],
+        create: bool,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Result<Option<R>, BackendError> {
+        self.expire_if_needed(key);
+        match self.db().keyspace.entry(Bytes::copy_from_slice(key)) {
+            Entry::Occupied(mut entry) => {
+                let value = T::from_value_mut(entry.get_mut()).ok_or(BackendError::WrongType)?;
+                let ret = f(value);
+                if value.is_empty() {
+                    entry.remove();
+                    self.db().expires.remove(key);
+                }
+                Ok(Some(ret))
+            }
+            Entry::Vacant(_) if !create => Ok(None),
+            Entry::Vacant(entry) => {
+                let mut value = T::default();
+                let ret = f(&mut value);
+                if !value.is_empty() {
+                    // a new key starts without a time to live, whatever deadline a key of
+                    // the same name left behind
+                    entry.insert(value.into_value());
+                    self.db().expires.remove(key);
+                }
+                Ok(Some(ret))
+            }
+        }
+    }
+
+    /// Err(WrongType) when `key` holds something else than a `T`
+    pub(crate) fn check_type<T: ValueType>(&self, key: &[u8]) -> Result<(), BackendError> {
+        self.read::<T>(key).map(|_| ())
+    }
+
+    /// Replace whatever `key` holds with `value`, the time to live is left alone
+    pub(crate) fn insert_value(&self, key: &[u8], value: Value) {
+        self.db()
+            .keyspace
+            .insert(Bytes::copy_from_slice(key), value);
+        self.touch_key(key);
+    }
+
+    /// TYPE, None when the key does not exist
+    pub fn key_type(&self, key: &[u8]) -> Option<&'static str> {
+        self.expire_if_needed(key);
+        self.db().keyspace.get(key).map(|value| value.type_name())
+    }
+
+    /// DEL, returns the number of keys removed
+    pub fn del(&self, keys: &[Bytes]) -> usize {
+        keys.iter()
+            .filter(|key| {
+                self.expire_if_needed(key);
+                self.remove_key(key)
+            })
+            .count()
+    }
+
+    /// UNLINK: the keys go away right away like with DEL, large values are freed by a
+    /// background thread
+    pub fn unlink(&self, keys: &[Bytes]) -> usize {
+        let mut removed = 0;
+        for key in keys {
+            self.expire_if_needed(key);
+            let db = self.db();
+            let Some((_, value)) = db.keyspace.remove(&key[..]) else {
+                continue;
+            };
+            db.expires.remove(&key[..]);
+            self.touch_key(key);
+            removed += 1;
+            if value.free_effort() > LAZYFREE_THRESHOLD {
+                self.free_lazily(vec![value]);
+            }
+        }
+        removed
+    }
+
+    /// EXISTS and TOUCH, a key given twice is counted twice
+    pub fn count_existing(&self, keys: &[Bytes]) -> usize {
+        keys.iter().filter(|key| self.contains_key(key)).count()
+    }
+
+    /// Drop `values` on the lazyfree thread
+    pub(crate) fn free_lazily(&self, values: Vec<Value>) {
+        let sender = self.lazyfree.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Vec<Value>>();
+            let spawned = thread::Builder::new()
+                .name("lazyfree".to_string())
+                .spawn(move || receiver.into_iter().for_each(drop));
+            if let Err(e) = spawned {
+                warn!("spawn lazyfree thread error: {}", e);
+            }
+            sender
+        });
+        // without the thread the values are simply dropped here
+        let _ = sender.send(values);
+    }
+}
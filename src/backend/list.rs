@@ -0,0 +1,464 @@
+use crate::{compact_frame, Backend, BackendError, ListValue, RespFrame};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+/// Options of LPOS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListPosition {
+    pub rank: i64,
+    pub count: Option<usize>,
+    pub maxlen: usize,
+}
+
+impl Default for ListPosition {
+    fn default() -> Self {
+        ListPosition {
+            rank: 1,
+            count: None,
+            maxlen: 0,
+        }
+    }
+}
+
+// redis style range: negative indexes count from the tail, out of range indexes are clamped
+fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        stop + len
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize))
+    }
+}
+
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { index + len as i64 } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+impl Backend {
+    /// LPUSH / RPUSH, returns the length of the list after the push.
+    /// With `only_if_exists` (LPUSHX / RPUSHX) nothing is pushed to a missing key.
+    pub fn list_push(
+        &self,
+        key: &[u8],
+        end: ListEnd,
+        values: Vec<RespFrame>,
+        only_if_exists: bool,
+    ) -> Result<usize, BackendError> {
+        let values = values.into_iter().map(compact_frame).collect();
+        let len = self.push_values(key, end, values, only_if_exists)?;
+        if len > 0 {
+            self.signal_ready(key);
+        }
+        Ok(len)
+    }
+
+    // push without waking up blocked clients
+    fn push_values(
+        &self,
+        key: &[u8],
+        end: ListEnd,
+        values: Vec<RespFrame>,
+        only_if_exists: bool,
+    ) -> Result<usize, BackendError> {
+        let len = self.update::<ListValue, _>(key, !only_if_exists, |list| {
+            for value in values {
+                match end {
+                    ListEnd::Left => list.push_front(value),
+                    ListEnd::Right => list.push_back(value),
+                }
+            }
+            list.len()
+        })?;
+        let Some(len) = len else {
+            return Ok(0);
+        };
+        self.touch_key(key);
+        Ok(len)
+    }
+
+    /// LPOP / RPOP, returns None if the key does not exist
+    pub fn list_pop(
+        &self,
+        key: &[u8],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<RespFrame>>, BackendError> {
+        let values = self.update::<ListValue, _>(key, false, |list| {
+            let count = count.min(list.len());
+            match end {
+                ListEnd::Left => list.drain(..count).collect::<Vec<_>>(),
+                ListEnd::Right => (0..count).filter_map(|_| list.pop_back()).collect(),
+            }
+        })?;
+        if values.as_ref().is_some_and(|values| !values.is_empty()) {
+            self.touch_key(key);
+        }
+        Ok(values)
+    }
+
+    pub fn llen(&self, key: &[u8]) -> Result<usize, BackendError> {
+        Ok(self
+            .read::<ListValue>(key)?
+            .map(|list| list.len())
+            .unwrap_or(0))
+    }
+
+    pub fn lrange(
+        &self,
+        key: &[u8],
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<RespFrame>, BackendError> {
+        let Some(list) = self.read::<ListValue>(key)? else {
+            return Ok(vec![]);
+        };
+        Ok(match normalize_range(start, stop, list.len()) {
+            Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+            None => vec![],
+        })
+    }
+
+    pub fn lindex(&self, key: &[u8], index: i64) -> Result<Option<RespFrame>, BackendError> {
+        Ok(self.read::<ListValue>(key)?.and_then(|list| {
+            normalize_index(index, list.len()).and_then(|index| list.get(index).cloned())
+        }))
+    }
+
+    pub fn lset(&self, key: &[u8], index: i64, value: RespFrame) -> Result<(), BackendError> {
+        self.update::<ListValue, _>(key, false, |list| {
+            let index = normalize_index(index, list.len()).ok_or(BackendError::OutOfRange)?;
+            list[index] = compact_frame(value);
+            Ok(())
+        })?
+        .ok_or(BackendError::NoSuchKey)??;
+        self.touch_key(key);
+        Ok(())
+    }
+
+    /// LREM: a positive count removes from the head, a negative one from the tail, zero removes all
+    pub fn lrem(&self, key: &[u8], count: i64, value: &RespFrame) -> Result<usize, BackendError> {
+        let limit = if count == 0 {
+            usize::MAX
+        } else {
+            count.unsigned_abs() as usize
+        };
+        let removed = self.update::<ListValue, _>(key, false, |list| {
+            let mut removed = 0;
+            if count >= 0 {
+                list.retain(|v| {
+                    let matched = removed < limit && v == value;
+                    removed += matched as usize;
+                    !matched
+                });
+            } else {
+                let mut i = list.len();
+                while i > 0 && removed < limit {
+                    i -= 1;
+                    if &list[i] == value {
+                        list.remove(i);
+                        removed += 1;
+                    }
+                }
+            }
+            removed
+        })?;
+
+        let removed = removed.unwrap_or(0);
+        if removed > 0 {
+            self.touch_key(key);
+        }
+        Ok(removed)
+    }
+
+    pub fn ltrim(&self, key: &[u8], start: i64, stop: i64) -> Result<(), BackendError> {
+        let trimmed = self.update::<ListValue, _>(key, false, |list| {
+            match normalize_range(start, stop, list.len()) {
+                Some((start, stop)) => {
+                    list.truncate(stop + 1);
+                    list.drain(..start);
+                }
+                None => list.clear(),
+            }
+        })?;
+        if trimmed.is_some() {
+            self.touch_key(key);
+        }
+        Ok(())
+    }
+
+    /// LINSERT, returns the new length, -1 when the pivot is not found and 0 for a missing key
+    pub fn linsert(
+        &self,
+        key: &[u8],
+        before: bool,
+        pivot: &RespFrame,
+        value: RespFrame,
+    ) -> Result<i64, BackendError> {
+        let len = self.update::<ListValue, _>(key, false, |list| {
+            let index = list.iter().position(|v| v == pivot)?;
+            list.insert(if before { index } else { index + 1 }, compact_frame(value));
+            Some(list.len())
+        })?;
+        match len {
+            None => Ok(0),
+            Some(None) => Ok(-1),
+            Some(Some(len)) => {
+                self.touch_key(key);
+                Ok(len as i64)
+            }
+        }
+    }
+
+    pub fn lpos(
+        &self,
+        key: &[u8],
+        value: &RespFrame,
+        position: ListPosition,
+    ) -> Result<Vec<usize>, BackendError> {
+        let Some(list) = self.read::<ListValue>(key)? else {
+            return Ok(vec![]);
+        };
+        let maxlen = if position.maxlen == 0 {
+            list.len()
+        } else {
+            position.maxlen.min(list.len())
+        };
+        let count = match position.count {
+            Some(0) => usize::MAX,
+            Some(count) => count,
+            None => 1,
+        };
+        // skip the first rank - 1 matches
+        let skip = (position.rank.unsigned_abs() - 1) as usize;
+
+        let matches = |(_, v): &(usize, &RespFrame)| *v == value;
+        let ret = if position.rank > 0 {
+            list.iter()
+                .enumerate()
+                .take(maxlen)
+                .filter(matches)
+                .skip(skip)
+                .take(count)
+                .map(|(i, _)| i)
+                .collect()
+        } else {
+            list.iter()
+                .enumerate()
+                .rev()
+                .take(maxlen)
+                .filter(matches)
+                .skip(skip)
+                .take(count)
+                .map(|(i, _)| i)
+                .collect()
+        };
+        Ok(ret)
+    }
+
+    /// LMOVE: pop an element from `source` and push it to `destination`
+    pub fn lmove(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<RespFrame>, BackendError> {
+        let value = self.move_element(source, destination, from, to)?;
+        if value.is_some() {
+            self.signal_ready(destination);
+        }
+        Ok(value)
+    }
+
+    // move without waking up the clients blocked on `destination`
+    pub(crate) fn move_element(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<RespFrame>, BackendError> {
+        self.check_type::<ListValue>(source)?;
+        self.check_type::<ListValue>(destination)?;
+
+        let Some(mut values) = self.list_pop(source, from, 1)? else {
+            return Ok(None);
+        };
+        let Some(value) = values.pop() else {
+            return Ok(None);
+        };
+        self.push_values(destination, to, vec![value.clone()], false)?;
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::BulkString;
+
+    use super::*;
+
+    fn values(items: &[&str]) -> Vec<RespFrame> {
+        items
+            .iter()
+            .map(|v| BulkString::new(v.to_string()).into())
+            .collect()
+    }
+
+    #[test]
+    fn test_list_push_pop_range() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.list_push(b"list", ListEnd::Right, values(&["a", "b", "c"]), false)?,
+            3
+        );
+        assert_eq!(
+            backend.list_push(b"list", ListEnd::Left, values(&["z"]), false)?,
+            4
+        );
+        assert_eq!(
+            backend.list_push(b"missing", ListEnd::Left, values(&["z"]), true)?,
+            0
+        );
+
+        assert_eq!(
+            backend.lrange(b"list", 0, -1)?,
+            values(&["z", "a", "b", "c"])
+        );
+        assert_eq!(backend.lrange(b"list", -2, 100)?, values(&["b", "c"]));
+        assert_eq!(backend.lrange(b"list", 3, 1)?, values(&[]));
+
+        assert_eq!(
+            backend.list_pop(b"list", ListEnd::Right, 2)?,
+            Some(values(&["c", "b"]))
+        );
+        assert_eq!(
+            backend.list_pop(b"list", ListEnd::Left, 5)?,
+            Some(values(&["z", "a"]))
+        );
+        // the key is removed with its last element
+        assert_eq!(backend.list_pop(b"list", ListEnd::Left, 1)?, None);
+        assert!(!backend.contains_key(b"list"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_copies_small_values() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        // small and big elements as the decoder leaves them, slices of one read buffer
+        let read = Bytes::from(vec![b'x'; 64 * 1024]);
+        let small = read.slice(0..5);
+        let big = read.slice(..);
+        backend.list_push(
+            b"list",
+            ListEnd::Right,
+            vec![
+                BulkString(small, false).into(),
+                BulkString(big, false).into(),
+            ],
+            false,
+        )?;
+
+        let stored = backend.lrange(b"list", 0, -1)?;
+        let [RespFrame::BulkString(small), RespFrame::BulkString(big)] = &stored[..] else {
+            panic!("unexpected list {:?}", stored);
+        };
+        assert!(!read.as_ptr_range().contains(&small.as_ptr()));
+        assert_eq!(big.as_ptr(), read.as_ptr());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_edit() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.list_push(
+            b"list",
+            ListEnd::Right,
+            values(&["a", "b", "a", "c", "a"]),
+            false,
+        )?;
+
+        assert_eq!(backend.lrem(b"list", -2, &BulkString::new("a").into())?, 2);
+        assert_eq!(backend.lrange(b"list", 0, -1)?, values(&["a", "b", "c"]));
+
+        let pivot = BulkString::new("b").into();
+        assert_eq!(
+            backend.linsert(b"list", false, &pivot, BulkString::new("x").into())?,
+            4
+        );
+        backend.lset(b"list", -1, BulkString::new("y").into())?;
+        assert_eq!(
+            backend.lrange(b"list", 0, -1)?,
+            values(&["a", "b", "x", "y"])
+        );
+        assert_eq!(
+            backend.lset(b"list", 10, BulkString::new("y").into()),
+            Err(BackendError::OutOfRange)
+        );
+
+        backend.ltrim(b"list", 1, -2)?;
+        assert_eq!(backend.lrange(b"list", 0, -1)?, values(&["b", "x"]));
+        assert_eq!(
+            backend.lindex(b"list", -1)?,
+            Some(BulkString::new("x").into())
+        );
+
+        backend.set(b"string", BulkString::new("v").into());
+        assert_eq!(backend.llen(b"string"), Err(BackendError::WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_lpos_lmove() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.list_push(
+            b"list",
+            ListEnd::Right,
+            values(&["a", "b", "c", "1", "2", "3", "c", "c"]),
+            false,
+        )?;
+        let c = BulkString::new("c").into();
+
+        assert_eq!(backend.lpos(b"list", &c, ListPosition::default())?, vec![2]);
+        let position = ListPosition {
+            rank: -1,
+            count: Some(2),
+            maxlen: 0,
+        };
+        assert_eq!(backend.lpos(b"list", &c, position)?, vec![7, 6]);
+        let position = ListPosition {
+            rank: 2,
+            count: Some(0),
+            maxlen: 0,
+        };
+        assert_eq!(backend.lpos(b"list", &c, position)?, vec![6, 7]);
+        let position = ListPosition {
+            rank: 1,
+            count: Some(0),
+            maxlen: 3,
+        };
+        assert_eq!(backend.lpos(b"list", &c, position)?, vec![2]);
+
+        let ret = backend.lmove(b"list", b"other", ListEnd::Left, ListEnd::Right)?;
+        assert_eq!(ret, Some(BulkString::new("a").into()));
+        assert_eq!(backend.lrange(b"other", 0, -1)?, values(&["a"]));
+        Ok(())
+    }
+}
@@ -1,25 +1,116 @@
+use bytes::Bytes;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::{mpsc, Arc, Mutex, OnceLock, RwLock};
 
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use thiserror::Error;
 
-use crate::RespFrame;
+use crate::{Aof, AofError, Config, RespFrame, SimpleError};
 
+pub use blocking::*;
+pub use db::*;
+pub use expire::*;
+pub use keyspace::*;
+pub use list::*;
+pub use pubsub::*;
+pub use scan::*;
+pub use set::*;
+pub use skiplist::{LexBound, ScoreBound};
+pub use string::*;
+pub use watch::*;
+pub use zset::*;
+
+mod blocking;
+mod db;
+mod expire;
+mod glob;
+mod keyspace;
+mod list;
+mod pubsub;
+mod scan;
+mod set;
+mod skiplist;
+mod string;
+mod watch;
+mod zset;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BackendError {
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("ERR index out of range")]
+    OutOfRange,
+    #[error("ERR resulting score is not a number (NaN)")]
+    NotANumber,
+    #[error("ERR value is not an integer or out of range")]
+    NotAnInteger,
+    #[error("ERR value is not a valid float")]
+    NotAFloat,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NotFinite,
+    #[error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")]
+    StringTooLong,
+    #[error("ERR Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len")]
+    LcsTooLong,
+}
+
+impl From<BackendError> for RespFrame {
+    fn from(value: BackendError) -> Self {
+        SimpleError::new(value.to_string()).into()
+    }
+}
+
+/// NX / XX modifiers of the SET command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetCondition {
+    #[default]
+    Always,
+    Nx,
+    Xx,
+}
+
+/// What happens to the time to live of a key overwritten by SET
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetExpiry {
+    #[default]
+    Clear,
+    Keep,
+    /// absolute deadline in unix milliseconds
+    At(i64),
+}
+
+/// The shared server state, seen through the database a connection selected
 #[derive(Debug, Clone)]
-pub struct Backend(Arc<BackendInner>);
+pub struct Backend {
+    inner: Arc<BackendInner>,
+    db: usize,
+}
 
 #[derive(Debug)]
 pub struct BackendInner {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
+    pub(crate) dbs: Vec<Db>,
+    // database index -> position in `dbs`, SWAPDB exchanges two of them
+    pub(crate) db_slots: RwLock<Vec<usize>>,
+    pub(crate) blocking: Mutex<BlockingState>,
+    pub(crate) pubsub: Mutex<PubSubState>,
+    pub(crate) exec_lock: RwLock<()>,
+    pub(crate) watched: DashMap<(usize, Bytes), WatchedKey>,
+    pub(crate) config: Config,
+    pub(crate) rdb_saving: AtomicBool,
+    pub(crate) aof: OnceLock<Aof>,
+    // values UNLINK leaves to the background thread
+    pub(crate) lazyfree: OnceLock<mpsc::Sender<Vec<Value>>>,
 }
 
 impl Default for Backend {
     fn default() -> Self {
-        Backend(Arc::new(BackendInner {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
-        }))
+        Backend::with_config(Config::default())
     }
 }
 
@@ -27,7 +118,7 @@ impl Deref for Backend {
     type Target = BackendInner;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
     }
 }
 
@@ -36,24 +127,138 @@ impl Backend {
         Self::default()
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    pub fn with_config(config: Config) -> Self {
+        let databases = config.databases.max(1);
+        let inner = BackendInner {
+            dbs: (0..databases).map(|_| Db::default()).collect(),
+            db_slots: RwLock::new((0..databases).collect()),
+            blocking: Mutex::new(BlockingState::default()),
+            pubsub: Mutex::new(PubSubState::default()),
+            exec_lock: RwLock::new(()),
+            watched: DashMap::new(),
+            config,
+            rdb_saving: AtomicBool::new(false),
+            aof: OnceLock::new(),
+            lazyfree: OnceLock::new(),
+        };
+        Backend {
+            inner: Arc::new(inner),
+            db: 0,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Start appending write commands to the append only file
+    pub fn enable_aof(&self) -> Result<&Aof, AofError> {
+        if let Some(aof) = self.aof.get() {
+            return Ok(aof);
+        }
+        let aof = Aof::open(&self.config.aof_path(), self.config.appendfsync)?;
+        Ok(self.aof.get_or_init(|| aof))
+    }
+
+    pub fn aof(&self) -> Option<&Aof> {
+        self.aof.get()
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<StringValue>, BackendError> {
+        self.expire_if_needed(key);
+        match self.db().keyspace.get(key).as_deref() {
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set(&self, key: &[u8], value: StringValue) {
+        // a plain SET discards any previous time to live, and a value of another type
+        self.insert_value(key, Value::String(value));
+        self.db().expires.remove(key);
+    }
+
+    /// Conditional SET, the check and the write happen under the lock of the key's shard.
+    /// Returns whether the value was written and the previous value of the key when it was
+    /// a string.
+    pub fn set_with(
+        &self,
+        key: &[u8],
+        value: StringValue,
+        condition: SetCondition,
+        expiry: SetExpiry,
+    ) -> (bool, Option<StringValue>) {
+        self.expire_if_needed(key);
+
+        let db = self.db();
+        let entry = db.keyspace.entry(Bytes::copy_from_slice(key));
+        let (exists, old) = match &entry {
+            Entry::Occupied(v) => match v.get() {
+                Value::String(old) => (true, Some(old.clone())),
+                _ => (true, None),
+            },
+            Entry::Vacant(_) => (false, None),
+        };
+        let allowed = match condition {
+            SetCondition::Always => true,
+            SetCondition::Nx => !exists,
+            SetCondition::Xx => exists,
+        };
+        if !allowed {
+            return (false, old);
+        }
+
+        // keep the shard locked until the ttl matches the new value
+        let _value = entry.insert(Value::String(value));
+        self.touch_key(key);
+        match expiry {
+            SetExpiry::Clear => {
+                db.expires.remove(key);
+            }
+            SetExpiry::Keep => {}
+            SetExpiry::At(deadline) => {
+                db.expires.insert(Bytes::copy_from_slice(key), deadline);
+            }
+        }
+        (true, old)
+    }
+
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<RespFrame>, BackendError> {
+        Ok(self
+            .read::<HashValue>(key)?
+            .and_then(|hash| hash.get(field).cloned()))
     }
 
-    pub fn set(&self, key: &str, value: RespFrame) {
-        self.map.insert(key.to_string(), value);
+    /// The fields and values of a hash, None when the key does not exist
+    pub fn hgetall(&self, key: &[u8]) -> Result<Option<Vec<(Bytes, RespFrame)>>, BackendError> {
+        Ok(self.read::<HashValue>(key)?.map(|hash| {
+            hash.iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()
+        }))
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    pub fn hset(&self, key: Bytes, field: Bytes, value: RespFrame) -> Result<(), BackendError> {
+        self.update::<HashValue, _>(&key, true, |hash| {
+            hash.insert(compact(field), compact_frame(value))
+        })?;
+        self.touch_key(&key);
+        Ok(())
     }
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        self.db().keyspace.contains_key(key)
     }
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
-        hmap.insert(field, value);
+
+    pub(crate) fn remove_key(&self, key: &[u8]) -> bool {
+        let db = self.db();
+        db.expires.remove(key);
+        let removed = db.keyspace.remove(key).is_some();
+        if removed {
+            self.touch_key(key);
+        }
+        removed
     }
 }
@@ -0,0 +1,329 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::backend::glob::glob_match;
+use crate::{now_millis, Backend, BulkString, OutputBufferLimit, RespEncode, RespFrame, RespPush};
+
+// a message with its encoded size, None tells the connection it fell behind past its limit
+type Message = Option<(RespFrame, usize)>;
+type Subscribers = HashMap<u64, MessageSender>;
+
+/// Channels and patterns with the connections subscribed to them
+#[derive(Debug, Default)]
+pub struct PubSubState {
+    channels: HashMap<Vec<u8>, Subscribers>,
+    patterns: HashMap<Vec<u8>, Subscribers>,
+}
+
+static NEXT_SUBSCRIBER_ID: AtomicU64 = AtomicU64::new(1);
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn unregister(targets: &mut HashMap<Vec<u8>, Subscribers>, name: &[u8], id: u64) {
+    if let Some(subscribers) = targets.get_mut(name) {
+        subscribers.remove(&id);
+        if subscribers.is_empty() {
+            targets.remove(name);
+        }
+    }
+}
+
+// The messages queued for a connection and not written yet, shared by the connection and
+// the senders PUBLISH uses
+#[derive(Debug, Default)]
+struct OutputBuffer {
+    queued: AtomicUsize,
+    // when the queue went past the soft limit, 0 while it is below
+    soft_limit_since: AtomicI64,
+    closed: AtomicBool,
+}
+
+impl OutputBuffer {
+    // like redis, the hard limit disconnects at once and the soft one after some time past it
+    fn exceeds(&self, limit: &OutputBufferLimit, queued: usize) -> bool {
+        if limit.hard_limit > 0 && queued > limit.hard_limit {
+            return true;
+        }
+        if limit.soft_limit == 0 || queued <= limit.soft_limit {
+            self.soft_limit_since.store(0, Ordering::Relaxed);
+            return false;
+        }
+        let now = now_millis();
+        match self
+            .soft_limit_since
+            .compare_exchange(0, now, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => false,
+            Err(since) => (now - since) as u64 > limit.soft_seconds * 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MessageSender {
+    sender: UnboundedSender<Message>,
+    buffer: Arc<OutputBuffer>,
+}
+
+impl MessageSender {
+    // queue a message unless the connection is gone, or is closed because it fell too far
+    // behind
+    fn send(&self, frame: RespFrame, len: usize, limit: &OutputBufferLimit) -> bool {
+        if self.buffer.closed.load(Ordering::Relaxed) {
+            return false;
+        }
+        let queued = self.buffer.queued.fetch_add(len, Ordering::Relaxed) + len;
+        if self.buffer.exceeds(limit, queued) {
+            self.buffer.closed.store(true, Ordering::Relaxed);
+            let _ = self.sender.send(None);
+            return false;
+        }
+        self.sender.send(Some((frame, len))).is_ok()
+    }
+}
+
+/// The messages published to a connection's subscriptions
+#[derive(Debug)]
+pub struct Messages {
+    receiver: UnboundedReceiver<Message>,
+    buffer: Arc<OutputBuffer>,
+}
+
+impl Messages {
+    /// The next message, None once the connection fell behind past
+    /// client-output-buffer-limit and has to be closed
+    pub async fn recv(&mut self) -> Option<RespFrame> {
+        let message = self.receiver.recv().await.flatten();
+        self.written(message)
+    }
+
+    pub fn try_recv(&mut self) -> Option<RespFrame> {
+        let message = self.receiver.try_recv().ok().flatten();
+        self.written(message)
+    }
+
+    fn written(&self, message: Message) -> Option<RespFrame> {
+        let (frame, len) = message?;
+        self.buffer.queued.fetch_sub(len, Ordering::Relaxed);
+        Some(frame)
+    }
+}
+
+fn message(kind: &'static str, args: &[&[u8]]) -> RespFrame {
+    let mut frames = vec![BulkString::new(kind).into()];
+    frames.extend(args.iter().map(|arg| BulkString::new(arg.to_vec()).into()));
+    RespPush::new(frames).into()
+}
+
+/// The subscriptions of one connection, published messages arrive on the `Messages`
+/// returned by `new`. Everything is unsubscribed when the connection drops it.
+#[derive(Debug)]
+pub struct Subscriber {
+    backend: Backend,
+    id: u64,
+    sender: MessageSender,
+    channels: HashSet<Vec<u8>>,
+    patterns: HashSet<Vec<u8>>,
+}
+
+impl Subscriber {
+    pub fn new(backend: Backend) -> (Self, Messages) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let buffer = Arc::new(OutputBuffer::default());
+        let subscriber = Subscriber {
+            backend,
+            id: NEXT_SUBSCRIBER_ID.fetch_add(1, Ordering::Relaxed),
+            sender: MessageSender {
+                sender,
+                buffer: buffer.clone(),
+            },
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+        };
+        (subscriber, Messages { receiver, buffer })
+    }
+
+    /// Number of channels and patterns subscribed to
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    pub fn channels(&self) -> Vec<Vec<u8>> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn patterns(&self) -> Vec<Vec<u8>> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    /// SUBSCRIBE one channel, returns the subscription count
+    pub fn subscribe(&mut self, channel: Vec<u8>) -> usize {
+        if self.channels.insert(channel.clone()) {
+            lock(&self.backend.pubsub)
+                .channels
+                .entry(channel)
+                .or_default()
+                .insert(self.id, self.sender.clone());
+        }
+        self.count()
+    }
+
+    pub fn unsubscribe(&mut self, channel: &[u8]) -> usize {
+        if self.channels.remove(channel) {
+            unregister(&mut lock(&self.backend.pubsub).channels, channel, self.id);
+        }
+        self.count()
+    }
+
+    pub fn psubscribe(&mut self, pattern: Vec<u8>) -> usize {
+        if self.patterns.insert(pattern.clone()) {
+            lock(&self.backend.pubsub)
+                .patterns
+                .entry(pattern)
+                .or_default()
+                .insert(self.id, self.sender.clone());
+        }
+        self.count()
+    }
+
+    pub fn punsubscribe(&mut self, pattern: &[u8]) -> usize {
+        if self.patterns.remove(pattern) {
+            unregister(&mut lock(&self.backend.pubsub).patterns, pattern, self.id);
+        }
+        self.count()
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        if self.count() == 0 {
+            return;
+        }
+        let mut state = lock(&self.backend.pubsub);
+        for channel in &self.channels {
+            unregister(&mut state.channels, channel, self.id);
+        }
+        for pattern in &self.patterns {
+            unregister(&mut state.patterns, pattern, self.id);
+        }
+    }
+}
+
+impl Backend {
+    /// PUBLISH, returns the number of subscriptions the message was delivered to
+    pub fn publish(&self, channel: &[u8], payload: &[u8]) -> usize {
+        let limit = &self.config().client_output_buffer_limit_pubsub;
+        let state = lock(&self.pubsub);
+        let mut receivers = 0;
+        if let Some(subscribers) = state.channels.get(channel) {
+            let frame = message("message", &[channel, payload]);
+            let len = frame.clone().encode().len();
+            receivers += subscribers
+                .values()
+                .filter(|sender| sender.send(frame.clone(), len, limit))
+                .count();
+        }
+        for (pattern, subscribers) in &state.patterns {
+            if !glob_match(pattern, channel) {
+                continue;
+            }
+            let frame = message("pmessage", &[pattern, channel, payload]);
+            let len = frame.clone().encode().len();
+            receivers += subscribers
+                .values()
+                .filter(|sender| sender.send(frame.clone(), len, limit))
+                .count();
+        }
+        receivers
+    }
+
+    /// PUBSUB CHANNELS, the channels with subscribers, optionally matching a pattern
+    pub fn pubsub_channels(&self, pattern: Option<&[u8]>) -> Vec<Vec<u8>> {
+        lock(&self.pubsub)
+            .channels
+            .keys()
+            .filter(|channel| pattern.is_none_or(|pattern| glob_match(pattern, channel)))
+            .cloned()
+            .collect()
+    }
+
+    /// PUBSUB NUMSUB, the number of subscribers of each channel
+    pub fn pubsub_numsub(&self, channels: &[Vec<u8>]) -> Vec<usize> {
+        let state = lock(&self.pubsub);
+        channels
+            .iter()
+            .map(|channel| state.channels.get(channel).map_or(0, |v| v.len()))
+            .collect()
+    }
+
+    /// PUBSUB NUMPAT, the number of patterns subscribed to
+    pub fn pubsub_numpat(&self) -> usize {
+        lock(&self.pubsub).patterns.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn test_publish() {
+        let backend = Backend::new();
+        let (mut a, mut a_rx) = Subscriber::new(backend.clone());
+        let (mut b, mut b_rx) = Subscriber::new(backend.clone());
+        assert_eq!(a.subscribe(b"news".to_vec()), 1);
+        assert_eq!(a.psubscribe(b"n*".to_vec()), 2);
+        b.psubscribe(b"n*".to_vec());
+
+        assert_eq!(backend.publish(b"news", b"hi"), 3);
+        assert_eq!(
+            a_rx.try_recv().unwrap(),
+            message("message", &[b"news", b"hi"])
+        );
+        assert_eq!(
+            a_rx.try_recv().unwrap(),
+            message("pmessage", &[b"n*", b"news", b"hi"])
+        );
+        assert!(b_rx.try_recv().is_some());
+        assert_eq!(
+            backend.pubsub_channels(Some(b"ne?s")),
+            vec![b"news".to_vec()]
+        );
+        assert_eq!(backend.pubsub_numpat(), 1);
+
+        assert_eq!(a.unsubscribe(b"news"), 1);
+        drop(b);
+        assert_eq!(backend.publish(b"news", b"hi"), 1);
+        assert_eq!(backend.pubsub_numsub(&[b"news".to_vec()]), vec![0]);
+    }
+
+    // a subscriber that does not read its messages is cut off past the hard limit
+    #[test]
+    fn test_output_buffer_limit() {
+        let backend = Backend::with_config(Config {
+            client_output_buffer_limit_pubsub: OutputBufferLimit {
+                hard_limit: 100,
+                soft_limit: 0,
+                soft_seconds: 0,
+            },
+            ..Default::default()
+        });
+        let (mut subscriber, mut messages) = Subscriber::new(backend.clone());
+        subscriber.subscribe(b"news".to_vec());
+
+        let payload = [b'x'; 30];
+        assert_eq!(backend.publish(b"news", &payload), 1);
+        assert!(messages.try_recv().is_some());
+        assert_eq!(backend.publish(b"news", &payload), 1);
+        assert_eq!(backend.publish(b"news", &payload), 0);
+        assert!(messages.try_recv().is_some());
+        assert_eq!(messages.try_recv(), None);
+        assert_eq!(backend.publish(b"news", &payload), 0);
+    }
+}
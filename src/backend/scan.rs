@@ -0,0 +1,400 @@
+use bytes::Bytes;
+use hashbrown::raw::RawTable;
+use std::hash::BuildHasher;
+
+use crate::backend::glob::glob_match;
+use crate::{Backend, BackendError, HashValue, RespFrame, ScoredMember, SetValue, SortedSet};
+
+const SCAN_DEFAULT_COUNT: usize = 10;
+// same as redis: a call gives up after visiting this many empty buckets per element asked for
+const SCAN_EMPTY_VISITS: usize = 10;
+// the low bits of a cursor are the bucket, SCAN keeps the keyspace shard above them
+const BUCKET_BITS: u32 = 48;
+const BUCKET_CURSOR: u64 = (1 << BUCKET_BITS) - 1;
+// hashbrown keeps the top 7 bits of a hash in the control byte of its bucket
+const HASH_TAG_SHIFT: u32 = 57;
+
+/// MATCH, COUNT and TYPE of the SCAN family
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanOptions {
+    pub pattern: Option<Vec<u8>>,
+    /// how many elements a call looks at, a hint like in redis: MATCH and TYPE filter them after
+    pub count: usize,
+    /// the type name of the keys SCAN returns, the other commands ignore it
+    pub kind: Option<String>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            pattern: None,
+            count: SCAN_DEFAULT_COUNT,
+            kind: None,
+        }
+    }
+}
+
+impl ScanOptions {
+    fn matches(&self, element: &[u8]) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| glob_match(pattern, element))
+    }
+}
+
+// The work left to a call: elements to visit, and buckets so a sparse table does not make a
+// call walk all of it
+struct Budget {
+    elements: usize,
+    buckets: usize,
+}
+
+impl Budget {
+    fn new(count: usize) -> Self {
+        let count = count.max(1);
+        Self {
+            elements: count,
+            buckets: count.saturating_mul(SCAN_EMPTY_VISITS),
+        }
+    }
+
+    fn is_spent(&self) -> bool {
+        self.elements == 0 || self.buckets == 0
+    }
+}
+
+// Visit the elements `table` placed from bucket `home`, `hash` is the hash the table gave
+// an element. The probe sequence of a hash starts at its low bits and the 128 tags cover
+// the control bytes of every element on it, the others met there are skipped.
+fn visit_bucket<T>(
+    table: &RawTable<T>,
+    home: u64,
+    hash: &impl Fn(&T) -> u64,
+    visit: &mut impl FnMut(&T),
+) -> usize {
+    let mask = table.buckets() as u64 - 1;
+    let mut visited = 0;
+    for tag in 0..128 {
+        // SAFETY: the table is borrowed for as long as its buckets are read
+        for bucket in unsafe { table.iter_hash(tag << HASH_TAG_SHIFT | home) } {
+            let element = unsafe { bucket.as_ref() };
+            if hash(element) & mask == home {
+                visit(element);
+                visited += 1;
+            }
+        }
+    }
+    visited
+}
+
+fn reverse(cursor: u64) -> u64 {
+    cursor.reverse_bits() >> (u64::BITS - BUCKET_BITS)
+}
+
+// Walk `table` from the bucket `cursor` like redis does, returns where to go on when the
+// budget runs out first. The cursor counts up with its bits reversed: a bucket splits into
+// buckets that follow each other in that order when the table grows, and buckets merge into
+// one that is not behind the cursor when it shrinks. So an element present for the whole
+// scan is returned at least once however the table is resized between calls, a shrinking
+// one can return some twice. Buckets come from the random hasher of each table, a cursor is
+// only meaningful to the process that returned it.
+fn walk<T>(
+    table: &RawTable<T>,
+    mut cursor: u64,
+    budget: &mut Budget,
+    hash: impl Fn(&T) -> u64,
+    mut visit: impl FnMut(&T),
+) -> Option<u64> {
+    let mask = table.buckets() as u64 - 1;
+    loop {
+        if budget.is_spent() {
+            return Some(cursor);
+        }
+        let visited = visit_bucket(table, cursor & mask, &hash, &mut visit);
+        budget.elements = budget.elements.saturating_sub(visited);
+        budget.buckets -= 1;
+        cursor = reverse(cursor | (!mask & BUCKET_CURSOR)) + 1;
+        if cursor > BUCKET_CURSOR {
+            return None;
+        }
+        cursor = reverse(cursor);
+    }
+}
+
+// One call of HSCAN, SSCAN or ZSCAN over the single table of a value, 0 when it is done
+fn scan_table<T>(
+    table: &RawTable<T>,
+    cursor: u64,
+    count: usize,
+    hash: impl Fn(&T) -> u64,
+    visit: impl FnMut(&T),
+) -> u64 {
+    let mut budget = Budget::new(count);
+    walk(table, cursor & BUCKET_CURSOR, &mut budget, hash, visit).unwrap_or(0)
+}
+
+impl Backend {
+    /// KEYS, the keys matching a glob style `pattern`
+    pub fn keys(&self, pattern: &[u8]) -> Vec<Bytes> {
+        let keys = self
+            .db()
+            .keyspace
+            .iter()
+            .filter(|entry| glob_match(pattern, entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect::<Vec<_>>();
+        // the keyspace is not locked anymore, expired keys can go away now
+        keys.into_iter()
+            .filter(|key| !self.expire_if_needed(key))
+            .collect()
+    }
+
+    /// SCAN, returns the next cursor and the keys of this call
+    pub fn scan(&self, cursor: u64, options: &ScanOptions) -> (u64, Vec<Bytes>) {
+        let shards = self.db().keyspace.shards();
+        let (mut index, mut cursor) = ((cursor >> BUCKET_BITS) as usize, cursor & BUCKET_CURSOR);
+        let mut budget = Budget::new(options.count);
+        let (mut next, mut keys) = (0, Vec::new());
+        // one shard locked at a time, a cursor never stops at the first bucket of the first
+        // shard so it is only 0 at the end
+        while index < shards.len() {
+            let shard = shards[index].read();
+            let hasher = shard.hasher();
+            let stop = walk(
+                shard.raw_table(),
+                cursor,
+                &mut budget,
+                |(key, _)| hasher.hash_one(key),
+                |(key, value)| {
+                    let kind = options.kind.as_deref();
+                    if options.matches(key)
+                        && kind.is_none_or(|kind| value.get().type_name() == kind)
+                    {
+                        keys.push(key.clone());
+                    }
+                },
+            );
+            if let Some(cursor) = stop {
+                next = (index as u64) << BUCKET_BITS | cursor;
+                break;
+            }
+            (index, cursor) = (index + 1, 0);
+        }
+        let keys = keys
+            .into_iter()
+            .filter(|key| !self.expire_if_needed(key))
+            .collect();
+        (next, keys)
+    }
+
+    /// HSCAN, the fields with their values
+    pub fn hscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        options: &ScanOptions,
+    ) -> Result<(u64, Vec<(Bytes, RespFrame)>), BackendError> {
+        let Some(hash) = self.read::<HashValue>(key)? else {
+            return Ok((0, Vec::new()));
+        };
+        let mut fields = Vec::new();
+        let hasher = hash.hasher();
+        let next = scan_table(
+            hash.raw_table(),
+            cursor,
+            options.count,
+            |(field, _)| hasher.hash_one(field),
+            |(field, value)| {
+                if options.matches(field) {
+                    fields.push((field.clone(), value.clone()));
+                }
+            },
+        );
+        Ok((next, fields))
+    }
+
+    /// SSCAN
+    pub fn sscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        options: &ScanOptions,
+    ) -> Result<(u64, Vec<Vec<u8>>), BackendError> {
+        let Some(set) = self.read::<SetValue>(key)? else {
+            return Ok((0, Vec::new()));
+        };
+        let mut members = Vec::new();
+        let hasher = set.hasher();
+        let next = scan_table(
+            set.raw_table(),
+            cursor,
+            options.count,
+            |(member, _)| hasher.hash_one(member),
+            |(member, _)| {
+                if options.matches(member) {
+                    members.push(member.clone());
+                }
+            },
+        );
+        Ok((next, members))
+    }
+
+    /// ZSCAN, the members with their scores
+    pub fn zscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        options: &ScanOptions,
+    ) -> Result<(u64, Vec<ScoredMember>), BackendError> {
+        let Some(zset) = self.read::<SortedSet>(key)? else {
+            return Ok((0, Vec::new()));
+        };
+        let mut members = Vec::new();
+        let scores = zset.scores();
+        let hasher = scores.hasher();
+        let next = scan_table(
+            scores.raw_table(),
+            cursor,
+            options.count,
+            |(member, _)| hasher.hash_one(member),
+            |(member, score)| {
+                if options.matches(member) {
+                    members.push((member.clone(), *score));
+                }
+            },
+        );
+        Ok((next, members))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    use crate::BulkString;
+
+    // every key present from start to end is returned while keys are added between the calls
+    #[test]
+    fn test_scan_while_growing() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(format!("key:{}", i).as_bytes(), BulkString::new("v").into());
+        }
+        let options = ScanOptions {
+            count: 7,
+            ..Default::default()
+        };
+
+        let (mut cursor, mut seen, mut calls) = (0, HashSet::new(), 0);
+        loop {
+            let (next, keys) = backend.scan(cursor, &options);
+            seen.extend(keys);
+            for i in 0..5 {
+                let key = format!("new:{}:{}", calls, i);
+                backend.set(key.as_bytes(), BulkString::new("v").into());
+            }
+            calls += 1;
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        for i in 0..100 {
+            assert!(seen.contains(format!("key:{}", i).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_scan_filters() {
+        let backend = Backend::new();
+        backend.set(b"string:1", BulkString::new("v").into());
+        backend.set(b"string:2", BulkString::new("v").into());
+        backend.sadd(b"set:1", vec![b"a".to_vec()]).unwrap();
+
+        let mut keys = backend.keys(b"string:*");
+        keys.sort();
+        assert_eq!(keys, vec![Bytes::from("string:1"), Bytes::from("string:2")]);
+
+        let options = ScanOptions {
+            count: 100,
+            kind: Some("set".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(backend.scan(0, &options), (0, vec![Bytes::from("set:1")]));
+
+        let options = ScanOptions {
+            pattern: Some(b"*:2".to_vec()),
+            count: 100,
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.scan(0, &options),
+            (0, vec![Bytes::from("string:2")])
+        );
+        assert_eq!(backend.sscan(b"missing", 0, &options), Ok((0, Vec::new())));
+        assert_eq!(
+            backend.hscan(b"set:1", 0, &options),
+            Err(BackendError::WrongType)
+        );
+    }
+
+    // fields present from start to end are returned while the hash is resized, and a call
+    // only looks at about COUNT of them
+    #[test]
+    fn test_hscan_while_growing() {
+        let backend = Backend::new();
+        let hset = |field: String| {
+            let value = BulkString::new("v").into();
+            backend
+                .hset(Bytes::from("hash"), field.into(), value)
+                .unwrap();
+        };
+        for i in 0..100 {
+            hset(format!("field:{}", i));
+        }
+        let options = ScanOptions {
+            count: 7,
+            ..Default::default()
+        };
+
+        let (mut cursor, mut seen, mut calls) = (0, HashSet::new(), 0);
+        loop {
+            let (next, fields) = backend.hscan(b"hash", cursor, &options).unwrap();
+            // the last bucket of a call is returned whole
+            assert!(fields.len() < 2 * 7);
+            seen.extend(fields.into_iter().map(|(field, _)| field));
+            for i in 0..5 {
+                hset(format!("new:{}:{}", calls, i));
+            }
+            calls += 1;
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        for i in 0..100 {
+            assert!(seen.contains(format!("field:{}", i).as_bytes()));
+        }
+    }
+
+    // the reverse binary cursor visits every bucket of a table once
+    #[test]
+    fn test_walk_buckets() {
+        let mut table = RawTable::new();
+        for i in 0..64u64 {
+            table.insert(i, i, |i| *i);
+        }
+        let (mut cursor, mut seen) = (0, Vec::new());
+        loop {
+            let mut budget = Budget::new(3);
+            let stop = walk(&table, cursor, &mut budget, |i| *i, |i| seen.push(*i));
+            match stop {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+        seen.sort();
+        assert_eq!(seen, (0..64).collect::<Vec<_>>());
+    }
+}
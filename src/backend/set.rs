@@ -0,0 +1,253 @@
+use bytes::Bytes;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::{Backend, BackendError, SetValue, Value};
+
+/// How SINTER / SUNION / SDIFF combine their sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    Diff,
+}
+
+impl Backend {
+    /// SADD, returns the number of members that were not in the set yet
+    pub fn sadd(&self, key: &[u8], members: Vec<Vec<u8>>) -> Result<usize, BackendError> {
+        let added = self
+            .update::<SetValue, _>(key, true, |set| {
+                members
+                    .into_iter()
+                    .filter(|member| set.insert(member.clone()))
+                    .count()
+            })?
+            .unwrap_or(0);
+        if added > 0 {
+            self.touch_key(key);
+        }
+        Ok(added)
+    }
+
+    /// SREM, returns the number of members removed, the key goes away with its last member
+    pub fn srem(&self, key: &[u8], members: &[Vec<u8>]) -> Result<usize, BackendError> {
+        let removed = self
+            .update::<SetValue, _>(key, false, |set| {
+                members.iter().filter(|member| set.remove(*member)).count()
+            })?
+            .unwrap_or(0);
+        if removed > 0 {
+            self.touch_key(key);
+        }
+        Ok(removed)
+    }
+
+    pub fn smembers(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, BackendError> {
+        Ok(self
+            .read::<SetValue>(key)?
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// SMISMEMBER, whether each of `members` is in the set
+    pub fn smismember(&self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<bool>, BackendError> {
+        let set = self.read::<SetValue>(key)?;
+        Ok(members
+            .iter()
+            .map(|member| set.as_ref().is_some_and(|set| set.contains(member)))
+            .collect())
+    }
+
+    pub fn scard(&self, key: &[u8]) -> Result<usize, BackendError> {
+        Ok(self
+            .read::<SetValue>(key)?
+            .map(|set| set.len())
+            .unwrap_or(0))
+    }
+
+    /// SPOP, removes up to `count` random members
+    pub fn spop(&self, key: &[u8], count: usize) -> Result<Vec<Vec<u8>>, BackendError> {
+        let popped = self
+            .update::<SetValue, _>(key, false, |set| {
+                let popped = set
+                    .iter()
+                    .cloned()
+                    .choose_multiple(&mut rand::thread_rng(), count);
+                for member in &popped {
+                    set.remove(member);
+                }
+                popped
+            })?
+            .unwrap_or_default();
+        if !popped.is_empty() {
+            self.touch_key(key);
+        }
+        Ok(popped)
+    }
+
+    /// SRANDMEMBER: a positive count returns distinct members,
+    /// a negative one returns `-count` members that may repeat
+    pub fn srandmember(&self, key: &[u8], count: i64) -> Result<Vec<Vec<u8>>, BackendError> {
+        let Some(set) = self.read::<SetValue>(key)? else {
+            return Ok(vec![]);
+        };
+        let mut rng = rand::thread_rng();
+        if count >= 0 {
+            return Ok(set
+                .iter()
+                .cloned()
+                .choose_multiple(&mut rng, count as usize));
+        }
+
+        let members = set.iter().collect::<Vec<_>>();
+        Ok((0..count.unsigned_abs())
+            .map(|_| members[rng.gen_range(0..members.len())].clone())
+            .collect())
+    }
+
+    /// SINTER / SUNION / SDIFF, missing keys are empty sets
+    pub fn set_combine(&self, op: SetOp, keys: &[Bytes]) -> Result<SetValue, BackendError> {
+        for key in keys {
+            self.check_type::<SetValue>(key)?;
+        }
+
+        let Some((first, rest)) = keys.split_first() else {
+            return Ok(SetValue::default());
+        };
+        let mut result = self
+            .read::<SetValue>(first)?
+            .map(|set| set.clone())
+            .unwrap_or_default();
+        for key in rest {
+            // each set is only borrowed for its own step, never two shards at once
+            let set = self.read::<SetValue>(key)?;
+            match (op, set) {
+                (SetOp::Inter, Some(set)) => result.retain(|member| set.contains(member)),
+                (SetOp::Inter, None) => result.clear(),
+                (SetOp::Union, Some(set)) => result.extend(set.iter().cloned()),
+                (SetOp::Diff, Some(set)) => result.retain(|member| !set.contains(member)),
+                (SetOp::Union | SetOp::Diff, None) => {}
+            }
+            if op == SetOp::Inter && result.is_empty() {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    /// SINTERSTORE / SUNIONSTORE / SDIFFSTORE, `destination` is overwritten whatever its type
+    /// and deleted when the result is empty. Returns the size of the result.
+    pub fn set_combine_store(
+        &self,
+        op: SetOp,
+        destination: &[u8],
+        keys: &[Bytes],
+    ) -> Result<usize, BackendError> {
+        let result = self.set_combine(op, keys)?;
+        let len = result.len();
+        self.remove_key(destination);
+        if len > 0 {
+            self.insert_value(destination, Value::Set(result));
+        }
+        Ok(len)
+    }
+
+    /// SINTERCARD, the size of the intersection, counting stops at `limit` unless it is 0
+    pub fn sintercard(&self, keys: &[Bytes], limit: usize) -> Result<usize, BackendError> {
+        let len = self.set_combine(SetOp::Inter, keys)?.len();
+        Ok(if limit == 0 { len } else { len.min(limit) })
+    }
+
+    /// SMOVE, returns whether the member was moved
+    pub fn smove(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        member: Vec<u8>,
+    ) -> Result<bool, BackendError> {
+        self.check_type::<SetValue>(source)?;
+        self.check_type::<SetValue>(destination)?;
+
+        if source == destination {
+            return Ok(self
+                .read::<SetValue>(source)?
+                .is_some_and(|set| set.contains(&member)));
+        }
+        if self.srem(source, std::slice::from_ref(&member))? == 0 {
+            return Ok(false);
+        }
+        self.sadd(destination, vec![member])?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn members(items: &[&str]) -> Vec<Vec<u8>> {
+        items.iter().map(|v| v.as_bytes().to_vec()).collect()
+    }
+
+    fn sorted(mut members: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn test_set_members() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(backend.sadd(b"s", members(&["a", "b", "a"]))?, 2);
+        assert_eq!(backend.sadd(b"s", members(&["b", "c"]))?, 1);
+        assert_eq!(sorted(backend.smembers(b"s")?), members(&["a", "b", "c"]));
+        assert_eq!(
+            backend.smismember(b"s", &members(&["a", "x"]))?,
+            vec![true, false]
+        );
+
+        assert_eq!(backend.spop(b"s", 2)?.len(), 2);
+        assert_eq!(backend.scard(b"s")?, 1);
+        assert_eq!(backend.srandmember(b"s", -3)?.len(), 3);
+        assert_eq!(backend.srandmember(b"s", 3)?.len(), 1);
+        assert_eq!(backend.srem(b"s", &backend.smembers(b"s")?)?, 1);
+        assert!(!backend.contains_key(b"s"));
+
+        backend.set(b"string", BulkString::new("v").into());
+        assert_eq!(
+            backend.sadd(b"string", members(&["a"])),
+            Err(BackendError::WrongType)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_algebra() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"a", members(&["1", "2", "3"]))?;
+        backend.sadd(b"b", members(&["2", "3", "4"]))?;
+        let keys = vec![Bytes::from("a"), Bytes::from("b")];
+
+        let inter = backend.set_combine(SetOp::Inter, &keys)?;
+        assert_eq!(sorted(inter.into_iter().collect()), members(&["2", "3"]));
+        let union = backend.set_combine(SetOp::Union, &keys)?;
+        assert_eq!(union.len(), 4);
+        let diff = backend.set_combine(SetOp::Diff, &keys)?;
+        assert_eq!(diff.into_iter().collect::<Vec<_>>(), members(&["1"]));
+        assert_eq!(backend.sintercard(&keys, 1)?, 1);
+
+        // an empty result deletes the destination
+        let missing = vec![Bytes::from("a"), Bytes::from("missing")];
+        backend.set(b"dst", BulkString::new("v").into());
+        assert_eq!(
+            backend.set_combine_store(SetOp::Inter, b"dst", &missing)?,
+            0
+        );
+        assert!(!backend.contains_key(b"dst"));
+
+        assert!(backend.smove(b"a", b"c", b"1".to_vec())?);
+        assert!(!backend.smove(b"a", b"c", b"1".to_vec())?);
+        assert_eq!(backend.smembers(b"c")?, members(&["1"]));
+        Ok(())
+    }
+}
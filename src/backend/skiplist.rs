@@ -0,0 +1,425 @@
+use std::cmp::Ordering;
+
+// same parameters as redis: 32 levels are plenty for 2^64 elements with p = 1/4
+const MAX_LEVEL: usize = 32;
+const P: f64 = 0.25;
+
+const HEAD: usize = 0;
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    forward: usize,
+    // number of level 0 links the forward link jumps over, gives the rank of each node
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: Vec<u8>,
+    score: f64,
+    backward: usize,
+    levels: Vec<Level>,
+}
+
+impl Node {
+    fn cmp(&self, score: f64, member: &[u8]) -> Ordering {
+        self.score
+            .total_cmp(&score)
+            .then_with(|| self.member.as_slice().cmp(member))
+    }
+}
+
+/// Skiplist ordered by (score, member) with spans, like the redis zskiplist, so ranks are
+/// found in O(log n). Nodes live in an arena and link to each other by index.
+#[derive(Debug, Clone)]
+pub(crate) struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    level: usize,
+    len: usize,
+    tail: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        SkipList {
+            nodes: vec![Node {
+                member: vec![],
+                score: 0.0,
+                backward: NIL,
+                levels: vec![
+                    Level {
+                        forward: NIL,
+                        span: 0,
+                    };
+                    MAX_LEVEL
+                ],
+            }],
+            free: vec![],
+            level: 1,
+            len: 0,
+            tail: NIL,
+        }
+    }
+}
+
+fn random_level() -> usize {
+    let mut level = 1;
+    while level < MAX_LEVEL && rand::random::<f64>() < P {
+        level += 1;
+    }
+    level
+}
+
+impl SkipList {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    fn forward(&self, node: usize, level: usize) -> usize {
+        self.nodes[node].levels[level].forward
+    }
+
+    fn alloc(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = node;
+                i
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Insert a member that is not in the list yet
+    pub(crate) fn insert(&mut self, score: f64, member: Vec<u8>) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0usize; MAX_LEVEL];
+
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+            loop {
+                let f = self.forward(x, i);
+                if f == NIL || self.nodes[f].cmp(score, &member) != Ordering::Less {
+                    break;
+                }
+                rank[i] += self.nodes[x].levels[i].span;
+                x = f;
+            }
+            update[i] = x;
+        }
+
+        let level = random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let new = self.alloc(Node {
+            member,
+            score,
+            backward: if update[0] == HEAD { NIL } else { update[0] },
+            levels: vec![
+                Level {
+                    forward: NIL,
+                    span: 0,
+                };
+                level
+            ],
+        });
+        for i in 0..level {
+            let prev = self.nodes[update[i]].levels[i];
+            self.nodes[new].levels[i] = Level {
+                forward: prev.forward,
+                span: prev.span - (rank[0] - rank[i]),
+            };
+            self.nodes[update[i]].levels[i] = Level {
+                forward: new,
+                span: rank[0] - rank[i] + 1,
+            };
+        }
+        for (i, &node) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[node].levels[i].span += 1;
+        }
+
+        match self.forward(new, 0) {
+            NIL => self.tail = new,
+            next => self.nodes[next].backward = new,
+        }
+        self.len += 1;
+    }
+
+    /// Remove the node of `member` with `score`, returns whether it was found
+    pub(crate) fn remove(&mut self, score: f64, member: &[u8]) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let f = self.forward(x, i);
+                if f == NIL || self.nodes[f].cmp(score, member) != Ordering::Less {
+                    break;
+                }
+                x = f;
+            }
+            update[i] = x;
+        }
+
+        let x = self.forward(x, 0);
+        if x == NIL || self.nodes[x].cmp(score, member) != Ordering::Equal {
+            return false;
+        }
+
+        for (i, prev) in update.iter().enumerate().take(self.level) {
+            if self.forward(*prev, i) == x {
+                let removed = self.nodes[x].levels[i];
+                let link = &mut self.nodes[*prev].levels[i];
+                link.span += removed.span;
+                link.span -= 1;
+                link.forward = removed.forward;
+            } else {
+                self.nodes[*prev].levels[i].span -= 1;
+            }
+        }
+        let backward = self.nodes[x].backward;
+        match self.forward(x, 0) {
+            NIL => self.tail = backward,
+            next => self.nodes[next].backward = backward,
+        }
+        while self.level > 1 && self.forward(HEAD, self.level - 1) == NIL {
+            self.level -= 1;
+        }
+
+        self.nodes[x].member = vec![];
+        self.free.push(x);
+        self.len -= 1;
+        true
+    }
+
+    // number of nodes matching `before`, which must hold for a prefix of the list,
+    // and the last of those nodes
+    fn seek(&self, before: impl Fn(&Node) -> bool) -> (usize, usize) {
+        let mut x = HEAD;
+        let mut traversed = 0;
+        for i in (0..self.level).rev() {
+            loop {
+                let f = self.forward(x, i);
+                if f == NIL || !before(&self.nodes[f]) {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = f;
+            }
+        }
+        (traversed, x)
+    }
+
+    /// 0 based rank of `member`
+    pub(crate) fn rank(&self, score: f64, member: &[u8]) -> Option<usize> {
+        let (rank, x) = self.seek(|node| node.cmp(score, member) == Ordering::Less);
+        let next = self.forward(x, 0);
+        (next != NIL && self.nodes[next].cmp(score, member) == Ordering::Equal).then_some(rank)
+    }
+
+    /// Ranks `[start, end)` of the nodes within the score bounds
+    pub(crate) fn score_range(&self, min: &ScoreBound, max: &ScoreBound) -> (usize, usize) {
+        let (start, _) = self.seek(|node| !min.below(node.score));
+        let (end, _) = self.seek(|node| max.above(node.score));
+        (start, end.max(start))
+    }
+
+    /// Ranks `[start, end)` of the nodes within the lexicographical bounds
+    pub(crate) fn lex_range(&self, min: &LexBound, max: &LexBound) -> (usize, usize) {
+        let (start, _) = self.seek(|node| !min.below(&node.member));
+        let (end, _) = self.seek(|node| max.above(&node.member));
+        (start, end.max(start))
+    }
+
+    // node at the 0 based rank
+    fn node_at(&self, rank: usize) -> usize {
+        let (_, x) = self.seek_rank(rank + 1);
+        x
+    }
+
+    fn seek_rank(&self, rank: usize) -> (usize, usize) {
+        let mut x = HEAD;
+        let mut traversed = 0;
+        for i in (0..self.level).rev() {
+            loop {
+                let f = self.forward(x, i);
+                if f == NIL || traversed + self.nodes[x].levels[i].span > rank {
+                    break;
+                }
+                traversed += self.nodes[x].levels[i].span;
+                x = f;
+            }
+        }
+        (traversed, x)
+    }
+
+    /// The nodes of ranks `[start, end)`, from `end - 1` down to `start` when `rev`
+    pub(crate) fn range(&self, start: usize, end: usize, rev: bool) -> Iter<'_> {
+        let end = end.min(self.len);
+        if start >= end {
+            return Iter {
+                list: self,
+                next: NIL,
+                remaining: 0,
+                rev,
+            };
+        }
+        Iter {
+            list: self,
+            next: self.node_at(if rev { end - 1 } else { start }),
+            remaining: end - start,
+            rev,
+        }
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_> {
+        self.range(0, self.len, false)
+    }
+}
+
+pub(crate) struct Iter<'a> {
+    list: &'a SkipList,
+    next: usize,
+    remaining: usize,
+    rev: bool,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a [u8], f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || self.next == NIL {
+            return None;
+        }
+        let node = &self.list.nodes[self.next];
+        self.next = if self.rev {
+            node.backward
+        } else {
+            node.levels[0].forward
+        };
+        self.remaining -= 1;
+        Some((node.member.as_slice(), node.score))
+    }
+}
+
+/// Score range limit, `(1.5` is exclusive, `-inf` / `+inf` are unbounded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    // whether `score` is before the range when used as the min
+    fn below(&self, score: f64) -> bool {
+        if self.exclusive {
+            score > self.value
+        } else {
+            score >= self.value
+        }
+    }
+
+    // whether `score` is within the range when used as the max
+    fn above(&self, score: f64) -> bool {
+        if self.exclusive {
+            score < self.value
+        } else {
+            score <= self.value
+        }
+    }
+}
+
+/// Lexicographical range limit of ZRANGE BYLEX: `[a` inclusive, `(a` exclusive, `-` and `+`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+impl LexBound {
+    fn below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(v) => member >= v.as_slice(),
+            LexBound::Exclusive(v) => member > v.as_slice(),
+        }
+    }
+
+    fn above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(v) => member <= v.as_slice(),
+            LexBound::Exclusive(v) => member < v.as_slice(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members<'a>(iter: impl Iterator<Item = (&'a [u8], f64)>) -> Vec<String> {
+        iter.map(|(m, _)| String::from_utf8_lossy(m).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_skiplist_rank() {
+        let mut list = SkipList::default();
+        for i in 0..1000 {
+            list.insert((i % 100) as f64, format!("m{:04}", i).into_bytes());
+        }
+        assert_eq!(list.len(), 1000);
+
+        let sorted = members(list.iter());
+        for (rank, member) in sorted.iter().enumerate().step_by(37) {
+            let score = member[1..].parse::<usize>().unwrap() % 100;
+            assert_eq!(list.rank(score as f64, member.as_bytes()), Some(rank));
+        }
+        assert_eq!(list.rank(1.0, b"missing"), None);
+
+        for i in (0..1000).step_by(2) {
+            assert!(list.remove((i % 100) as f64, format!("m{:04}", i).as_bytes()));
+        }
+        assert!(!list.remove(0.0, b"m0000"));
+        assert_eq!(list.len(), 500);
+        let sorted = members(list.iter());
+        assert_eq!(sorted.len(), 500);
+        assert_eq!(list.rank(99.0, b"m0999"), Some(499));
+        assert_eq!(members(list.range(498, 600, true)), vec!["m0999", "m0899"]);
+    }
+
+    #[test]
+    fn test_skiplist_ranges() {
+        let mut list = SkipList::default();
+        for (score, member) in [(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d")] {
+            list.insert(score, member.as_bytes().to_vec());
+        }
+
+        let bound = |value, exclusive| ScoreBound { value, exclusive };
+        let (start, end) = list.score_range(&bound(2.0, false), &bound(f64::INFINITY, false));
+        assert_eq!(members(list.range(start, end, false)), vec!["b", "c", "d"]);
+        let (start, end) = list.score_range(&bound(1.0, true), &bound(3.0, true));
+        assert_eq!((start, end), (1, 3));
+        let (start, end) = list.score_range(&bound(5.0, false), &bound(1.0, false));
+        assert_eq!(start, end);
+
+        let (start, end) = list.lex_range(&LexBound::Exclusive(b"a".to_vec()), &LexBound::Max);
+        assert_eq!(members(list.range(start, end, true)), vec!["d", "c", "b"]);
+    }
+}
@@ -0,0 +1,643 @@
+use bytes::Bytes;
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+
+use dashmap::mapref::entry::Entry;
+use dashmap::SharedValue;
+
+use crate::{
+    compact, Backend, BackendError, BulkString, ExpireCondition, RespFrame, SetExpiry, Value,
+};
+
+/// A string value. Strings that are the canonical form of a 64 bit integer are kept as the
+/// integer, INCR and friends update them without parsing.
+#[derive(Debug, Clone)]
+pub enum StringValue {
+    Int(i64),
+    Raw(Bytes),
+}
+
+impl StringValue {
+    pub fn to_bytes(&self) -> Bytes {
+        match self {
+            StringValue::Int(value) => Bytes::from(value.to_string()),
+            StringValue::Raw(value) => value.clone(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            StringValue::Int(value) => value.to_string().len(),
+            StringValue::Raw(value) => value.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value as an integer, like redis only the canonical form counts: no sign for
+    /// positive numbers, no leading zeros and no spaces
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            StringValue::Int(value) => Some(*value),
+            StringValue::Raw(value) => parse_int(value),
+        }
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        match self {
+            StringValue::Int(value) => Some(*value as f64),
+            StringValue::Raw(value) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .filter(|v| !v.is_nan()),
+        }
+    }
+
+    // the bytes to change in place, the buffer is reused when nobody else holds it
+    fn into_vec(self) -> Vec<u8> {
+        match self {
+            StringValue::Int(value) => value.to_string().into_bytes(),
+            StringValue::Raw(value) => value.into(),
+        }
+    }
+}
+
+fn parse_int(bytes: &[u8]) -> Option<i64> {
+    // i64::MIN has 20 characters
+    if bytes.is_empty() || bytes.len() > 20 {
+        return None;
+    }
+    let value = std::str::from_utf8(bytes).ok()?.parse::<i64>().ok()?;
+    // "+1", "01" and "-0" parse as well
+    (value.to_string().as_bytes() == bytes).then_some(value)
+}
+
+impl Default for StringValue {
+    fn default() -> Self {
+        StringValue::Raw(Bytes::new())
+    }
+}
+
+// two values are the same string no matter how they are stored
+impl PartialEq for StringValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (StringValue::Int(a), StringValue::Int(b)) => a == b,
+            _ => self.to_bytes() == other.to_bytes(),
+        }
+    }
+}
+
+impl From<Bytes> for StringValue {
+    fn from(value: Bytes) -> Self {
+        match parse_int(&value) {
+            Some(value) => StringValue::Int(value),
+            None => StringValue::Raw(compact(value)),
+        }
+    }
+}
+
+impl From<Vec<u8>> for StringValue {
+    fn from(value: Vec<u8>) -> Self {
+        match parse_int(&value) {
+            Some(value) => StringValue::Int(value),
+            None => StringValue::Raw(Bytes::from(value)),
+        }
+    }
+}
+
+impl From<BulkString> for StringValue {
+    fn from(value: BulkString) -> Self {
+        value.0.into()
+    }
+}
+
+impl From<i64> for StringValue {
+    fn from(value: i64) -> Self {
+        StringValue::Int(value)
+    }
+}
+
+impl From<StringValue> for RespFrame {
+    fn from(value: StringValue) -> Self {
+        BulkString::new(value.to_bytes()).into()
+    }
+}
+
+// Lock the shards at `indices` of a map, always in ascending order so that two commands
+// holding several shards at once never wait on each other
+fn lock_shards<'a, S, G>(
+    shards: &'a [S],
+    indices: impl Iterator<Item = usize>,
+    lock: impl Fn(&'a S) -> G,
+) -> BTreeMap<usize, G> {
+    let indices = indices.collect::<BTreeSet<_>>();
+    indices
+        .into_iter()
+        .map(|index| (index, lock(&shards[index])))
+        .collect()
+}
+
+/// The longest common subsequence of two strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommonSubsequence {
+    pub sequence: Vec<u8>,
+    /// the matching ranges of the first and the second string, inclusive and last first
+    pub matches: Vec<((usize, usize), (usize, usize))>,
+}
+
+impl Backend {
+    /// Run `f` on the string of `key` under the lock of its shard. `f` gets None when the key
+    /// does not exist, the key is created when `f` leaves a value and removed when it takes
+    /// the value away. `f` has to leave the value alone when it fails.
+    fn update_string<R>(
+        &self,
+        key: &[u8],
+        f: impl FnOnce(&mut Option<StringValue>) -> Result<R, BackendError>,
+    ) -> Result<R, BackendError> {
+        self.expire_if_needed(key);
+        let db = self.db();
+        match db.keyspace.entry(Bytes::copy_from_slice(key)) {
+            Entry::Occupied(mut entry) => {
+                let Value::String(value) = entry.get_mut() else {
+                    return Err(BackendError::WrongType);
+                };
+                let mut slot = Some(mem::take(value));
+                let ret = f(&mut slot);
+                match slot {
+                    Some(slot) => *value = slot,
+                    None => {
+                        entry.remove();
+                        db.expires.remove(key);
+                    }
+                }
+                ret
+            }
+            Entry::Vacant(entry) => {
+                let mut slot = None;
+                let ret = f(&mut slot);
+                if let Some(value) = slot {
+                    entry.insert(Value::String(value));
+                    db.expires.remove(key);
+                }
+                ret
+            }
+        }
+    }
+
+    // APPEND and SETRANGE do not build strings larger than a client may send
+    fn check_string_length(&self, len: usize) -> Result<(), BackendError> {
+        if len > self.config.proto_max_bulk_len {
+            return Err(BackendError::StringTooLong);
+        }
+        Ok(())
+    }
+
+    /// INCRBY and friends, the time to live is kept
+    pub fn incr_by(&self, key: &[u8], increment: i64) -> Result<i64, BackendError> {
+        let value = self.update_string(key, |slot| {
+            let current = match slot {
+                Some(value) => value.as_int().ok_or(BackendError::NotAnInteger)?,
+                None => 0,
+            };
+            let value = current
+                .checked_add(increment)
+                .ok_or(BackendError::Overflow)?;
+            *slot = Some(StringValue::Int(value));
+            Ok(value)
+        })?;
+        self.touch_key(key);
+        Ok(value)
+    }
+
+    /// INCRBYFLOAT, returns the new value as it is stored
+    pub fn incr_by_float(&self, key: &[u8], increment: f64) -> Result<StringValue, BackendError> {
+        let value = self.update_string(key, |slot| {
+            let current = match slot {
+                Some(value) => value.as_float().ok_or(BackendError::NotAFloat)?,
+                None => 0.0,
+            };
+            let value = current + increment;
+            if !value.is_finite() {
+                return Err(BackendError::NotFinite);
+            }
+            let value = StringValue::from(value.to_string().into_bytes());
+            *slot = Some(value.clone());
+            Ok(value)
+        })?;
+        self.touch_key(key);
+        Ok(value)
+    }
+
+    /// APPEND, returns the length of the string afterwards
+    pub fn append(&self, key: &[u8], suffix: &[u8]) -> Result<usize, BackendError> {
+        let len = self.update_string(key, |slot| {
+            let len = slot.as_ref().map_or(0, |value| value.len()) + suffix.len();
+            self.check_string_length(len)?;
+            let mut value = slot.take().map(StringValue::into_vec).unwrap_or_default();
+            value.extend_from_slice(suffix);
+            *slot = Some(value.into());
+            Ok(len)
+        })?;
+        self.touch_key(key);
+        Ok(len)
+    }
+
+    /// STRLEN, 0 when the key does not exist
+    pub fn strlen(&self, key: &[u8]) -> Result<usize, BackendError> {
+        Ok(self.get(key)?.map_or(0, |value| value.len()))
+    }
+
+    /// GETRANGE, `start` and `end` are inclusive and count from the end when negative
+    pub fn getrange(&self, key: &[u8], start: i64, end: i64) -> Result<Bytes, BackendError> {
+        let Some(value) = self.get(key)? else {
+            return Ok(Bytes::new());
+        };
+        let value = value.to_bytes();
+        let len = value.len() as i64;
+        let start = if start < 0 { len + start } else { start }.max(0);
+        let end = if end < 0 { len + end } else { end }.max(0).min(len - 1);
+        if len == 0 || start > end {
+            return Ok(Bytes::new());
+        }
+        Ok(value.slice(start as usize..=end as usize))
+    }
+
+    /// SETRANGE, overwrite the string from `offset` on, padding it with zero bytes when it
+    /// is shorter. Returns the length of the string afterwards.
+    pub fn setrange(&self, key: &[u8], offset: usize, data: &[u8]) -> Result<usize, BackendError> {
+        let len = self.update_string(key, |slot| {
+            let len = slot.as_ref().map_or(0, |value| value.len());
+            // nothing to write, a missing key is not created either
+            if data.is_empty() {
+                return Ok(len);
+            }
+            let end = offset
+                .checked_add(data.len())
+                .ok_or(BackendError::StringTooLong)?;
+            self.check_string_length(end)?;
+            let mut value = slot.take().map(StringValue::into_vec).unwrap_or_default();
+            if value.len() < end {
+                value.resize(end, 0);
+            }
+            value[offset..end].copy_from_slice(data);
+            let len = value.len();
+            *slot = Some(value.into());
+            Ok(len)
+        })?;
+        if !data.is_empty() {
+            self.touch_key(key);
+        }
+        Ok(len)
+    }
+
+    /// GETDEL, the value of the key before it was removed
+    pub fn getdel(&self, key: &[u8]) -> Result<Option<StringValue>, BackendError> {
+        let value = self.update_string(key, |slot| Ok(slot.take()))?;
+        if value.is_some() {
+            self.touch_key(key);
+        }
+        Ok(value)
+    }
+
+    /// GETEX, the value of the key with its time to live changed by `expiry`. A deadline in
+    /// the past removes the key.
+    pub fn getex(
+        &self,
+        key: &[u8],
+        expiry: SetExpiry,
+    ) -> Result<Option<StringValue>, BackendError> {
+        let Some(value) = self.get(key)? else {
+            return Ok(None);
+        };
+        // the ttl is changed through the same paths as PERSIST and EXPIRE, which take the lock
+        // of the key's shard before the expires map
+        match expiry {
+            SetExpiry::Keep => {}
+            SetExpiry::Clear => {
+                self.persist(key);
+            }
+            SetExpiry::At(deadline) => {
+                self.expire_at(key, deadline, ExpireCondition::Always);
+            }
+        }
+        Ok(Some(value))
+    }
+
+    /// MGET, None for the keys that do not exist or do not hold a string. The shards of all
+    /// the keys are read locked together, so an MSET is either seen whole or not at all.
+    pub fn mget(&self, keys: &[Bytes]) -> Vec<Option<StringValue>> {
+        for key in keys {
+            self.expire_if_needed(key);
+        }
+        let keyspace = &self.db().keyspace;
+        let guards = lock_shards(
+            keyspace.shards(),
+            keys.iter().map(|key| keyspace.determine_map(key)),
+            |shard| shard.read(),
+        );
+        keys.iter()
+            .map(|key| {
+                let value = guards
+                    .get(&keyspace.determine_map(key))
+                    .and_then(|shard| shard.get(key));
+                match value.map(SharedValue::get) {
+                    Some(Value::String(value)) => Some(value.clone()),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// MSET, the keys lose their time to live like with SET
+    pub fn mset(&self, pairs: Vec<(Bytes, StringValue)>) {
+        self.set_many(pairs, false);
+    }
+
+    /// MSETNX, returns false and writes nothing when any of the keys exists
+    pub fn msetnx(&self, pairs: Vec<(Bytes, StringValue)>) -> bool {
+        self.set_many(pairs, true)
+    }
+
+    // The shards of all the keys stay write locked until every value is in place: no reader
+    // sees some of the keys written and not the others, and with `nx` no key can be created
+    // between the check and the write.
+    fn set_many(&self, pairs: Vec<(Bytes, StringValue)>, nx: bool) -> bool {
+        for (key, _) in &pairs {
+            self.expire_if_needed(key);
+        }
+        let db = self.db();
+        let shards = pairs
+            .iter()
+            .map(|(key, _)| db.keyspace.determine_map(key))
+            .collect::<Vec<_>>();
+        let mut guards = lock_shards(db.keyspace.shards(), shards.iter().copied(), |shard| {
+            shard.write()
+        });
+        let exists = |(key, shard): (&Bytes, &usize)| {
+            guards
+                .get(shard)
+                .is_some_and(|shard| shard.contains_key(key))
+        };
+        if nx && pairs.iter().map(|(key, _)| key).zip(&shards).any(exists) {
+            return false;
+        }
+
+        let keys = pairs.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+        for ((key, value), shard) in pairs.into_iter().zip(shards) {
+            if let Some(guard) = guards.get_mut(&shard) {
+                db.expires.remove(&key);
+                guard.insert(key, SharedValue::new(Value::String(value)));
+            }
+        }
+        // WATCH has its own locks, touch the keys once the keyspace is released
+        drop(guards);
+        for key in keys {
+            self.touch_key(&key);
+        }
+        true
+    }
+
+    /// LCS, missing keys are empty strings
+    pub fn lcs(&self, first: &[u8], second: &[u8]) -> Result<CommonSubsequence, BackendError> {
+        let a = self.get(first)?.map(|v| v.to_bytes()).unwrap_or_default();
+        let b = self.get(second)?.map(|v| v.to_bytes()).unwrap_or_default();
+
+        // the table of the LCS lengths of every pair of prefixes
+        let width = b.len() + 1;
+        let cells = (a.len() + 1)
+            .checked_mul(width)
+            .filter(|cells| {
+                cells.saturating_mul(mem::size_of::<u32>()) <= self.config.proto_max_bulk_len
+            })
+            .ok_or(BackendError::LcsTooLong)?;
+        let mut table = vec![0u32; cells];
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                table[i * width + j] = if a[i - 1] == b[j - 1] {
+                    table[(i - 1) * width + j - 1] + 1
+                } else {
+                    table[(i - 1) * width + j].max(table[i * width + j - 1])
+                };
+            }
+        }
+
+        // walk back from the end, consecutive matches are one range
+        let mut lcs = CommonSubsequence {
+            sequence: Vec::with_capacity(table[cells - 1] as usize),
+            matches: Vec::new(),
+        };
+        let mut current: Option<((usize, usize), (usize, usize))> = None;
+        let (mut i, mut j) = (a.len(), b.len());
+        while i > 0 && j > 0 {
+            if a[i - 1] == b[j - 1] {
+                lcs.sequence.push(a[i - 1]);
+                current = Some(match current {
+                    Some(((_, a_end), (_, b_end))) => ((i - 1, a_end), (j - 1, b_end)),
+                    None => ((i - 1, i - 1), (j - 1, j - 1)),
+                });
+                i -= 1;
+                j -= 1;
+            } else {
+                lcs.matches.extend(current.take());
+                if table[(i - 1) * width + j] > table[i * width + j - 1] {
+                    i -= 1;
+                } else {
+                    j -= 1;
+                }
+            }
+        }
+        lcs.matches.extend(current);
+        lcs.sequence.reverse();
+        Ok(lcs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::now_millis;
+
+    #[test]
+    fn test_string_encoding() {
+        for (bytes, int) in [
+            ("12", Some(12)),
+            ("-7", Some(-7)),
+            ("0", Some(0)),
+            ("9223372036854775807", Some(i64::MAX)),
+            ("9223372036854775808", None),
+            ("012", None),
+            ("+1", None),
+            ("-0", None),
+            (" 1", None),
+            ("1.5", None),
+            ("", None),
+        ] {
+            let value = StringValue::from(Bytes::from(bytes));
+            assert_eq!(
+                matches!(value, StringValue::Int(_)),
+                int.is_some(),
+                "{}",
+                bytes
+            );
+            assert_eq!(value.as_int(), int);
+            assert_eq!(value.to_bytes(), bytes);
+            assert_eq!(value.len(), bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_incr() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(backend.incr_by(b"counter", 5)?, 5);
+        assert_eq!(backend.incr_by(b"counter", -7)?, -2);
+        assert_eq!(backend.get(b"counter")?, Some(StringValue::Int(-2)));
+
+        backend.set(b"max", StringValue::Int(i64::MAX));
+        assert_eq!(backend.incr_by(b"max", 1), Err(BackendError::Overflow));
+        assert_eq!(backend.get(b"max")?, Some(StringValue::Int(i64::MAX)));
+        backend.set(b"text", Bytes::from("a").into());
+        assert_eq!(backend.incr_by(b"text", 1), Err(BackendError::NotAnInteger));
+
+        assert_eq!(
+            backend.incr_by_float(b"float", 10.5)?,
+            StringValue::Raw(Bytes::from("10.5"))
+        );
+        assert_eq!(backend.incr_by_float(b"float", 0.5)?, StringValue::Int(11));
+        assert_eq!(
+            backend.incr_by_float(b"float", f64::INFINITY),
+            Err(BackendError::NotFinite)
+        );
+        assert_eq!(
+            backend.incr_by_float(b"text", 1.0),
+            Err(BackendError::NotAFloat)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ranges() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(backend.append(b"key", b"Hello")?, 5);
+        assert_eq!(backend.append(b"key", b" World")?, 11);
+        assert_eq!(backend.getrange(b"key", 0, 3)?, "Hell");
+        assert_eq!(backend.getrange(b"key", -3, -1)?, "rld");
+        assert_eq!(backend.getrange(b"key", 0, -100)?, "H");
+        assert_eq!(backend.getrange(b"key", -1, -5)?, "");
+        assert_eq!(backend.getrange(b"key", 10, 100)?, "d");
+        assert_eq!(backend.getrange(b"missing", 0, -1)?, "");
+
+        assert_eq!(backend.setrange(b"key", 6, b"Redis")?, 11);
+        assert_eq!(
+            backend.get(b"key")?,
+            Some(Bytes::from("Hello Redis").into())
+        );
+        assert_eq!(backend.setrange(b"pad", 2, b"x")?, 3);
+        assert_eq!(backend.get(b"pad")?, Some(Bytes::from("\0\0x").into()));
+        assert_eq!(backend.setrange(b"empty", 2, b"")?, 0);
+        assert!(!backend.contains_key(b"empty"));
+        assert_eq!(
+            backend.setrange(b"key", usize::MAX, b"x"),
+            Err(BackendError::StringTooLong)
+        );
+
+        // an integer stays one when digits are appended
+        backend.set(b"number", StringValue::Int(1));
+        assert_eq!(backend.append(b"number", b"2")?, 2);
+        assert_eq!(backend.get(b"number")?, Some(StringValue::Int(12)));
+        assert_eq!(backend.strlen(b"number")?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_getdel_getex() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.set(b"key", Bytes::from("v").into());
+        let deadline = now_millis() + 60_000;
+        let value = backend.getex(b"key", SetExpiry::At(deadline))?;
+        assert_eq!(value, Some(Bytes::from("v").into()));
+        assert_eq!(backend.expiry(b"key"), crate::KeyExpiry::At(deadline));
+        backend.getex(b"key", SetExpiry::Clear)?;
+        assert_eq!(backend.expiry(b"key"), crate::KeyExpiry::Persistent);
+        assert_eq!(backend.getex(b"missing", SetExpiry::Clear)?, None);
+
+        assert_eq!(backend.getdel(b"key")?, Some(Bytes::from("v").into()));
+        assert!(!backend.contains_key(b"key"));
+        backend.sadd(b"set", vec![b"a".to_vec()])?;
+        assert_eq!(backend.getdel(b"set"), Err(BackendError::WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mset_msetnx() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let pairs = |items: &[(&'static str, &'static str)]| {
+            items
+                .iter()
+                .map(|(key, value)| (Bytes::from(*key), Bytes::from(*value).into()))
+                .collect::<Vec<_>>()
+        };
+        backend.set_with(
+            b"a",
+            1.into(),
+            crate::SetCondition::Always,
+            SetExpiry::At(now_millis() + 60_000),
+        );
+        backend.mset(pairs(&[("a", "1"), ("b", "2"), ("a", "3")]));
+        assert_eq!(backend.expiry(b"a"), crate::KeyExpiry::Persistent);
+        backend.sadd(b"set", vec![b"x".to_vec()])?;
+        let keys = [&b"a"[..], b"b", b"set", b"missing"].map(Bytes::copy_from_slice);
+        assert_eq!(
+            backend.mget(&keys),
+            vec![Some(3.into()), Some(2.into()), None, None]
+        );
+
+        assert!(!backend.msetnx(pairs(&[("c", "1"), ("b", "1")])));
+        assert!(!backend.contains_key(b"c"));
+        assert!(backend.msetnx(pairs(&[("c", "1"), ("d", "1")])));
+        assert_eq!(backend.get(b"d")?, Some(1.into()));
+        Ok(())
+    }
+
+    // readers never see some keys of an MSET written and others not
+    #[test]
+    fn test_mset_is_atomic() {
+        let backend = Backend::new();
+        let keys = (0..32)
+            .map(|i| Bytes::from(format!("key:{}", i)))
+            .collect::<Vec<_>>();
+        let pairs = |value: i64| {
+            keys.iter()
+                .map(|key| (key.clone(), value.into()))
+                .collect::<Vec<_>>()
+        };
+        backend.mset(pairs(0));
+
+        std::thread::scope(|scope| {
+            let writer = scope.spawn(|| {
+                for value in 1..=500 {
+                    backend.mset(pairs(value));
+                }
+            });
+            while !writer.is_finished() {
+                let values = backend.mget(&keys);
+                assert!(values.iter().all(|value| *value == values[0]));
+            }
+        });
+        assert_eq!(backend.get(b"key:7"), Ok(Some(500.into())));
+    }
+
+    #[test]
+    fn test_lcs() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.set(b"key1", Bytes::from("ohmytext").into());
+        backend.set(b"key2", Bytes::from("mynewtext").into());
+        let lcs = backend.lcs(b"key1", b"key2")?;
+        assert_eq!(lcs.sequence, b"mytext");
+        assert_eq!(lcs.matches, vec![((4, 7), (5, 8)), ((2, 3), (0, 1))]);
+        assert_eq!(backend.lcs(b"key1", b"missing")?.sequence, b"");
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use bytes::Bytes;
+use std::cell::Cell;
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use dashmap::mapref::entry::Entry;
+
+use crate::Backend;
+
+/// Version counter of a key WATCHed by at least one client
+#[derive(Debug, Default)]
+pub struct WatchedKey {
+    version: u64,
+    watchers: usize,
+}
+
+thread_local! {
+    // changes to the dataset made on this thread, every touch counts one. Commands run on a
+    // single thread, so the difference across one tells whether it changed anything.
+    static DIRTY: Cell<u64> = const { Cell::new(0) };
+}
+
+fn mark_dirty() {
+    DIRTY.set(DIRTY.get().wrapping_add(1));
+}
+
+/// The changes made on this thread so far
+pub(crate) fn dirty() -> u64 {
+    DIRTY.get()
+}
+
+impl Backend {
+    /// Held while a command runs, so that EXEC can run its queue without any other client
+    /// command in between
+    pub fn lock_shared(&self) -> RwLockReadGuard<'_, ()> {
+        self.exec_lock.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Held by EXEC for the whole queue
+    pub fn lock_exclusive(&self) -> RwLockWriteGuard<'_, ()> {
+        self.exec_lock.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Called whenever `key` is modified, deleted or expires.
+    /// Only the keys some client watches keep a version.
+    pub(crate) fn touch_key(&self, key: &[u8]) {
+        mark_dirty();
+        if let Some(mut watched) = self.watched.get_mut(&self.watched_key(key)) {
+            watched.version += 1;
+        }
+    }
+
+    /// Touch the keys watched in `dbs` for which `exists` holds, before they are flushed or
+    /// swapped all at once
+    pub(crate) fn touch_watched(&self, dbs: &[usize], exists: impl Fn(&[u8]) -> bool) {
+        mark_dirty();
+        for mut watched in self.watched.iter_mut() {
+            let (db, key) = watched.key();
+            if dbs.contains(db) && exists(key) {
+                watched.version += 1;
+            }
+        }
+    }
+
+    fn watched_key(&self, key: &[u8]) -> (usize, Bytes) {
+        (self.db_index(), Bytes::copy_from_slice(key))
+    }
+
+    /// WATCH `key`, returns its current version
+    pub fn watch(&self, key: &[u8]) -> u64 {
+        self.expire_if_needed(key);
+        let mut watched = self.watched.entry(self.watched_key(key)).or_default();
+        watched.watchers += 1;
+        watched.version
+    }
+
+    pub fn unwatch(&self, key: &[u8]) {
+        if let Entry::Occupied(mut entry) = self.watched.entry(self.watched_key(key)) {
+            entry.get_mut().watchers -= 1;
+            if entry.get().watchers == 0 {
+                entry.remove();
+            }
+        }
+    }
+
+    /// The version of a watched key, it changes with every modification of the key
+    pub fn key_version(&self, key: &[u8]) -> u64 {
+        // an expired key counts as modified even if nothing touched it yet
+        self.expire_if_needed(key);
+        self.watched
+            .get(&self.watched_key(key))
+            .map(|v| v.version)
+            .unwrap_or(0)
+    }
+}
+
+/// The keys WATCHed by one connection with their database and their versions at the time,
+/// released on drop
+#[derive(Debug)]
+pub struct WatchedKeys {
+    backend: Backend,
+    keys: Vec<(usize, Bytes, u64)>,
+}
+
+impl WatchedKeys {
+    pub fn new(backend: Backend) -> Self {
+        WatchedKeys {
+            backend,
+            keys: Vec::new(),
+        }
+    }
+
+    /// WATCH `key` of the database `db`
+    pub fn watch(&mut self, db: usize, key: Bytes) {
+        if self.keys.iter().any(|(d, k, _)| *d == db && *k == key) {
+            return;
+        }
+        let version = self.backend.select(db).watch(&key);
+        self.keys.push((db, key, version));
+    }
+
+    /// Whether a watched key changed since it was watched
+    pub fn is_dirty(&self) -> bool {
+        self.keys
+            .iter()
+            .any(|(db, key, version)| self.backend.select(*db).key_version(key) != *version)
+    }
+
+    /// UNWATCH
+    pub fn clear(&mut self) {
+        for (db, key, _) in self.keys.drain(..) {
+            self.backend.select(db).unwatch(&key);
+        }
+    }
+}
+
+impl Drop for WatchedKeys {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_watched_keys() {
+        let backend = Backend::new();
+        let mut watched = WatchedKeys::new(backend.clone());
+        watched.watch(0, Bytes::from("key"));
+        backend.sadd(b"other", vec![b"a".to_vec()]).unwrap();
+        assert!(!watched.is_dirty());
+
+        backend.set(b"key", BulkString::new("v").into());
+        assert!(watched.is_dirty());
+
+        watched.clear();
+        assert!(backend.watched.is_empty());
+    }
+}
@@ -0,0 +1,580 @@
+use bytes::Bytes;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::backend::skiplist::{Iter, SkipList};
+use crate::{Backend, BackendError, LexBound, ScoreBound, SetCondition, SetOp, Value};
+
+/// A member with its score
+pub type ScoredMember = (Vec<u8>, f64);
+
+/// Sorted set: member -> score, plus a skiplist ordered by (score, member) for ranks and ranges
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: hashbrown::HashMap<Vec<u8>, f64, RandomState>,
+    index: SkipList,
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Add or update a member, returns its previous score
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            if old == score {
+                return Some(old);
+            }
+            self.index.remove(old, &member);
+        }
+        self.index.insert(score, member);
+        old
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.index.remove(score, member);
+        Some(score)
+    }
+
+    /// 0 based rank, from the highest score when `rev`
+    pub fn rank(&self, member: &[u8], rev: bool) -> Option<usize> {
+        let score = self.score(member)?;
+        let rank = self.index.rank(score, member)?;
+        Some(if rev { self.len() - 1 - rank } else { rank })
+    }
+
+    /// Members with their scores in no particular order
+    pub(crate) fn scores(&self) -> &hashbrown::HashMap<Vec<u8>, f64, RandomState> {
+        &self.scores
+    }
+
+    /// Members in (score, member) order
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], f64)> {
+        self.index.iter()
+    }
+
+    fn range(&self, start: usize, end: usize, rev: bool) -> Iter<'_> {
+        self.index.range(start, end, rev)
+    }
+}
+
+/// ZADD GT / LT: only update members whose score grows or shrinks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScoreComparison {
+    #[default]
+    Any,
+    Greater,
+    Less,
+}
+
+/// Options of ZADD
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddOptions {
+    pub condition: SetCondition,
+    pub comparison: ScoreComparison,
+    /// CH: count the updated members along with the added ones
+    pub changed: bool,
+}
+
+/// Which members ZRANGE selects
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    /// ranks, negative ones count from the end
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+/// How ZUNIONSTORE / ZINTERSTORE combine the scores of a member
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            // inf + -inf is NaN, which redis turns into 0
+            Aggregate::Sum => {
+                let sum = a + b;
+                if sum.is_nan() {
+                    0.0
+                } else {
+                    sum
+                }
+            }
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+// ranks of `[start, stop]` with redis semantics, None when empty
+fn rank_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        stop + len
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize + 1))
+}
+
+fn cloned<'a>(iter: impl Iterator<Item = (&'a [u8], f64)>) -> Vec<ScoredMember> {
+    iter.map(|(member, score)| (member.to_vec(), score))
+        .collect()
+}
+
+impl Backend {
+    /// ZADD, returns the number of members added, plus the number updated with `changed`
+    pub fn zadd(
+        &self,
+        key: &[u8],
+        members: Vec<(f64, Vec<u8>)>,
+        options: ZAddOptions,
+    ) -> Result<usize, BackendError> {
+        let (mut count, mut modified) = (0, false);
+        let ret = self.update::<SortedSet, _>(key, true, |zset| {
+            for (score, member) in members {
+                let updated = update_score(zset, member, score, false, options)?;
+                modified |= matches!(updated, Some(Updated::Added | Updated::Changed));
+                count += match updated {
+                    Some(Updated::Added) => 1,
+                    Some(Updated::Changed) if options.changed => 1,
+                    _ => 0,
+                };
+            }
+            Ok(())
+        });
+        if modified {
+            self.touch_key(key);
+        }
+        ret?.unwrap_or(Ok(()))?;
+        Ok(count)
+    }
+
+    /// ZINCRBY and ZADD INCR, returns the new score, or None when the options prevented it
+    pub fn zincrby(
+        &self,
+        key: &[u8],
+        member: Vec<u8>,
+        increment: f64,
+        options: ZAddOptions,
+    ) -> Result<Option<f64>, BackendError> {
+        let ret = self
+            .update::<SortedSet, _>(key, true, |zset| {
+                Ok(
+                    update_score(zset, member.clone(), increment, true, options)?
+                        .and_then(|_| zset.score(&member)),
+                )
+            })?
+            .unwrap_or(Ok(None))?;
+        if ret.is_some() {
+            self.touch_key(key);
+        }
+        Ok(ret)
+    }
+
+    pub fn zrem(&self, key: &[u8], members: &[Vec<u8>]) -> Result<usize, BackendError> {
+        let removed = self
+            .update::<SortedSet, _>(key, false, |zset| {
+                members
+                    .iter()
+                    .filter(|member| zset.remove(member).is_some())
+                    .count()
+            })?
+            .unwrap_or(0);
+        if removed > 0 {
+            self.touch_key(key);
+        }
+        Ok(removed)
+    }
+
+    pub fn zscore(&self, key: &[u8], member: &[u8]) -> Result<Option<f64>, BackendError> {
+        Ok(self
+            .read::<SortedSet>(key)?
+            .and_then(|zset| zset.score(member)))
+    }
+
+    pub fn zcard(&self, key: &[u8]) -> Result<usize, BackendError> {
+        Ok(self
+            .read::<SortedSet>(key)?
+            .map(|zset| zset.len())
+            .unwrap_or(0))
+    }
+
+    /// ZRANK / ZREVRANK, the rank of the member and its score
+    pub fn zrank(
+        &self,
+        key: &[u8],
+        member: &[u8],
+        rev: bool,
+    ) -> Result<Option<(usize, f64)>, BackendError> {
+        Ok(self.read::<SortedSet>(key)?.and_then(|zset| {
+            let rank = zset.rank(member, rev)?;
+            Some((rank, zset.score(member)?))
+        }))
+    }
+
+    /// ZRANGE, `limit` is the offset and count of LIMIT, a negative count returns everything
+    pub fn zrange(
+        &self,
+        key: &[u8],
+        by: &ZRangeBy,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+    ) -> Result<Vec<ScoredMember>, BackendError> {
+        let Some(zset) = self.read::<SortedSet>(key)? else {
+            return Ok(vec![]);
+        };
+        let len = zset.len();
+        let (start, end) = match by {
+            ZRangeBy::Rank(start, stop) => {
+                let Some((start, end)) = rank_range(*start, *stop, len) else {
+                    return Ok(vec![]);
+                };
+                if rev {
+                    (len - end, len - start)
+                } else {
+                    (start, end)
+                }
+            }
+            ZRangeBy::Score(min, max) => zset.index.score_range(min, max),
+            ZRangeBy::Lex(min, max) => zset.index.lex_range(min, max),
+        };
+
+        let members = zset.range(start, end, rev);
+        Ok(match limit {
+            Some((offset, _)) if offset < 0 => vec![],
+            Some((offset, count)) if count >= 0 => {
+                cloned(members.skip(offset as usize).take(count as usize))
+            }
+            Some((offset, _)) => cloned(members.skip(offset as usize)),
+            None => cloned(members),
+        })
+    }
+
+    /// ZCOUNT / ZLEXCOUNT, the number of members within the score or lexicographical range
+    pub fn zcount(&self, key: &[u8], by: &ZRangeBy) -> Result<usize, BackendError> {
+        let Some(zset) = self.read::<SortedSet>(key)? else {
+            return Ok(0);
+        };
+        let (start, end) = match by {
+            ZRangeBy::Rank(start, stop) => rank_range(*start, *stop, zset.len()).unwrap_or((0, 0)),
+            ZRangeBy::Score(min, max) => zset.index.score_range(min, max),
+            ZRangeBy::Lex(min, max) => zset.index.lex_range(min, max),
+        };
+        Ok(end - start)
+    }
+
+    /// ZPOPMIN / ZPOPMAX
+    pub fn zpop(
+        &self,
+        key: &[u8],
+        count: usize,
+        max: bool,
+    ) -> Result<Vec<ScoredMember>, BackendError> {
+        let popped = self
+            .update::<SortedSet, _>(key, false, |zset| {
+                let len = zset.len();
+                let popped = if max {
+                    cloned(zset.range(len.saturating_sub(count), len, true))
+                } else {
+                    cloned(zset.range(0, count, false))
+                };
+                for (member, _) in &popped {
+                    zset.remove(member);
+                }
+                popped
+            })?
+            .unwrap_or_default();
+        if !popped.is_empty() {
+            self.touch_key(key);
+        }
+        Ok(popped)
+    }
+
+    /// ZRANDMEMBER: a positive count returns distinct members,
+    /// a negative one returns `-count` members that may repeat
+    pub fn zrandmember(&self, key: &[u8], count: i64) -> Result<Vec<ScoredMember>, BackendError> {
+        let Some(zset) = self.read::<SortedSet>(key)? else {
+            return Ok(vec![]);
+        };
+        let mut rng = rand::thread_rng();
+        if count >= 0 {
+            return Ok(cloned(
+                zset.iter()
+                    .choose_multiple(&mut rng, count as usize)
+                    .into_iter(),
+            ));
+        }
+
+        let len = zset.len();
+        Ok((0..count.unsigned_abs())
+            .filter_map(|_| zset.range(rng.gen_range(0..len), len, false).next())
+            .map(|(member, score)| (member.to_vec(), score))
+            .collect())
+    }
+
+    /// ZUNIONSTORE / ZINTERSTORE: plain sets count as sorted sets with scores of 1.
+    /// `destination` is overwritten and deleted when the result is empty.
+    pub fn zstore(
+        &self,
+        op: SetOp,
+        destination: &[u8],
+        keys: &[Bytes],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<usize, BackendError> {
+        let mut inputs = Vec::with_capacity(keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            self.expire_if_needed(key);
+            let weight = weights.get(i).copied().unwrap_or(1.0);
+            let weighted = |score: f64| {
+                let score = score * weight;
+                // 0 * inf
+                if score.is_nan() {
+                    0.0
+                } else {
+                    score
+                }
+            };
+            // each input is copied on its own, never holding two shards at once
+            let members = match self.db().keyspace.get(key).as_deref() {
+                Some(Value::ZSet(zset)) => zset
+                    .scores
+                    .iter()
+                    .map(|(member, score)| (member.clone(), weighted(*score)))
+                    .collect::<HashMap<_, _>>(),
+                Some(Value::Set(set)) => set
+                    .iter()
+                    .map(|member| (member.clone(), weighted(1.0)))
+                    .collect(),
+                Some(_) => return Err(BackendError::WrongType),
+                None => HashMap::new(),
+            };
+            inputs.push(members);
+        }
+
+        let mut inputs = inputs.into_iter();
+        let mut result = inputs.next().unwrap_or_default();
+        for input in inputs {
+            match op {
+                SetOp::Union => {
+                    for (member, score) in input {
+                        result
+                            .entry(member)
+                            .and_modify(|v| *v = aggregate.apply(*v, score))
+                            .or_insert(score);
+                    }
+                }
+                SetOp::Inter => {
+                    result.retain(|member, v| match input.get(member) {
+                        Some(score) => {
+                            *v = aggregate.apply(*v, *score);
+                            true
+                        }
+                        None => false,
+                    });
+                }
+                SetOp::Diff => result.retain(|member, _| !input.contains_key(member)),
+            }
+        }
+
+        let len = result.len();
+        self.remove_key(destination);
+        if len > 0 {
+            let mut zset = SortedSet::default();
+            for (member, score) in result {
+                zset.insert(member, score);
+            }
+            self.insert_value(destination, Value::ZSet(zset));
+        }
+        Ok(len)
+    }
+}
+
+enum Updated {
+    Added,
+    Changed,
+    Unchanged,
+}
+
+// ZADD of one member, None when the options skipped it
+fn update_score(
+    zset: &mut SortedSet,
+    member: Vec<u8>,
+    score: f64,
+    incr: bool,
+    options: ZAddOptions,
+) -> Result<Option<Updated>, BackendError> {
+    let current = zset.score(&member);
+    match (options.condition, current) {
+        (SetCondition::Nx, Some(_)) | (SetCondition::Xx, None) => return Ok(None),
+        _ => {}
+    }
+
+    let Some(current) = current else {
+        zset.insert(member, score);
+        return Ok(Some(Updated::Added));
+    };
+    let new = if incr { current + score } else { score };
+    if new.is_nan() {
+        return Err(BackendError::NotANumber);
+    }
+    let allowed = match options.comparison {
+        ScoreComparison::Any => true,
+        ScoreComparison::Greater => new > current,
+        ScoreComparison::Less => new < current,
+    };
+    if !allowed {
+        return Ok(None);
+    }
+    if new == current {
+        return Ok(Some(Updated::Unchanged));
+    }
+    zset.insert(member, new);
+    Ok(Some(Updated::Changed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(f64, &str)]) -> Vec<(f64, Vec<u8>)> {
+        items
+            .iter()
+            .map(|(score, member)| (*score, member.as_bytes().to_vec()))
+            .collect()
+    }
+
+    fn names(members: Vec<ScoredMember>) -> Vec<String> {
+        members
+            .into_iter()
+            .map(|(member, _)| String::from_utf8(member).unwrap())
+            .collect()
+    }
+
+    const ANY: ZAddOptions = ZAddOptions {
+        condition: SetCondition::Always,
+        comparison: ScoreComparison::Any,
+        changed: false,
+    };
+
+    #[test]
+    fn test_zadd_options() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.zadd(b"z", pairs(&[(1.0, "a"), (2.0, "b")]), ANY)?,
+            2
+        );
+
+        let gt_ch = ZAddOptions {
+            comparison: ScoreComparison::Greater,
+            changed: true,
+            ..ANY
+        };
+        assert_eq!(
+            backend.zadd(b"z", pairs(&[(0.0, "a"), (5.0, "b")]), gt_ch)?,
+            1
+        );
+        assert_eq!(backend.zscore(b"z", b"a")?, Some(1.0));
+        assert_eq!(backend.zscore(b"z", b"b")?, Some(5.0));
+
+        let xx = ZAddOptions {
+            condition: SetCondition::Xx,
+            ..ANY
+        };
+        assert_eq!(backend.zincrby(b"z", b"c".to_vec(), 1.0, xx)?, None);
+        assert_eq!(backend.zincrby(b"z", b"a".to_vec(), 2.5, xx)?, Some(3.5));
+        assert_eq!(backend.zrank(b"z", b"a", false)?, Some((0, 3.5)));
+        assert_eq!(backend.zrank(b"z", b"a", true)?, Some((1, 3.5)));
+
+        backend.zadd(b"inf", pairs(&[(f64::INFINITY, "a")]), ANY)?;
+        assert_eq!(
+            backend.zincrby(b"inf", b"a".to_vec(), f64::NEG_INFINITY, ANY),
+            Err(BackendError::NotANumber)
+        );
+
+        // nothing added, no key created
+        assert_eq!(backend.zadd(b"none", pairs(&[(1.0, "a")]), xx)?, 0);
+        assert!(!backend.contains_key(b"none"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let members = pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c"), (4.0, "d")]);
+        backend.zadd(b"z", members, ANY)?;
+
+        let ret = backend.zrange(b"z", &ZRangeBy::Rank(0, -2), true, None)?;
+        assert_eq!(names(ret), vec!["d", "c", "b"]);
+
+        let bound = |value, exclusive| ScoreBound { value, exclusive };
+        let by = ZRangeBy::Score(bound(1.0, true), bound(f64::INFINITY, false));
+        let ret = backend.zrange(b"z", &by, false, Some((1, 1)))?;
+        assert_eq!(ret, vec![(b"c".to_vec(), 3.0)]);
+        assert_eq!(backend.zcount(b"z", &by)?, 3);
+
+        let by = ZRangeBy::Lex(LexBound::Inclusive(b"b".to_vec()), LexBound::Max);
+        assert_eq!(
+            names(backend.zrange(b"z", &by, true, Some((0, -1)))?),
+            vec!["d", "c", "b"]
+        );
+
+        assert_eq!(names(backend.zpop(b"z", 2, true)?), vec!["d", "c"]);
+        assert_eq!(names(backend.zpop(b"z", 5, false)?), vec!["a", "b"]);
+        assert!(!backend.contains_key(b"z"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zstore() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.zadd(b"a", pairs(&[(1.0, "x"), (2.0, "y")]), ANY)?;
+        backend.sadd(b"b", vec![b"y".to_vec(), b"z".to_vec()])?;
+        let keys = vec![Bytes::from("a"), Bytes::from("b")];
+
+        assert_eq!(
+            backend.zstore(SetOp::Union, b"u", &keys, &[2.0, 10.0], Aggregate::Sum)?,
+            3
+        );
+        assert_eq!(backend.zscore(b"u", b"y")?, Some(14.0));
+        assert_eq!(
+            backend.zstore(SetOp::Inter, b"i", &keys, &[], Aggregate::Max)?,
+            1
+        );
+        assert_eq!(backend.zscore(b"i", b"y")?, Some(2.0));
+
+        backend.set(b"s", crate::BulkString::new("v").into());
+        let keys = vec![Bytes::from("a"), Bytes::from("s")];
+        assert_eq!(
+            backend.zstore(SetOp::Union, b"u", &keys, &[], Aggregate::Sum),
+            Err(BackendError::WrongType)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use bytes::Bytes;
+
+use std::time::Duration;
+
+use crate::cmd::list::{extract_list_end, syntax_error};
+use crate::cmd::{extract_args, extract_integer, extract_key, extract_string};
+use crate::{
+    BLMPop, BLMove, BLPop, BRPop, Backend, BackendError, BlockingOp, BulkString, CommandError,
+    CommandExecutor, ListEnd, RespArray, RespFrame, RespNull, Served,
+};
+
+// the sync path serves the commands replayed from the aof, which never block
+impl CommandExecutor for BLPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        pop_reply(backend.pop_first(&self.keys, &pop_one(ListEnd::Left)))
+    }
+}
+
+impl CommandExecutor for BRPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        pop_reply(backend.pop_first(&self.keys, &pop_one(ListEnd::Right)))
+    }
+}
+
+impl CommandExecutor for BLMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (keys, op) = self.op();
+        move_reply(backend.pop_first(&keys, &op))
+    }
+}
+
+impl CommandExecutor for BLMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let op = self.op();
+        mpop_reply(backend.pop_first(&self.keys, &op))
+    }
+}
+
+impl BLPop {
+    pub(crate) async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .blocking_pop(self.keys, pop_one(ListEnd::Left), self.timeout)
+            .await;
+        pop_reply(ret)
+    }
+}
+
+impl BRPop {
+    pub(crate) async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .blocking_pop(self.keys, pop_one(ListEnd::Right), self.timeout)
+            .await;
+        pop_reply(ret)
+    }
+}
+
+impl BLMove {
+    fn op(self) -> (Vec<Bytes>, BlockingOp) {
+        let op = BlockingOp::Move {
+            destination: self.destination,
+            from: self.from,
+            to: self.to,
+        };
+        (vec![self.source], op)
+    }
+
+    pub(crate) async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let timeout = self.timeout;
+        let (keys, op) = self.op();
+        move_reply(backend.blocking_pop(keys, op, timeout).await)
+    }
+}
+
+impl BLMPop {
+    fn op(&self) -> BlockingOp {
+        BlockingOp::Pop {
+            end: self.end,
+            count: self.count,
+        }
+    }
+
+    pub(crate) async fn execute_blocking(self, backend: &Backend) -> RespFrame {
+        let op = self.op();
+        mpop_reply(backend.blocking_pop(self.keys, op, self.timeout).await)
+    }
+}
+
+fn pop_one(end: ListEnd) -> BlockingOp {
+    BlockingOp::Pop { end, count: 1 }
+}
+
+// [key, element], or a null array when timed out
+fn pop_reply(ret: Result<Option<Served>, BackendError>) -> RespFrame {
+    match ret {
+        Ok(Some((key, mut values))) => match values.pop() {
+            Some(value) => RespArray::new(vec![BulkString::new(key).into(), value]).into(),
+            None => RespArray::nill_new().into(),
+        },
+        Ok(None) => RespArray::nill_new().into(),
+        Err(e) => e.into(),
+    }
+}
+
+fn move_reply(ret: Result<Option<Served>, BackendError>) -> RespFrame {
+    match ret {
+        Ok(Some((_, mut values))) => values.pop().unwrap_or(RespFrame::Null(RespNull)),
+        Ok(None) => RespFrame::Null(RespNull),
+        Err(e) => e.into(),
+    }
+}
+
+// [key, [element ...]], or a null array when timed out
+fn mpop_reply(ret: Result<Option<Served>, BackendError>) -> RespFrame {
+    match ret {
+        Ok(Some((key, values))) => RespArray::new(vec![
+            BulkString::new(key).into(),
+            RespArray::new(values).into(),
+        ])
+        .into(),
+        Ok(None) => RespArray::nill_new().into(),
+        Err(e) => e.into(),
+    }
+}
+
+// timeouts are seconds with decimals, zero blocks forever
+fn extract_timeout(frame: Option<RespFrame>) -> Result<Option<Duration>, CommandError> {
+    let timeout = extract_string(frame, "timeout")?
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| {
+            CommandError::InvalidArguments("timeout is not a float or out of range".to_string())
+        })?;
+    if timeout < 0.0 {
+        return Err(CommandError::InvalidArguments(
+            "timeout is negative".to_string(),
+        ));
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(timeout).map(Some).map_err(|_| {
+        CommandError::InvalidArguments("timeout is not a float or out of range".to_string())
+    })
+}
+
+// BLPOP key [key ...] timeout
+fn parse_blocking_pop(value: RespArray) -> Result<(Vec<Bytes>, Option<Duration>), CommandError> {
+    let mut args = extract_args(value, 1)?;
+    let timeout = extract_timeout(args.pop())?;
+    let keys = args
+        .into_iter()
+        .map(|arg| extract_key(Some(arg), "key"))
+        .collect::<Result<Vec<Bytes>, CommandError>>()?;
+    Ok((keys, timeout))
+}
+
+impl TryFrom<RespArray> for BLPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_blocking_pop(value)?;
+        Ok(BLPop { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BRPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = parse_blocking_pop(value)?;
+        Ok(BRPop { keys, timeout })
+    }
+}
+
+// BLMOVE source destination LEFT | RIGHT LEFT | RIGHT timeout
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BLMove {
+            source: extract_key(args.next(), "source")?,
+            destination: extract_key(args.next(), "destination")?,
+            from: extract_list_end(args.next())?,
+            to: extract_list_end(args.next())?,
+            timeout: extract_timeout(args.next())?,
+        })
+    }
+}
+
+// BLMPOP timeout numkeys key [key ...] LEFT | RIGHT [COUNT count]
+impl TryFrom<RespArray> for BLMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = extract_timeout(args.next())?;
+        let numkeys = usize::try_from(extract_integer(args.next())?)
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                CommandError::InvalidArguments("numkeys should be greater than 0".to_string())
+            })?;
+        let keys = args
+            .by_ref()
+            .take(numkeys)
+            .map(|arg| extract_key(Some(arg), "key"))
+            .collect::<Result<Vec<Bytes>, CommandError>>()?;
+        if keys.len() != numkeys {
+            return Err(syntax_error());
+        }
+        let end = extract_list_end(args.next())?;
+
+        let count = match args.next() {
+            None => 1,
+            Some(arg) => {
+                if !extract_string(Some(arg), "option")?.eq_ignore_ascii_case("count") {
+                    return Err(syntax_error());
+                }
+                usize::try_from(extract_integer(args.next())?)
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| {
+                        CommandError::InvalidArguments("count should be greater than 0".to_string())
+                    })?
+            }
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(BLMPop {
+            keys,
+            end,
+            count,
+            timeout,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::cmd::command;
+
+    #[test]
+    fn test_blocking_parse() -> Result<()> {
+        let cmd = BLPop::try_from(command(&["blpop", "a", "b", "0.5"]))?;
+        assert_eq!(cmd.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(cmd.timeout, Some(Duration::from_millis(500)));
+        assert_eq!(
+            BRPop::try_from(command(&["brpop", "a", "0"]))?.timeout,
+            None
+        );
+        assert!(BLPop::try_from(command(&["blpop", "a", "-1"])).is_err());
+        assert!(BLPop::try_from(command(&["blpop", "a", "soon"])).is_err());
+
+        let cmd = BLMPop::try_from(command(&[
+            "blmpop", "1", "2", "a", "b", "RIGHT", "COUNT", "3",
+        ]))?;
+        assert_eq!(cmd.keys.len(), 2);
+        assert_eq!((cmd.end, cmd.count), (ListEnd::Right, 3));
+        assert!(BLMPop::try_from(command(&["blmpop", "1", "3", "a", "b", "LEFT"])).is_err());
+        assert!(
+            BLMPop::try_from(command(&["blmpop", "1", "1", "a", "LEFT", "COUNT", "0"])).is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocking_replies() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BLPop::try_from(command(&["blpop", "a", "0.01"]))?;
+        assert_eq!(
+            cmd.execute_blocking(&backend).await,
+            RespArray::nill_new().into()
+        );
+
+        backend.list_push(
+            b"b",
+            ListEnd::Right,
+            vec![BulkString::new("1").into(), BulkString::new("2").into()],
+            false,
+        )?;
+        let cmd = BRPop::try_from(command(&["brpop", "a", "b", "0"]))?;
+        assert_eq!(
+            cmd.execute_blocking(&backend).await,
+            RespArray::new(vec![
+                BulkString::new("b").into(),
+                BulkString::new("2").into()
+            ])
+            .into()
+        );
+        let cmd = BLMove::try_from(command(&["blmove", "b", "c", "LEFT", "LEFT", "0"]))?;
+        assert_eq!(cmd.execute(&backend), BulkString::new("1").into());
+        Ok(())
+    }
+}
@@ -0,0 +1,1011 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::cmd::set::extract_members;
+use crate::cmd::{extract_args, extract_integer, extract_string, CommandsSubcommand};
+use crate::{
+    Append, BLMPop, BLMove, BLPop, BRPop, Backend, BgRewriteAof, BgSave, BulkString, Command,
+    CommandError, CommandExecutor, Commands, DbSize, Decr, DecrBy, Del, Discard, Echo, Exec,
+    Exists, Expire, ExpireAt, ExpireTime, FlushAll, FlushDb, Get, GetDel, GetEx, GetRange, HGet,
+    HGetAll, HScan, HSet, Hello, Incr, IncrBy, IncrByFloat, Keys, LIndex, LInsert, LLen, LMove,
+    LPop, LPos, LPush, LPushX, LRange, LRem, LSet, LTrim, Lcs, MGet, MSet, MSetNx, Move, Multi,
+    PExpire, PExpireAt, PSetEx, PSubscribe, PTtl, PUnsubscribe, Persist, Ping, PubSub, Publish,
+    RPop, RPopLPush, RPush, RPushX, RespArray, RespFrame, RespMap, RespNull, RespSet, SAdd, SCard,
+    SDiff, SDiffStore, SInter, SInterCard, SInterStore, SIsMember, SMIsMember, SMembers, SMove,
+    SPop, SRandMember, SRem, SScan, SUnion, SUnionStore, Save, Scan, Select, Set, SetEx, SetNx,
+    SetRange, SimpleString, StrLen, Subscribe, SwapDb, Touch, Ttl, Type, Unlink, Unsubscribe,
+    Unwatch, Watch, ZAdd, ZCard, ZCount, ZIncrBy, ZInterStore, ZLexCount, ZPopMax, ZPopMin,
+    ZRandMember, ZRange, ZRangeByScore, ZRank, ZRem, ZRevRank, ZScan, ZScore, ZUnionStore,
+};
+
+/// An entry of the command table: how to parse the command and what COMMAND reports about it
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// the number of arguments with the name, `-n` when it takes at least `n` of them
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    /// position of the first key, 0 when the command takes no key at a fixed position
+    pub first_key: i64,
+    /// position of the last key, negative positions count from the end
+    pub last_key: i64,
+    pub step: i64,
+    /// position of the number of keys for commands like ZUNIONSTORE, the keys follow it
+    pub numkeys: Option<usize>,
+    pub acl_categories: &'static [&'static str],
+    pub group: &'static str,
+    pub since: &'static str,
+    pub summary: &'static str,
+    pub(crate) parse: fn(RespArray) -> Result<Command, CommandError>,
+}
+
+fn parse<T>(value: RespArray) -> Result<Command, CommandError>
+where
+    T: TryFrom<RespArray, Error = CommandError> + Into<Command>,
+{
+    Ok(T::try_from(value)?.into())
+}
+
+impl CommandSpec {
+    const fn new<T>(
+        name: &'static str,
+        arity: i64,
+        flags: &'static [&'static str],
+        acl_categories: &'static [&'static str],
+    ) -> Self
+    where
+        T: TryFrom<RespArray, Error = CommandError> + Into<Command>,
+    {
+        CommandSpec {
+            name,
+            arity,
+            flags,
+            first_key: 0,
+            last_key: 0,
+            step: 0,
+            numkeys: None,
+            acl_categories,
+            group: "",
+            since: "",
+            summary: "",
+            parse: parse::<T>,
+        }
+    }
+
+    const fn keys(self, first_key: i64, last_key: i64, step: i64) -> Self {
+        CommandSpec {
+            first_key,
+            last_key,
+            step,
+            ..self
+        }
+    }
+
+    const fn numkeys(self, position: usize) -> Self {
+        CommandSpec {
+            numkeys: Some(position),
+            ..self
+        }
+    }
+
+    const fn doc(self, group: &'static str, since: &'static str, summary: &'static str) -> Self {
+        CommandSpec {
+            group,
+            since,
+            summary,
+            ..self
+        }
+    }
+
+    /// Whether the command may change the dataset, the ones the aof records
+    pub fn is_write(&self) -> bool {
+        self.flags.contains(&"write")
+    }
+
+    /// Whether MULTI refuses to queue the command, like SAVE
+    pub fn is_no_multi(&self) -> bool {
+        self.flags.contains(&"no_multi")
+    }
+
+    /// Whether a request of `argc` frames, the name included, has the right number of arguments
+    pub fn check_arity(&self, argc: usize) -> bool {
+        let argc = argc as i64;
+        if self.arity >= 0 {
+            argc == self.arity
+        } else {
+            argc >= -self.arity
+        }
+    }
+
+    /// Positions of the keys in `args`, the name of the command at 0
+    pub fn key_positions(&self, args: &[RespFrame]) -> Result<Vec<usize>, CommandError> {
+        let mut positions = Vec::new();
+        if self.first_key > 0 {
+            let last = if self.last_key < 0 {
+                args.len() as i64 + self.last_key
+            } else {
+                self.last_key
+            };
+            let mut i = self.first_key;
+            while i <= last && i < args.len() as i64 {
+                positions.push(i as usize);
+                i += self.step.max(1);
+            }
+        }
+        if let Some(position) = self.numkeys {
+            let count = extract_integer(args.get(position).cloned())?;
+            if count <= 0 || position + count as usize >= args.len() {
+                return Err(CommandError::InvalidArguments(
+                    "Invalid arguments specified for command".to_string(),
+                ));
+            }
+            positions.extend(position + 1..=position + count as usize);
+        }
+        Ok(positions)
+    }
+}
+
+/// The commands this server knows about
+pub static COMMAND_TABLE: &[CommandSpec] = &[
+    // string
+    CommandSpec::new::<Get>("get", 2, &["readonly", "fast"], &["@read", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc("string", "1.0.0", "Returns the string value of a key."),
+    CommandSpec::new::<Set>("set", -3, &["write", "denyoom"], &["@write", "@string", "@slow"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "1.0.0",
+            "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+        ),
+    CommandSpec::new::<SetNx>("setnx", 3, &["write", "denyoom", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc("string", "1.0.0", "Set the string value of a key only when the key doesn't exist."),
+    CommandSpec::new::<SetEx>("setex", 4, &["write", "denyoom"], &["@write", "@string", "@slow"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "2.0.0",
+            "Sets the string value and expiration time of a key. Creates the key if it doesn't exist.",
+        ),
+    CommandSpec::new::<PSetEx>("psetex", 4, &["write", "denyoom"], &["@write", "@string", "@slow"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "2.6.0",
+            "Sets both string value and expiration time in milliseconds of a key. The key is created if it doesn't exist.",
+        ),
+    CommandSpec::new::<GetDel>("getdel", 2, &["write", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc("string", "6.2.0", "Returns the string value of a key after deleting the key."),
+    CommandSpec::new::<GetEx>("getex", -2, &["write", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "6.2.0",
+            "Returns the string value of a key after setting its expiration time.",
+        ),
+    CommandSpec::new::<Incr>("incr", 2, &["write", "denyoom", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "1.0.0",
+            "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new::<Decr>("decr", 2, &["write", "denyoom", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "1.0.0",
+            "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new::<IncrBy>("incrby", 3, &["write", "denyoom", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "1.0.0",
+            "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new::<DecrBy>("decrby", 3, &["write", "denyoom", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "1.0.0",
+            "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist.",
+        ),
+    CommandSpec::new::<IncrByFloat>(
+        "incrbyfloat",
+        3,
+        &["write", "denyoom", "fast"],
+        &["@write", "@string", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc(
+        "string",
+        "2.6.0",
+        "Increment the floating point value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+    ),
+    CommandSpec::new::<Append>("append", 3, &["write", "denyoom", "fast"], &["@write", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "2.0.0",
+            "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+        ),
+    CommandSpec::new::<StrLen>("strlen", 2, &["readonly", "fast"], &["@read", "@string", "@fast"])
+        .keys(1, 1, 1)
+        .doc("string", "2.2.0", "Returns the length of a string value."),
+    CommandSpec::new::<GetRange>("getrange", 4, &["readonly"], &["@read", "@string", "@slow"])
+        .keys(1, 1, 1)
+        .doc("string", "2.4.0", "Returns a substring of the string stored at a key."),
+    CommandSpec::new::<SetRange>("setrange", 4, &["write", "denyoom"], &["@write", "@string", "@slow"])
+        .keys(1, 1, 1)
+        .doc(
+            "string",
+            "2.2.0",
+            "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+        ),
+    CommandSpec::new::<Lcs>("lcs", -3, &["readonly"], &["@read", "@string", "@slow"])
+        .keys(1, 2, 1)
+        .doc("string", "7.0.0", "Finds the longest common substring."),
+    CommandSpec::new::<MGet>("mget", -2, &["readonly", "fast"], &["@read", "@string", "@fast"])
+        .keys(1, -1, 1)
+        .doc("string", "1.0.0", "Atomically returns the string values of one or more keys."),
+    CommandSpec::new::<MSet>("mset", -3, &["write", "denyoom"], &["@write", "@string", "@slow"])
+        .keys(1, -1, 2)
+        .doc("string", "1.0.1", "Atomically creates or modifies the string values of one or more keys."),
+    CommandSpec::new::<MSetNx>("msetnx", -3, &["write", "denyoom"], &["@write", "@string", "@slow"])
+        .keys(1, -1, 2)
+        .doc(
+            "string",
+            "1.0.1",
+            "Atomically modifies the string values of one or more keys only when all keys don't exist.",
+        ),
+    // hash
+    CommandSpec::new::<HGet>("hget", 3, &["readonly", "fast"], &["@read", "@hash", "@fast"])
+        .keys(1, 1, 1)
+        .doc("hash", "2.0.0", "Returns the value of a field in a hash."),
+    CommandSpec::new::<HGetAll>("hgetall", 2, &["readonly"], &["@read", "@hash", "@slow"])
+        .keys(1, 1, 1)
+        .doc("hash", "2.0.0", "Returns all fields and values in a hash."),
+    CommandSpec::new::<HSet>(
+        "hset",
+        4,
+        &["write", "denyoom", "fast"],
+        &["@write", "@hash", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("hash", "2.0.0", "Creates or modifies the value of a field in a hash."),
+    CommandSpec::new::<HScan>("hscan", -3, &["readonly"], &["@read", "@hash", "@slow"])
+        .keys(1, 1, 1)
+        .doc("hash", "2.8.0", "Iterates over fields and values of a hash."),
+    // generic
+    CommandSpec::new::<Expire>("expire", -3, &["write", "fast"], &["@keyspace", "@write", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "1.0.0", "Sets the expiration time of a key in seconds."),
+    CommandSpec::new::<PExpire>("pexpire", -3, &["write", "fast"], &["@keyspace", "@write", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "2.6.0", "Sets the expiration time of a key in milliseconds."),
+    CommandSpec::new::<ExpireAt>("expireat", -3, &["write", "fast"], &["@keyspace", "@write", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "1.2.0", "Sets the expiration time of a key to a Unix timestamp."),
+    CommandSpec::new::<PExpireAt>("pexpireat", -3, &["write", "fast"], &["@keyspace", "@write", "@fast"])
+        .keys(1, 1, 1)
+        .doc(
+            "generic",
+            "2.6.0",
+            "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+        ),
+    CommandSpec::new::<ExpireTime>("expiretime", 2, &["readonly", "fast"], &["@keyspace", "@read", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "7.0.0", "Returns the expiration time of a key as a Unix timestamp."),
+    CommandSpec::new::<Ttl>("ttl", 2, &["readonly", "fast"], &["@keyspace", "@read", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "1.0.0", "Returns the expiration time in seconds of a key."),
+    CommandSpec::new::<PTtl>("pttl", 2, &["readonly", "fast"], &["@keyspace", "@read", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "2.6.0", "Returns the expiration time in milliseconds of a key."),
+    CommandSpec::new::<Persist>("persist", 2, &["write", "fast"], &["@keyspace", "@write", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "2.2.0", "Removes the expiration time of a key."),
+    CommandSpec::new::<Del>("del", -2, &["write"], &["@keyspace", "@write", "@slow"])
+        .keys(1, -1, 1)
+        .doc("generic", "1.0.0", "Deletes one or more keys."),
+    CommandSpec::new::<Unlink>("unlink", -2, &["write", "fast"], &["@keyspace", "@write", "@fast"])
+        .keys(1, -1, 1)
+        .doc("generic", "4.0.0", "Asynchronously deletes one or more keys."),
+    CommandSpec::new::<Exists>("exists", -2, &["readonly", "fast"], &["@keyspace", "@read", "@fast"])
+        .keys(1, -1, 1)
+        .doc("generic", "1.0.0", "Determines whether one or more keys exist."),
+    CommandSpec::new::<Type>("type", 2, &["readonly", "fast"], &["@keyspace", "@read", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "1.0.0", "Determines the type of value stored at a key."),
+    CommandSpec::new::<Touch>("touch", -2, &["readonly", "fast"], &["@keyspace", "@read", "@fast"])
+        .keys(1, -1, 1)
+        .doc("generic", "3.2.1", "Returns the number of existing keys out of those specified after updating the time they were last accessed."),
+    CommandSpec::new::<Move>("move", 3, &["write", "fast"], &["@keyspace", "@write", "@fast"])
+        .keys(1, 1, 1)
+        .doc("generic", "1.0.0", "Moves a key to another database."),
+    CommandSpec::new::<Keys>("keys", 2, &["readonly"], &["@keyspace", "@read", "@slow", "@dangerous"])
+        .doc("generic", "1.0.0", "Returns all key names that match a pattern."),
+    CommandSpec::new::<Scan>("scan", -2, &["readonly"], &["@keyspace", "@read", "@slow"])
+        .doc("generic", "2.8.0", "Iterates over the key names in the database."),
+    // server
+    CommandSpec::new::<Save>(
+        "save",
+        1,
+        &["admin", "noscript", "no_async_loading", "no_multi"],
+        &["@admin", "@slow", "@dangerous"],
+    )
+    .doc("server", "1.0.0", "Synchronously saves the database(s) to disk."),
+    CommandSpec::new::<BgSave>(
+        "bgsave",
+        1,
+        &["admin", "noscript", "no_async_loading", "no_multi"],
+        &["@admin", "@slow", "@dangerous"],
+    )
+    .doc("server", "1.0.0", "Asynchronously saves the database(s) to disk."),
+    CommandSpec::new::<BgRewriteAof>(
+        "bgrewriteaof",
+        1,
+        &["admin", "noscript", "no_async_loading", "no_multi"],
+        &["@admin", "@slow", "@dangerous"],
+    )
+    .doc("server", "1.0.0", "Asynchronously rewrites the append-only file to disk."),
+    CommandSpec::new::<DbSize>("dbsize", 1, &["readonly", "fast"], &["@keyspace", "@read", "@fast"])
+        .doc("server", "1.0.0", "Returns the number of keys in the database."),
+    CommandSpec::new::<SwapDb>(
+        "swapdb",
+        3,
+        &["write", "fast"],
+        &["@keyspace", "@write", "@fast", "@dangerous"],
+    )
+    .doc("server", "4.0.0", "Swaps two Redis databases."),
+    CommandSpec::new::<FlushDb>("flushdb", -1, &["write"], &["@keyspace", "@write", "@slow", "@dangerous"])
+        .doc("server", "1.0.0", "Remove all keys from the current database."),
+    CommandSpec::new::<FlushAll>("flushall", -1, &["write"], &["@keyspace", "@write", "@slow", "@dangerous"])
+        .doc("server", "1.0.0", "Removes all keys from all databases."),
+    CommandSpec::new::<Commands>("command", -1, &["loading", "stale"], &["@slow", "@connection"]).doc(
+        "server",
+        "2.8.13",
+        "Returns detailed information about all commands.",
+    ),
+    // list
+    CommandSpec::new::<LPush>(
+        "lpush",
+        -3,
+        &["write", "denyoom", "fast"],
+        &["@write", "@list", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("list", "1.0.0", "Prepends one or more elements to a list. Creates the key if it doesn't exist."),
+    CommandSpec::new::<RPush>(
+        "rpush",
+        -3,
+        &["write", "denyoom", "fast"],
+        &["@write", "@list", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("list", "1.0.0", "Appends one or more elements to a list. Creates the key if it doesn't exist."),
+    CommandSpec::new::<LPushX>(
+        "lpushx",
+        -3,
+        &["write", "denyoom", "fast"],
+        &["@write", "@list", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("list", "2.2.0", "Prepends one or more elements to a list only when the list exists."),
+    CommandSpec::new::<RPushX>(
+        "rpushx",
+        -3,
+        &["write", "denyoom", "fast"],
+        &["@write", "@list", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("list", "2.2.0", "Appends an element to a list only when the list exists."),
+    CommandSpec::new::<LPop>("lpop", -2, &["write", "fast"], &["@write", "@list", "@fast"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Returns the first elements in a list after removing it. Deletes the list if the last element was popped."),
+    CommandSpec::new::<RPop>("rpop", -2, &["write", "fast"], &["@write", "@list", "@fast"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Returns and removes the last elements of a list. Deletes the list if the last element was popped."),
+    CommandSpec::new::<LRange>("lrange", 4, &["readonly"], &["@read", "@list", "@slow"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Returns a range of elements from a list."),
+    CommandSpec::new::<LLen>("llen", 2, &["readonly", "fast"], &["@read", "@list", "@fast"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Returns the length of a list."),
+    CommandSpec::new::<LIndex>("lindex", 3, &["readonly"], &["@read", "@list", "@slow"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Returns an element from a list by its index."),
+    CommandSpec::new::<LSet>("lset", 4, &["write", "denyoom"], &["@write", "@list", "@slow"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Sets the value of an element in a list by its index."),
+    CommandSpec::new::<LRem>("lrem", 4, &["write"], &["@write", "@list", "@slow"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Removes elements from a list. Deletes the list if the last element was removed."),
+    CommandSpec::new::<LTrim>("ltrim", 4, &["write"], &["@write", "@list", "@slow"])
+        .keys(1, 1, 1)
+        .doc("list", "1.0.0", "Removes elements from both ends a list. Deletes the list if all elements were trimmed."),
+    CommandSpec::new::<LInsert>("linsert", 5, &["write", "denyoom"], &["@write", "@list", "@slow"])
+        .keys(1, 1, 1)
+        .doc("list", "2.2.0", "Inserts an element before or after another element in a list."),
+    CommandSpec::new::<LPos>("lpos", -3, &["readonly"], &["@read", "@list", "@slow"])
+        .keys(1, 1, 1)
+        .doc("list", "6.0.6", "Returns the index of matching elements in a list."),
+    CommandSpec::new::<LMove>("lmove", 5, &["write", "denyoom"], &["@write", "@list", "@slow"])
+        .keys(1, 2, 1)
+        .doc("list", "6.2.0", "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved."),
+    CommandSpec::new::<RPopLPush>("rpoplpush", 3, &["write", "denyoom"], &["@write", "@list", "@slow"])
+        .keys(1, 2, 1)
+        .doc("list", "1.2.0", "Returns the last element of a list after removing and pushing it to another list. Deletes the list if the last element was popped."),
+    CommandSpec::new::<BLPop>(
+        "blpop",
+        -3,
+        &["write", "blocking"],
+        &["@write", "@list", "@slow", "@blocking"],
+    )
+    .keys(1, -2, 1)
+    .doc("list", "2.0.0", "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    CommandSpec::new::<BRPop>(
+        "brpop",
+        -3,
+        &["write", "blocking"],
+        &["@write", "@list", "@slow", "@blocking"],
+    )
+    .keys(1, -2, 1)
+    .doc("list", "2.0.0", "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    CommandSpec::new::<BLMove>(
+        "blmove",
+        6,
+        &["write", "denyoom", "blocking"],
+        &["@write", "@list", "@slow", "@blocking"],
+    )
+    .keys(1, 2, 1)
+    .doc("list", "6.2.0", "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved."),
+    CommandSpec::new::<BLMPop>(
+        "blmpop",
+        -5,
+        &["write", "blocking", "movablekeys"],
+        &["@write", "@list", "@slow", "@blocking"],
+    )
+    .numkeys(2)
+    .doc("list", "7.0.0", "Pops the first element from one of multiple lists. Blocks until an element is available otherwise. Deletes the list if the last element was popped."),
+    // set
+    CommandSpec::new::<SAdd>(
+        "sadd",
+        -3,
+        &["write", "denyoom", "fast"],
+        &["@write", "@set", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("set", "1.0.0", "Adds one or more members to a set. Creates the key if it doesn't exist."),
+    CommandSpec::new::<SRem>("srem", -3, &["write", "fast"], &["@write", "@set", "@fast"])
+        .keys(1, 1, 1)
+        .doc("set", "1.0.0", "Removes one or more members from a set. Deletes the set if the last member was removed."),
+    CommandSpec::new::<SMembers>("smembers", 2, &["readonly"], &["@read", "@set", "@slow"])
+        .keys(1, 1, 1)
+        .doc("set", "1.0.0", "Returns all members of a set."),
+    CommandSpec::new::<SIsMember>("sismember", 3, &["readonly", "fast"], &["@read", "@set", "@fast"])
+        .keys(1, 1, 1)
+        .doc("set", "1.0.0", "Determines whether a member belongs to a set."),
+    CommandSpec::new::<SMIsMember>("smismember", -3, &["readonly", "fast"], &["@read", "@set", "@fast"])
+        .keys(1, 1, 1)
+        .doc("set", "6.2.0", "Determines whether multiple members belong to a set."),
+    CommandSpec::new::<SCard>("scard", 2, &["readonly", "fast"], &["@read", "@set", "@fast"])
+        .keys(1, 1, 1)
+        .doc("set", "1.0.0", "Returns the number of members in a set."),
+    CommandSpec::new::<SPop>("spop", -2, &["write", "fast"], &["@write", "@set", "@fast"])
+        .keys(1, 1, 1)
+        .doc("set", "1.0.0", "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped."),
+    CommandSpec::new::<SRandMember>("srandmember", -2, &["readonly"], &["@read", "@set", "@slow"])
+        .keys(1, 1, 1)
+        .doc("set", "1.0.0", "Get one or multiple random members from a set."),
+    CommandSpec::new::<SInter>("sinter", -2, &["readonly"], &["@read", "@set", "@slow"])
+        .keys(1, -1, 1)
+        .doc("set", "1.0.0", "Returns the intersect of multiple sets."),
+    CommandSpec::new::<SUnion>("sunion", -2, &["readonly"], &["@read", "@set", "@slow"])
+        .keys(1, -1, 1)
+        .doc("set", "1.0.0", "Returns the union of multiple sets."),
+    CommandSpec::new::<SDiff>("sdiff", -2, &["readonly"], &["@read", "@set", "@slow"])
+        .keys(1, -1, 1)
+        .doc("set", "1.0.0", "Returns the difference of multiple sets."),
+    CommandSpec::new::<SInterStore>("sinterstore", -3, &["write", "denyoom"], &["@write", "@set", "@slow"])
+        .keys(1, -1, 1)
+        .doc("set", "1.0.0", "Stores the intersect of multiple sets in a key."),
+    CommandSpec::new::<SUnionStore>("sunionstore", -3, &["write", "denyoom"], &["@write", "@set", "@slow"])
+        .keys(1, -1, 1)
+        .doc("set", "1.0.0", "Stores the union of multiple sets in a key."),
+    CommandSpec::new::<SDiffStore>("sdiffstore", -3, &["write", "denyoom"], &["@write", "@set", "@slow"])
+        .keys(1, -1, 1)
+        .doc("set", "1.0.0", "Stores the difference of multiple sets in a key."),
+    CommandSpec::new::<SInterCard>("sintercard", -3, &["readonly", "movablekeys"], &["@read", "@set", "@slow"])
+        .numkeys(1)
+        .doc("set", "7.0.0", "Returns the number of members of the intersect of multiple sets."),
+    CommandSpec::new::<SMove>("smove", 4, &["write", "fast"], &["@write", "@set", "@fast"])
+        .keys(1, 2, 1)
+        .doc("set", "1.0.0", "Moves a member from one set to another."),
+    CommandSpec::new::<SScan>("sscan", -3, &["readonly"], &["@read", "@set", "@slow"])
+        .keys(1, 1, 1)
+        .doc("set", "2.8.0", "Iterates over members of a set."),
+    // sorted set
+    CommandSpec::new::<ZAdd>(
+        "zadd",
+        -4,
+        &["write", "denyoom", "fast"],
+        &["@write", "@sortedset", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("sorted-set", "1.2.0", "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist."),
+    CommandSpec::new::<ZIncrBy>(
+        "zincrby",
+        4,
+        &["write", "denyoom", "fast"],
+        &["@write", "@sortedset", "@fast"],
+    )
+    .keys(1, 1, 1)
+    .doc("sorted-set", "1.2.0", "Increments the score of a member in a sorted set."),
+    CommandSpec::new::<ZRem>("zrem", -3, &["write", "fast"], &["@write", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "1.2.0", "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed."),
+    CommandSpec::new::<ZScore>("zscore", 3, &["readonly", "fast"], &["@read", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "1.2.0", "Returns the score of a member in a sorted set."),
+    CommandSpec::new::<ZCard>("zcard", 2, &["readonly", "fast"], &["@read", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "1.2.0", "Returns the number of members in a sorted set."),
+    CommandSpec::new::<ZRank>("zrank", -3, &["readonly", "fast"], &["@read", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "2.0.0", "Returns the index of a member in a sorted set ordered by ascending scores."),
+    CommandSpec::new::<ZRevRank>("zrevrank", -3, &["readonly", "fast"], &["@read", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "2.0.0", "Returns the index of a member in a sorted set ordered by descending scores."),
+    CommandSpec::new::<ZRange>("zrange", -4, &["readonly"], &["@read", "@sortedset", "@slow"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "1.2.0", "Returns members in a sorted set within a range of indexes."),
+    CommandSpec::new::<ZRangeByScore>("zrangebyscore", -4, &["readonly"], &["@read", "@sortedset", "@slow"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "1.0.5", "Returns members in a sorted set within a range of scores."),
+    CommandSpec::new::<ZCount>("zcount", 4, &["readonly", "fast"], &["@read", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "2.0.0", "Returns the count of members in a sorted set that have scores within a range."),
+    CommandSpec::new::<ZLexCount>("zlexcount", 4, &["readonly", "fast"], &["@read", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "2.8.9", "Returns the number of members in a sorted set within a lexicographical range."),
+    CommandSpec::new::<ZPopMin>("zpopmin", -2, &["write", "fast"], &["@write", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "5.0.0", "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped."),
+    CommandSpec::new::<ZPopMax>("zpopmax", -2, &["write", "fast"], &["@write", "@sortedset", "@fast"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "5.0.0", "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped."),
+    CommandSpec::new::<ZUnionStore>(
+        "zunionstore",
+        -4,
+        &["write", "denyoom", "movablekeys"],
+        &["@write", "@sortedset", "@slow"],
+    )
+    .keys(1, 1, 1)
+    .numkeys(2)
+    .doc("sorted-set", "2.0.0", "Stores the union of multiple sorted sets in a key."),
+    CommandSpec::new::<ZInterStore>(
+        "zinterstore",
+        -4,
+        &["write", "denyoom", "movablekeys"],
+        &["@write", "@sortedset", "@slow"],
+    )
+    .keys(1, 1, 1)
+    .numkeys(2)
+    .doc("sorted-set", "2.0.0", "Stores the intersect of multiple sorted sets in a key."),
+    CommandSpec::new::<ZRandMember>("zrandmember", -2, &["readonly"], &["@read", "@sortedset", "@slow"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "6.2.0", "Returns one or more random members from a sorted set."),
+    CommandSpec::new::<ZScan>("zscan", -3, &["readonly"], &["@read", "@sortedset", "@slow"])
+        .keys(1, 1, 1)
+        .doc("sorted-set", "2.8.0", "Iterates over members and scores of a sorted set."),
+    // pubsub
+    CommandSpec::new::<Subscribe>(
+        "subscribe",
+        -2,
+        &["pubsub", "noscript", "loading", "stale"],
+        &["@pubsub", "@slow"],
+    )
+    .doc("pubsub", "2.0.0", "Listens for messages published to channels."),
+    CommandSpec::new::<Unsubscribe>(
+        "unsubscribe",
+        -1,
+        &["pubsub", "noscript", "loading", "stale"],
+        &["@pubsub", "@slow"],
+    )
+    .doc("pubsub", "2.0.0", "Stops listening to messages posted to channels."),
+    CommandSpec::new::<PSubscribe>(
+        "psubscribe",
+        -2,
+        &["pubsub", "noscript", "loading", "stale"],
+        &["@pubsub", "@slow"],
+    )
+    .doc("pubsub", "2.0.0", "Listens for messages published to channels that match one or more patterns."),
+    CommandSpec::new::<PUnsubscribe>(
+        "punsubscribe",
+        -1,
+        &["pubsub", "noscript", "loading", "stale"],
+        &["@pubsub", "@slow"],
+    )
+    .doc("pubsub", "2.0.0", "Stops listening to messages published to channels that match one or more patterns."),
+    CommandSpec::new::<Publish>(
+        "publish",
+        3,
+        &["pubsub", "loading", "stale", "fast"],
+        &["@pubsub", "@fast"],
+    )
+    .doc("pubsub", "2.0.0", "Posts a message to a channel."),
+    CommandSpec::new::<PubSub>("pubsub", -2, &["pubsub", "loading", "stale"], &["@pubsub", "@slow"]).doc(
+        "pubsub",
+        "2.8.0",
+        "A container for Pub/Sub commands.",
+    ),
+    // transactions
+    CommandSpec::new::<Multi>(
+        "multi",
+        1,
+        &["noscript", "loading", "stale", "fast", "allow_busy"],
+        &["@fast", "@transaction"],
+    )
+    .doc("transactions", "1.2.0", "Starts a transaction."),
+    CommandSpec::new::<Exec>(
+        "exec",
+        1,
+        &["noscript", "loading", "stale", "skip_slowlog"],
+        &["@slow", "@transaction"],
+    )
+    .doc("transactions", "1.2.0", "Executes all commands in a transaction."),
+    CommandSpec::new::<Discard>(
+        "discard",
+        1,
+        &["noscript", "loading", "stale", "fast", "allow_busy"],
+        &["@fast", "@transaction"],
+    )
+    .doc("transactions", "2.0.0", "Discards a transaction."),
+    CommandSpec::new::<Watch>(
+        "watch",
+        -2,
+        &["noscript", "loading", "stale", "fast", "allow_busy"],
+        &["@fast", "@transaction"],
+    )
+    .keys(1, -1, 1)
+    .doc("transactions", "2.2.0", "Monitors changes to keys to determine the execution of a transaction."),
+    CommandSpec::new::<Unwatch>(
+        "unwatch",
+        1,
+        &["noscript", "loading", "stale", "fast", "allow_busy"],
+        &["@fast", "@transaction"],
+    )
+    .doc("transactions", "2.2.0", "Forgets about watched keys of a transaction."),
+    // connection
+    CommandSpec::new::<Echo>("echo", 2, &["fast"], &["@fast", "@connection"]).doc(
+        "connection",
+        "1.0.0",
+        "Returns the given string.",
+    ),
+    CommandSpec::new::<Hello>(
+        "hello",
+        -1,
+        &["noscript", "loading", "stale", "fast", "no_auth", "allow_busy"],
+        &["@fast", "@connection"],
+    )
+    .doc("connection", "6.0.0", "Handshakes with the Redis server."),
+    CommandSpec::new::<Ping>("ping", -1, &["fast"], &["@fast", "@connection"]).doc(
+        "connection",
+        "1.0.0",
+        "Returns the server's liveliness response.",
+    ),
+    CommandSpec::new::<Select>("select", 2, &["loading", "stale", "fast"], &["@fast", "@connection"])
+        .doc("connection", "1.0.0", "Changes the selected database."),
+];
+
+lazy_static! {
+    // clients send names in any case, the table is keyed by the lowercase one
+    static ref REGISTRY: HashMap<&'static [u8], &'static CommandSpec> = COMMAND_TABLE
+        .iter()
+        .map(|spec| (spec.name.as_bytes(), spec))
+        .collect();
+}
+
+/// The entry of the command named `name`, whatever its case
+pub fn lookup_command(name: &[u8]) -> Option<&'static CommandSpec> {
+    REGISTRY.get(name.to_ascii_lowercase().as_slice()).copied()
+}
+
+/// Whether the table marks the command of `args` as a write, before parsing it
+pub fn is_write_command(args: &RespArray) -> bool {
+    match args.first() {
+        Some(RespFrame::BulkString(name)) => {
+            lookup_command(name).is_some_and(|spec| spec.is_write())
+        }
+        _ => false,
+    }
+}
+
+fn simple_strings(names: &[&str]) -> RespFrame {
+    RespSet::new(
+        names
+            .iter()
+            .map(|name| SimpleString::new(*name).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+// [name, arity, flags, first key, last key, step, acl categories, tips, key specs, subcommands]
+fn info_reply(spec: &CommandSpec) -> RespFrame {
+    RespArray::new(vec![
+        BulkString::new(spec.name).into(),
+        RespFrame::Integer(spec.arity),
+        simple_strings(spec.flags),
+        RespFrame::Integer(spec.first_key),
+        RespFrame::Integer(spec.last_key),
+        RespFrame::Integer(spec.step),
+        simple_strings(spec.acl_categories),
+        RespArray::new(vec![]).into(),
+        RespArray::new(vec![]).into(),
+        RespArray::new(vec![]).into(),
+    ])
+    .into()
+}
+
+fn docs_reply(spec: &CommandSpec) -> RespFrame {
+    let mut docs = RespMap::new();
+    docs.insert("summary".to_string(), BulkString::new(spec.summary).into());
+    docs.insert("since".to_string(), BulkString::new(spec.since).into());
+    docs.insert("group".to_string(), BulkString::new(spec.group).into());
+    docs.into()
+}
+
+// COMMAND GETKEYS checks the request like a call to the command would
+fn getkeys_reply(args: Vec<RespFrame>) -> Result<RespFrame, CommandError> {
+    let spec = match args.first() {
+        Some(RespFrame::BulkString(name)) => lookup_command(name),
+        _ => None,
+    }
+    .ok_or_else(|| CommandError::InvalidArguments("Invalid command specified".to_string()))?;
+    if !spec.check_arity(args.len()) {
+        return Err(CommandError::InvalidArguments(
+            "Invalid number of arguments specified for command".to_string(),
+        ));
+    }
+    let positions = spec.key_positions(&args)?;
+    if positions.is_empty() {
+        return Err(CommandError::InvalidArguments(
+            "The command has no key arguments".to_string(),
+        ));
+    }
+    Ok(RespArray::new(
+        positions
+            .into_iter()
+            .map(|i| args[i].clone())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into())
+}
+
+impl CommandExecutor for Commands {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            CommandsSubcommand::List => {
+                RespArray::new(COMMAND_TABLE.iter().map(info_reply).collect::<Vec<_>>()).into()
+            }
+            CommandsSubcommand::Count => RespFrame::Integer(COMMAND_TABLE.len() as i64),
+            // an unknown name gets a null in its place
+            CommandsSubcommand::Info(names) if names.is_empty() => {
+                RespArray::new(COMMAND_TABLE.iter().map(info_reply).collect::<Vec<_>>()).into()
+            }
+            CommandsSubcommand::Info(names) => RespArray::new(
+                names
+                    .iter()
+                    .map(|name| match lookup_command(name) {
+                        Some(spec) => info_reply(spec),
+                        None => RespFrame::Null(RespNull),
+                    })
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            // unknown names are left out
+            CommandsSubcommand::Docs(names) => {
+                let mut docs = RespMap::new();
+                let specs: Vec<&CommandSpec> = if names.is_empty() {
+                    COMMAND_TABLE.iter().collect()
+                } else {
+                    names
+                        .iter()
+                        .filter_map(|name| lookup_command(name))
+                        .collect()
+                };
+                for spec in specs {
+                    docs.insert(spec.name.to_string(), docs_reply(spec));
+                }
+                docs.into()
+            }
+            CommandsSubcommand::GetKeys(args) => getkeys_reply(args).unwrap_or_else(From::from),
+        }
+    }
+}
+
+// COMMAND [COUNT | INFO [name ...] | DOCS [name ...] | GETKEYS command [arg ...]]
+impl TryFrom<RespArray> for Commands {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        if args.len() == 0 {
+            return Ok(Commands {
+                subcommand: CommandsSubcommand::List,
+            });
+        }
+        let name = extract_string(args.next(), "subcommand")?.to_ascii_lowercase();
+        let subcommand = match name.as_str() {
+            "count" if args.len() == 0 => CommandsSubcommand::Count,
+            "info" => CommandsSubcommand::Info(extract_members(args)?),
+            "docs" => CommandsSubcommand::Docs(extract_members(args)?),
+            "getkeys" if args.len() > 0 => CommandsSubcommand::GetKeys(args.collect()),
+            _ => {
+                return Err(CommandError::InvalidArguments(format!(
+                    "unknown subcommand or wrong number of arguments for '{}'",
+                    name
+                )))
+            }
+        };
+        Ok(Commands { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::cmd::command;
+    use crate::{Command, SimpleError};
+
+    fn bulk(s: &str) -> RespFrame {
+        BulkString::new(s.as_bytes().to_vec()).into()
+    }
+
+    #[test]
+    fn test_dispatch_through_registry() {
+        for spec in COMMAND_TABLE {
+            // names are matched whatever their case
+            let name = spec.name.to_uppercase();
+            let mut args = vec![name.as_str(); spec.arity.unsigned_abs() as usize];
+            let ret = Command::try_from(command(&args));
+            assert!(
+                !matches!(
+                    ret,
+                    Err(CommandError::UnknownCommand(..) | CommandError::WrongArity(_))
+                ),
+                "{} is not dispatched",
+                spec.name
+            );
+            // the arity comes from the table
+            if spec.arity > 0 {
+                args.push("extra");
+            } else {
+                args.truncate(args.len() - 1);
+            }
+            if args.len() > 1 || spec.arity > 0 {
+                let ret = Command::try_from(command(&args));
+                assert_eq!(
+                    ret.unwrap_err().to_string(),
+                    format!("ERR wrong number of arguments for '{}' command", spec.name)
+                );
+            }
+        }
+        let ret = Command::try_from(command(&["sett", "a", "b"]));
+        assert_eq!(
+            ret.unwrap_err().to_string(),
+            "ERR unknown command 'sett', with args beginning with: 'a' 'b' "
+        );
+    }
+
+    #[test]
+    fn test_command_info() -> Result<()> {
+        let backend = Backend::new();
+        let ret = Commands::try_from(command(&["command", "count"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(COMMAND_TABLE.len() as i64));
+
+        let ret = Commands::try_from(command(&["command", "info", "GET", "nope"]))?
+            .execute(&backend)
+            .into_resp2();
+        let RespFrame::Array(infos) = ret else {
+            panic!("expected an array: {:?}", ret);
+        };
+        assert_eq!(infos[1], BulkString::nill_new().into());
+        assert_eq!(
+            infos[0],
+            RespArray::new(vec![
+                bulk("get"),
+                RespFrame::Integer(2),
+                RespArray::new(vec![
+                    SimpleString::new("readonly").into(),
+                    SimpleString::new("fast").into(),
+                ])
+                .into(),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespArray::new(vec![
+                    SimpleString::new("@read").into(),
+                    SimpleString::new("@string").into(),
+                    SimpleString::new("@fast").into(),
+                ])
+                .into(),
+                RespArray::new(vec![]).into(),
+                RespArray::new(vec![]).into(),
+                RespArray::new(vec![]).into(),
+            ])
+            .into()
+        );
+
+        let ret = Commands::try_from(command(&["command", "docs", "echo"]))?
+            .execute(&backend)
+            .into_resp2();
+        assert_eq!(
+            ret,
+            RespArray::new(vec![
+                bulk("echo"),
+                RespArray::new(vec![
+                    bulk("group"),
+                    bulk("connection"),
+                    bulk("since"),
+                    bulk("1.0.0"),
+                    bulk("summary"),
+                    bulk("Returns the given string."),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_getkeys() -> Result<()> {
+        let backend = Backend::new();
+        let getkeys = |args: &[&str]| -> Result<RespFrame> {
+            let mut request = vec!["command", "getkeys"];
+            request.extend_from_slice(args);
+            Ok(Commands::try_from(command(&request))?.execute(&backend))
+        };
+        let keys = |keys: &[&str]| -> RespFrame {
+            RespArray::new(keys.iter().map(|key| bulk(key)).collect::<Vec<_>>()).into()
+        };
+
+        assert_eq!(getkeys(&["SET", "a", "1", "EX", "10"])?, keys(&["a"]));
+        assert_eq!(getkeys(&["blpop", "a", "b", "0"])?, keys(&["a", "b"]));
+        assert_eq!(getkeys(&["smove", "a", "b", "m"])?, keys(&["a", "b"]));
+        assert_eq!(
+            getkeys(&["zunionstore", "d", "2", "a", "b", "WEIGHTS", "1", "2"])?,
+            keys(&["d", "a", "b"])
+        );
+        assert_eq!(
+            getkeys(&["blmpop", "0", "2", "a", "b", "LEFT"])?,
+            keys(&["a", "b"])
+        );
+        assert_eq!(
+            getkeys(&["sintercard", "3", "a", "b"])?,
+            SimpleError::new("ERR Invalid arguments specified for command").into()
+        );
+        assert_eq!(
+            getkeys(&["get"])?,
+            SimpleError::new("ERR Invalid number of arguments specified for command").into()
+        );
+        assert_eq!(
+            getkeys(&["echo", "a"])?,
+            SimpleError::new("ERR The command has no key arguments").into()
+        );
+        assert_eq!(
+            getkeys(&["nope", "a"])?,
+            SimpleError::new("ERR Invalid command specified").into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::cmd::list::syntax_error;
+use crate::cmd::{extract_args, extract_integer, extract_string, RESP_OK};
+use crate::{
+    Backend, BulkString, CommandError, CommandExecutor, Hello, RespArray, RespFrame, RespMap,
+    RespProtocol, Select, SimpleError,
+};
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// What a connection negotiated about itself
+#[derive(Debug)]
+pub struct Client {
+    id: u64,
+    name: Option<String>,
+    protocol: RespProtocol,
+    // the database selected with SELECT
+    db: usize,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Client {
+            id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            name: None,
+            protocol: RespProtocol::default(),
+            db: 0,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The RESP version replies are encoded with
+    pub fn protocol(&self) -> RespProtocol {
+        self.protocol
+    }
+
+    /// The database the commands of the connection run in
+    pub fn db(&self) -> usize {
+        self.db
+    }
+
+    /// Switch the database, after an EXEC that ran a SELECT
+    pub(crate) fn select(&mut self, db: usize) {
+        self.db = db;
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// run by EXEC, the transaction switches the database of the commands queued after it
+impl CommandExecutor for Select {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.db(backend) {
+            Some(_) => RESP_OK.clone(),
+            None => SimpleError::new("ERR DB index is out of range").into(),
+        }
+    }
+}
+
+impl Select {
+    /// The database to switch to, None when the index is out of range
+    pub(crate) fn db(&self, backend: &Backend) -> Option<usize> {
+        usize::try_from(self.index)
+            .ok()
+            .filter(|index| *index < backend.databases())
+    }
+
+    /// Switch the database the following commands of the connection run in
+    pub(crate) fn execute_for(self, backend: &Backend, client: &mut Client) -> RespFrame {
+        match self.db(backend) {
+            Some(index) => {
+                client.db = index;
+                RESP_OK.clone()
+            }
+            None => SimpleError::new("ERR DB index is out of range").into(),
+        }
+    }
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        SimpleError::new("ERR HELLO needs a client connection").into()
+    }
+}
+
+impl Hello {
+    /// Switch the connection to the requested protocol and reply with the server info,
+    /// encoded with the new protocol already
+    pub(crate) fn execute_for(self, client: &mut Client) -> RespFrame {
+        let protocol = match self.protover {
+            None => client.protocol,
+            Some(2) => RespProtocol::Resp2,
+            Some(3) => RespProtocol::Resp3,
+            Some(_) => return SimpleError::new("NOPROTO unsupported protocol version").into(),
+        };
+        // there are no users besides the default one, which needs no password
+        if let Some((username, _)) = &self.auth {
+            if username != "default" {
+                return SimpleError::new(
+                    "WRONGPASS invalid username-password pair or user is disabled.",
+                )
+                .into();
+            }
+        }
+        if let Some(name) = self.setname {
+            if name.bytes().any(|c| !(b'!'..=b'~').contains(&c)) {
+                return SimpleError::new(
+                    "ERR Client names cannot contain spaces, newlines or special characters.",
+                )
+                .into();
+            }
+            client.name = (!name.is_empty()).then_some(name);
+        }
+        client.protocol = protocol;
+
+        let proto = match protocol {
+            RespProtocol::Resp2 => 2,
+            RespProtocol::Resp3 => 3,
+        };
+        let mut info = RespMap::new();
+        info.insert("server".to_string(), BulkString::new("redis").into());
+        info.insert(
+            "version".to_string(),
+            BulkString::new(env!("CARGO_PKG_VERSION")).into(),
+        );
+        info.insert("proto".to_string(), RespFrame::Integer(proto));
+        info.insert("id".to_string(), RespFrame::Integer(client.id as i64));
+        info.insert("mode".to_string(), BulkString::new("standalone").into());
+        info.insert("role".to_string(), BulkString::new("master").into());
+        info.insert("modules".to_string(), RespArray::new(vec![]).into());
+        info.into()
+    }
+}
+
+// HELLO [protover [AUTH username password] [SETNAME clientname]]
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut hello = Hello {
+            protover: None,
+            auth: None,
+            setname: None,
+        };
+        if args.len() == 0 {
+            return Ok(hello);
+        }
+        hello.protover = Some(extract_integer(args.next()).map_err(|_| {
+            CommandError::InvalidArguments(
+                "Protocol version is not an integer or out of range".to_string(),
+            )
+        })?);
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg), "option")?
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "auth" if args.len() >= 2 => {
+                    let username = extract_string(args.next(), "username")?;
+                    let password = extract_string(args.next(), "password")?;
+                    hello.auth = Some((username, password));
+                }
+                "setname" if args.len() >= 1 => {
+                    hello.setname = Some(extract_string(args.next(), "clientname")?);
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(hello)
+    }
+}
+
+impl TryFrom<RespArray> for Select {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Select {
+            index: extract_integer(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::cmd::command;
+    use crate::RespEncode;
+
+    #[test]
+    fn test_hello() -> Result<()> {
+        let mut client = Client::new();
+
+        let ret =
+            Hello::try_from(command(&["hello", "3", "setname", "app"]))?.execute_for(&mut client);
+        assert_eq!(client.protocol(), RespProtocol::Resp3);
+        assert_eq!(client.name(), Some("app"));
+        let RespFrame::Map(info) = ret else {
+            panic!("HELLO replies with a map");
+        };
+        assert_eq!(info["proto"], RespFrame::Integer(3));
+        assert_eq!(info["id"], RespFrame::Integer(client.id() as i64));
+
+        let ret = Hello::try_from(command(&["hello", "4"]))?.execute_for(&mut client);
+        assert_eq!(
+            ret,
+            SimpleError::new("NOPROTO unsupported protocol version").into()
+        );
+        assert_eq!(client.protocol(), RespProtocol::Resp3);
+
+        let ret = Hello::try_from(command(&["hello", "2", "auth", "default", "pass"]))?
+            .execute_for(&mut client);
+        assert_eq!(client.protocol(), RespProtocol::Resp2);
+        assert!(ret
+            .into_resp2()
+            .encode()
+            .starts_with(b"*14\r\n$2\r\nid\r\n"));
+
+        assert!(Hello::try_from(command(&["hello", "three"])).is_err());
+        assert!(Hello::try_from(command(&["hello", "3", "auth", "default"])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::cmd::list::syntax_error;
+use crate::cmd::{extract_args, extract_integer, extract_key, extract_string, RESP_OK};
+use crate::{
+    Backend, CommandError, CommandExecutor, DbSize, FlushAll, FlushDb, Move, RespArray, RespFrame,
+    SimpleError, SwapDb,
+};
+
+// the index as a database of `backend`, the number of databases is only known at runtime
+fn db_index(backend: &Backend, index: i64) -> Option<usize> {
+    usize::try_from(index)
+        .ok()
+        .filter(|index| *index < backend.databases())
+}
+
+fn out_of_range() -> RespFrame {
+    SimpleError::new("ERR DB index is out of range").into()
+}
+
+impl CommandExecutor for SwapDb {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match (
+            db_index(backend, self.first),
+            db_index(backend, self.second),
+        ) {
+            (Some(first), Some(second)) => {
+                if first != second {
+                    backend.swapdb(first, second);
+                }
+                RESP_OK.clone()
+            }
+            _ => out_of_range(),
+        }
+    }
+}
+
+impl CommandExecutor for Move {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(db) = db_index(backend, self.db) else {
+            return out_of_range();
+        };
+        if db == backend.db_index() {
+            return SimpleError::new("ERR source and destination objects are the same").into();
+        }
+        RespFrame::Integer(backend.move_key(&self.key, db) as i64)
+    }
+}
+
+impl CommandExecutor for DbSize {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.dbsize() as i64)
+    }
+}
+
+impl CommandExecutor for FlushDb {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.flushdb(self.lazy);
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for FlushAll {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.flushall(self.lazy);
+        RESP_OK.clone()
+    }
+}
+
+// SWAPDB index1 index2
+impl TryFrom<RespArray> for SwapDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let invalid =
+            |which: &str| CommandError::InvalidArguments(format!("invalid {} DB index", which));
+        let first = extract_integer(args.next()).map_err(|_| invalid("first"))?;
+        let second = extract_integer(args.next()).map_err(|_| invalid("second"))?;
+        Ok(SwapDb { first, second })
+    }
+}
+
+// MOVE key db
+impl TryFrom<RespArray> for Move {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        Ok(Move {
+            key,
+            db: extract_integer(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for DbSize {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(DbSize)
+    }
+}
+
+// FLUSHDB / FLUSHALL [ASYNC | SYNC]
+fn parse_flush_mode(value: RespArray) -> Result<bool, CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let mode = match (args.next(), args.next()) {
+        (None, _) => return Ok(false),
+        (mode, None) => extract_string(mode, "mode")?.to_ascii_lowercase(),
+        _ => return Err(syntax_error()),
+    };
+    match mode.as_str() {
+        "async" => Ok(true),
+        "sync" => Ok(false),
+        _ => Err(syntax_error()),
+    }
+}
+
+impl TryFrom<RespArray> for FlushDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(FlushDb {
+            lazy: parse_flush_mode(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FlushAll {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(FlushAll {
+            lazy: parse_flush_mode(value)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{BulkString, Client, Command};
+
+    use super::*;
+    use crate::cmd::command;
+
+    fn run(backend: &Backend, client: &mut Client, args: &[&str]) -> Result<RespFrame> {
+        let cmd = Command::try_from(command(args))?;
+        Ok(cmd.execute_connection(&backend.select(client.db()), client))
+    }
+
+    #[test]
+    fn test_select_and_move() -> Result<()> {
+        let backend = Backend::new();
+        let mut client = Client::new();
+        run(&backend, &mut client, &["set", "key", "v"])?;
+
+        let ret = run(&backend, &mut client, &["select", "16"])?;
+        assert_eq!(ret, out_of_range());
+        let ret = run(&backend, &mut client, &["move", "key", "0"])?;
+        assert_eq!(
+            ret,
+            SimpleError::new("ERR source and destination objects are the same").into()
+        );
+        let ret = run(&backend, &mut client, &["move", "key", "3"])?;
+        assert_eq!(ret, RespFrame::Integer(1));
+
+        let ret = run(&backend, &mut client, &["select", "3"])?;
+        assert_eq!(ret, RESP_OK.clone());
+        assert_eq!(client.db(), 3);
+        let ret = run(&backend, &mut client, &["dbsize"])?;
+        assert_eq!(ret, RespFrame::Integer(1));
+        let ret = run(&backend, &mut client, &["get", "key"])?;
+        assert_eq!(ret, BulkString::new("v").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_swapdb_and_flush() -> Result<()> {
+        let backend = Backend::new();
+        let mut client = Client::new();
+        run(&backend, &mut client, &["set", "key", "v"])?;
+
+        let ret = run(&backend, &mut client, &["swapdb", "0", "1"])?;
+        assert_eq!(ret, RESP_OK.clone());
+        assert_eq!(backend.dbsize(), 0);
+        assert_eq!(backend.select(1).dbsize(), 1);
+        let ret = run(&backend, &mut client, &["swapdb", "0", "-1"])?;
+        assert_eq!(ret, out_of_range());
+        let ret = Command::try_from(command(&["swapdb", "a", "1"]));
+        assert!(ret.is_err());
+
+        // FLUSHDB only empties the selected database
+        run(&backend, &mut client, &["set", "key", "v"])?;
+        let ret = run(&backend, &mut client, &["flushdb", "async"])?;
+        assert_eq!(ret, RESP_OK.clone());
+        assert_eq!(backend.dbsize(), 0);
+        assert_eq!(backend.select(1).dbsize(), 1);
+
+        let ret = run(&backend, &mut client, &["flushall", "sync"])?;
+        assert_eq!(ret, RESP_OK.clone());
+        assert_eq!(backend.select(1).dbsize(), 0);
+        assert!(Command::try_from(command(&["flushall", "later"])).is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
-use crate::cmd::{extract_args, validate_command};
-use crate::{Backend, BulkString, CommandError, CommandExecutor, Echo, RespArray, RespFrame};
+use crate::cmd::{extract_args, extract_key};
+use crate::{
+    Backend, BulkString, CommandError, CommandExecutor, Echo, Ping, RespArray, RespFrame, RespPush,
+    SimpleString,
+};
 
 impl CommandExecutor for Echo {
     fn execute(self, _backend: &Backend) -> RespFrame {
@@ -9,19 +12,54 @@ impl CommandExecutor for Echo {
     }
 }
 
+impl CommandExecutor for Ping {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        match self.message {
+            Some(message) => BulkString::new(message).into(),
+            None => SimpleString::new("PONG").into(),
+        }
+    }
+}
+
+impl Ping {
+    /// PING of a RESP2 client in subscribed mode, the reply is shaped like the messages
+    /// around it: ["pong", message]. A push frame like them, the connection downgrades it
+    /// with the rest of what it writes.
+    pub(crate) fn execute_subscribed(self) -> RespFrame {
+        RespPush::new(vec![
+            BulkString::new("pong").into(),
+            BulkString::new(self.message.unwrap_or_default()).into(),
+        ])
+        .into()
+    }
+}
+
 impl TryFrom<RespArray> for Echo {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["echo"], 1)?;
-
         let mut args = extract_args(value, 1)?.into_iter();
 
         match args.next() {
-            Some(RespFrame::BulkString(value)) => Ok(Echo {
-                value: String::from_utf8(value.0)?,
-            }),
+            Some(RespFrame::BulkString(value)) => Ok(Echo { value: value.0 }),
             _ => Err(CommandError::InvalidArguments("Invalid key".to_string())),
         }
     }
 }
+
+// PING [message]
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let message = match args.next() {
+            Some(message) => Some(extract_key(Some(message), "message")?),
+            None => None,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::WrongArity("ping".to_string()));
+        }
+        Ok(Ping { message })
+    }
+}
@@ -0,0 +1,280 @@
+use bytes::Bytes;
+
+use crate::cmd::{extract_args, extract_integer, extract_key, extract_string};
+use crate::{
+    now_millis, Backend, CommandError, CommandExecutor, Expire, ExpireAt, ExpireCondition,
+    ExpireTime, KeyExpiry, PExpire, PExpireAt, PTtl, Persist, RespArray, RespFrame, SimpleError,
+    Ttl,
+};
+
+impl CommandExecutor for Expire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match now_millis().checked_add(self.seconds.saturating_mul(1000)) {
+            Some(deadline) => expire_at(backend, &self.key, deadline, self.condition),
+            None => invalid_expire_time("expire"),
+        }
+    }
+}
+
+impl CommandExecutor for PExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match now_millis().checked_add(self.milliseconds) {
+            Some(deadline) => expire_at(backend, &self.key, deadline, self.condition),
+            None => invalid_expire_time("pexpire"),
+        }
+    }
+}
+
+impl CommandExecutor for ExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        expire_at(backend, &self.key, self.timestamp * 1000, self.condition)
+    }
+}
+
+impl CommandExecutor for PExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        expire_at(backend, &self.key, self.timestamp, self.condition)
+    }
+}
+
+impl CommandExecutor for ExpireTime {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.expiry(&self.key) {
+            KeyExpiry::NotFound => RespFrame::Integer(-2),
+            KeyExpiry::Persistent => RespFrame::Integer(-1),
+            KeyExpiry::At(deadline) => RespFrame::Integer(deadline / 1000),
+        }
+    }
+}
+
+impl CommandExecutor for Ttl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.expiry(&self.key) {
+            KeyExpiry::NotFound => RespFrame::Integer(-2),
+            KeyExpiry::Persistent => RespFrame::Integer(-1),
+            // round to the closest second like redis does
+            KeyExpiry::At(deadline) => {
+                RespFrame::Integer(((deadline - now_millis()).max(0) + 500) / 1000)
+            }
+        }
+    }
+}
+
+impl CommandExecutor for PTtl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.expiry(&self.key) {
+            KeyExpiry::NotFound => RespFrame::Integer(-2),
+            KeyExpiry::Persistent => RespFrame::Integer(-1),
+            KeyExpiry::At(deadline) => RespFrame::Integer((deadline - now_millis()).max(0)),
+        }
+    }
+}
+
+impl CommandExecutor for Persist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.persist(&self.key) as i64)
+    }
+}
+
+fn expire_at(
+    backend: &Backend,
+    key: &[u8],
+    deadline: i64,
+    condition: ExpireCondition,
+) -> RespFrame {
+    RespFrame::Integer(backend.expire_at(key, deadline, condition) as i64)
+}
+
+fn invalid_expire_time(name: &str) -> RespFrame {
+    SimpleError::new(format!("ERR invalid expire time in '{}' command", name)).into()
+}
+
+// EXPIRE key seconds [NX | XX | GT | LT]
+fn parse_expire(
+    value: RespArray,
+    name: &'static str,
+    unit: i64,
+) -> Result<(Bytes, i64, ExpireCondition), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    let time = extract_integer(args.next())?;
+    if time.checked_mul(unit).is_none() {
+        return Err(CommandError::InvalidArguments(format!(
+            "invalid expire time in '{}' command",
+            name
+        )));
+    }
+
+    let mut condition = ExpireCondition::Always;
+    for arg in args {
+        let option = extract_string(Some(arg), "option")?.to_ascii_lowercase();
+        let next = match option.as_str() {
+            "nx" => ExpireCondition::Nx,
+            "xx" => ExpireCondition::Xx,
+            "gt" => ExpireCondition::Gt,
+            "lt" => ExpireCondition::Lt,
+            _ => {
+                return Err(CommandError::InvalidArguments(format!(
+                    "Unsupported option {}",
+                    option
+                )))
+            }
+        };
+        condition = match (condition, next) {
+            (ExpireCondition::Always, next) => next,
+            (current, next) if current == next => current,
+            (ExpireCondition::Gt, ExpireCondition::Lt)
+            | (ExpireCondition::Lt, ExpireCondition::Gt) => {
+                return Err(CommandError::InvalidArguments(
+                    "GT and LT options at the same time are not compatible".to_string(),
+                ))
+            }
+            // XX can be combined with GT or LT
+            (ExpireCondition::Xx, next @ (ExpireCondition::Gt | ExpireCondition::Lt))
+            | (next @ (ExpireCondition::Gt | ExpireCondition::Lt), ExpireCondition::Xx) => next,
+            _ => {
+                return Err(CommandError::InvalidArguments(
+                    "NX and XX, GT or LT options at the same time are not compatible".to_string(),
+                ))
+            }
+        };
+    }
+
+    Ok((key, time, condition))
+}
+
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, seconds, condition) = parse_expire(value, "expire", 1000)?;
+        Ok(Expire {
+            key,
+            seconds,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PExpire {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, milliseconds, condition) = parse_expire(value, "pexpire", 1)?;
+        Ok(PExpire {
+            key,
+            milliseconds,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ExpireAt {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp, condition) = parse_expire(value, "expireat", 1000)?;
+        Ok(ExpireAt {
+            key,
+            timestamp,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PExpireAt {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp, condition) = parse_expire(value, "pexpireat", 1)?;
+        Ok(PExpireAt {
+            key,
+            timestamp,
+            condition,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ExpireTime {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ExpireTime {
+            key: extract_key(args.next(), "key")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Ttl {
+            key: extract_key(args.next(), "key")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PTtl {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(PTtl {
+            key: extract_key(args.next(), "key")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Persist {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Persist {
+            key: extract_key(args.next(), "key")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{BulkString, RespFrame};
+
+    use super::*;
+    use crate::cmd::command;
+
+    #[test]
+    fn test_expire_parse_condition() -> Result<()> {
+        let cmd = Expire::try_from(command(&["expire", "key", "10", "XX", "GT"]))?;
+        assert_eq!(cmd.seconds, 10);
+        assert_eq!(cmd.condition, ExpireCondition::Gt);
+
+        assert!(Expire::try_from(command(&["expire", "key", "10", "NX", "GT"])).is_err());
+        assert!(Expire::try_from(command(&["expire", "key", "10", "GT", "LT"])).is_err());
+        assert!(Expire::try_from(command(&["expire", "key", "ten"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_and_ttl() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key", BulkString::new("value").into());
+
+        let ret = Ttl::try_from(command(&["ttl", "key"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(-1));
+        let ret = Expire::try_from(command(&["expire", "key", "100"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(1));
+        let ret = Ttl::try_from(command(&["ttl", "key"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(100));
+        let ret = Persist::try_from(command(&["persist", "key"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(1));
+        let ret = PTtl::try_from(command(&["pttl", "missing"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(-2));
+        Ok(())
+    }
+}
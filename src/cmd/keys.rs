@@ -0,0 +1,172 @@
+use crate::cmd::set::extract_keys;
+use crate::cmd::{extract_args, extract_key};
+use crate::{
+    Backend, CommandError, CommandExecutor, Del, Exists, RespArray, RespFrame, SimpleString, Touch,
+    Type, Unlink,
+};
+
+impl CommandExecutor for Del {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.del(&self.keys) as i64)
+    }
+}
+
+impl CommandExecutor for Unlink {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.unlink(&self.keys) as i64)
+    }
+}
+
+impl CommandExecutor for Exists {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.count_existing(&self.keys) as i64)
+    }
+}
+
+impl CommandExecutor for Type {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let name = backend.key_type(&self.key).unwrap_or("none");
+        SimpleString::new(name).into()
+    }
+}
+
+// there is no LRU clock to update, TOUCH only counts the keys
+impl CommandExecutor for Touch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.count_existing(&self.keys) as i64)
+    }
+}
+
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Del {
+            keys: extract_keys(extract_args(value, 1)?)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unlink {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Unlink {
+            keys: extract_keys(extract_args(value, 1)?)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Exists {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Exists {
+            keys: extract_keys(extract_args(value, 1)?)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Type {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Type {
+            key: extract_key(args.next(), "key")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Touch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Touch {
+            keys: extract_keys(extract_args(value, 1)?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::RESP_OK;
+    use crate::{BackendError, Command};
+
+    use super::*;
+    use crate::cmd::command;
+
+    fn run(backend: &Backend, args: &[&str]) -> Result<RespFrame> {
+        Ok(Command::try_from(command(args))?.execute(backend))
+    }
+
+    #[test]
+    fn test_key_commands() -> Result<()> {
+        let backend = Backend::new();
+        run(&backend, &["set", "string", "v"])?;
+        run(&backend, &["hset", "hash", "f", "v"])?;
+        run(&backend, &["rpush", "list", "a"])?;
+        run(&backend, &["sadd", "set", "a"])?;
+        run(&backend, &["zadd", "zset", "1", "a"])?;
+
+        for kind in ["string", "hash", "list", "set", "zset"] {
+            let ret = run(&backend, &["type", kind])?;
+            assert_eq!(ret, SimpleString::new(kind).into());
+        }
+        let ret = run(&backend, &["type", "missing"])?;
+        assert_eq!(ret, SimpleString::new("none").into());
+
+        // a key given twice is counted twice
+        let ret = run(&backend, &["exists", "string", "string", "missing"])?;
+        assert_eq!(ret, RespFrame::Integer(2));
+        let ret = run(&backend, &["touch", "hash", "missing"])?;
+        assert_eq!(ret, RespFrame::Integer(1));
+
+        let ret = run(&backend, &["del", "string", "hash", "missing"])?;
+        assert_eq!(ret, RespFrame::Integer(2));
+        let ret = run(&backend, &["exists", "string", "hash"])?;
+        assert_eq!(ret, RespFrame::Integer(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlink_large_value() -> Result<()> {
+        let backend = Backend::new();
+        let members = (0..1000).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut args = vec!["sadd", "set"];
+        args.extend(members.iter().map(|m| m.as_str()));
+        run(&backend, &args)?;
+        run(&backend, &["set", "string", "v"])?;
+
+        let ret = run(&backend, &["unlink", "set", "string", "missing"])?;
+        assert_eq!(ret, RespFrame::Integer(2));
+        assert!(!backend.contains_key(b"set"));
+        assert!(!backend.contains_key(b"string"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_type() -> Result<()> {
+        let backend = Backend::new();
+        run(&backend, &["set", "string", "v"])?;
+        run(&backend, &["hset", "hash", "f", "v"])?;
+        run(&backend, &["rpush", "list", "a"])?;
+        run(&backend, &["zadd", "zset", "1", "a"])?;
+
+        let wrong_type = RespFrame::from(BackendError::WrongType);
+        assert_eq!(run(&backend, &["get", "list"])?, wrong_type);
+        assert_eq!(run(&backend, &["hset", "string", "f", "v"])?, wrong_type);
+        assert_eq!(run(&backend, &["hgetall", "zset"])?, wrong_type);
+        assert_eq!(run(&backend, &["lpush", "hash", "a"])?, wrong_type);
+        assert_eq!(run(&backend, &["sadd", "zset", "a"])?, wrong_type);
+        assert_eq!(run(&backend, &["zadd", "list", "1", "a"])?, wrong_type);
+        assert_eq!(run(&backend, &["set", "list", "v", "GET"])?, wrong_type);
+
+        // SET replaces whatever the key holds
+        assert_eq!(run(&backend, &["set", "list", "v"])?, RESP_OK.clone());
+        assert_eq!(backend.key_type(b"list"), Some("string"));
+        Ok(())
+    }
+}
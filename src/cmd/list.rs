@@ -0,0 +1,492 @@
+use bytes::Bytes;
+
+use crate::cmd::{extract_args, extract_integer, extract_key, extract_string, RESP_OK};
+use crate::{
+    Backend, CommandError, CommandExecutor, LIndex, LInsert, LLen, LMove, LPop, LPos, LPush,
+    LPushX, LRange, LRem, LSet, LTrim, ListEnd, ListPosition, RPop, RPopLPush, RPush, RPushX,
+    RespArray, RespFrame, RespNull,
+};
+
+impl CommandExecutor for LPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, &self.key, ListEnd::Left, self.values, false)
+    }
+}
+
+impl CommandExecutor for RPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, &self.key, ListEnd::Right, self.values, false)
+    }
+}
+
+impl CommandExecutor for LPushX {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, &self.key, ListEnd::Left, self.values, true)
+    }
+}
+
+impl CommandExecutor for RPushX {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, &self.key, ListEnd::Right, self.values, true)
+    }
+}
+
+impl CommandExecutor for LPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        pop(backend, &self.key, ListEnd::Left, self.count)
+    }
+}
+
+impl CommandExecutor for RPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        pop(backend, &self.key, ListEnd::Right, self.count)
+    }
+}
+
+impl CommandExecutor for LRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lrange(&self.key, self.start, self.stop) {
+            Ok(values) => RespArray::new(values).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.llen(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lindex(&self.key, self.index) {
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lset(&self.key, self.index, self.value) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lrem(&self.key, self.count, &self.value) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ltrim(&self.key, self.start, self.stop) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.linsert(&self.key, self.before, &self.pivot, self.value) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let with_count = self.position.count.is_some();
+        match backend.lpos(&self.key, &self.value, self.position) {
+            Ok(positions) if with_count => RespArray::new(
+                positions
+                    .into_iter()
+                    .map(|i| RespFrame::Integer(i as i64))
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            Ok(positions) => match positions.first() {
+                Some(i) => RespFrame::Integer(*i as i64),
+                None => RespFrame::Null(RespNull),
+            },
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        lmove(backend, &self.source, &self.destination, self.from, self.to)
+    }
+}
+
+impl CommandExecutor for RPopLPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        lmove(
+            backend,
+            &self.source,
+            &self.destination,
+            ListEnd::Right,
+            ListEnd::Left,
+        )
+    }
+}
+
+fn push(
+    backend: &Backend,
+    key: &[u8],
+    end: ListEnd,
+    values: Vec<RespFrame>,
+    only_if_exists: bool,
+) -> RespFrame {
+    match backend.list_push(key, end, values, only_if_exists) {
+        Ok(len) => RespFrame::Integer(len as i64),
+        Err(e) => e.into(),
+    }
+}
+
+fn pop(backend: &Backend, key: &[u8], end: ListEnd, count: Option<usize>) -> RespFrame {
+    match (backend.list_pop(key, end, count.unwrap_or(1)), count) {
+        (Ok(Some(values)), Some(_)) => RespArray::new(values).into(),
+        (Ok(Some(mut values)), None) => values.pop().unwrap_or(RespFrame::Null(RespNull)),
+        (Ok(None), Some(_)) => RespArray::nill_new().into(),
+        (Ok(None), None) => RespFrame::Null(RespNull),
+        (Err(e), _) => e.into(),
+    }
+}
+
+fn lmove(
+    backend: &Backend,
+    source: &[u8],
+    destination: &[u8],
+    from: ListEnd,
+    to: ListEnd,
+) -> RespFrame {
+    match backend.lmove(source, destination, from, to) {
+        Ok(Some(value)) => value,
+        Ok(None) => RespFrame::Null(RespNull),
+        Err(e) => e.into(),
+    }
+}
+
+pub(super) fn syntax_error() -> CommandError {
+    CommandError::InvalidArguments("syntax error".to_string())
+}
+
+pub(super) fn extract_list_end(frame: Option<RespFrame>) -> Result<ListEnd, CommandError> {
+    match extract_string(frame, "direction")?
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "left" => Ok(ListEnd::Left),
+        "right" => Ok(ListEnd::Right),
+        _ => Err(syntax_error()),
+    }
+}
+
+// LPUSH key element [element ...]
+fn parse_push(value: RespArray) -> Result<(Bytes, Vec<RespFrame>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    Ok((key, args.collect()))
+}
+
+// LPOP key [count]
+fn parse_pop(value: RespArray) -> Result<(Bytes, Option<usize>), CommandError> {
+    if value.len() > 3 {
+        return Err(syntax_error());
+    }
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    let count = match args.next() {
+        Some(frame) => Some(usize::try_from(extract_integer(Some(frame))?).map_err(|_| {
+            CommandError::InvalidArguments("value is out of range, must be positive".to_string())
+        })?),
+        None => None,
+    };
+    Ok((key, count))
+}
+
+// LRANGE key start stop
+fn parse_range(value: RespArray) -> Result<(Bytes, i64, i64), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    Ok((
+        extract_key(args.next(), "key")?,
+        extract_integer(args.next())?,
+        extract_integer(args.next())?,
+    ))
+}
+
+impl TryFrom<RespArray> for LPush {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value)?;
+        Ok(LPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPush {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value)?;
+        Ok(RPush { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPushX {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value)?;
+        Ok(LPushX { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for RPushX {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, values) = parse_push(value)?;
+        Ok(RPushX { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for LPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value)?;
+        Ok(LPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for RPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value)?;
+        Ok(RPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, stop) = parse_range(value)?;
+        Ok(LRange { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LTrim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, stop) = parse_range(value)?;
+        Ok(LTrim { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LLen {
+            key: extract_key(args.next(), "key")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LIndex {
+            key: extract_key(args.next(), "key")?,
+            index: extract_integer(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LSet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LSet {
+            key: extract_key(args.next(), "key")?,
+            index: extract_integer(args.next())?,
+            value: args.next().ok_or_else(syntax_error)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LRem {
+            key: extract_key(args.next(), "key")?,
+            count: extract_integer(args.next())?,
+            value: args.next().ok_or_else(syntax_error)?,
+        })
+    }
+}
+
+// LINSERT key BEFORE | AFTER pivot element
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let before = match extract_string(args.next(), "position")?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "before" => true,
+            "after" => false,
+            _ => return Err(syntax_error()),
+        };
+        match (args.next(), args.next()) {
+            (Some(pivot), Some(value)) => Ok(LInsert {
+                key,
+                before,
+                pivot,
+                value,
+            }),
+            _ => Err(syntax_error()),
+        }
+    }
+}
+
+// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+impl TryFrom<RespArray> for LPos {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let value = args.next().ok_or_else(syntax_error)?;
+
+        let mut position = ListPosition::default();
+        while let Some(arg) = args.next() {
+            let option = extract_string(Some(arg), "option")?.to_ascii_lowercase();
+            let n = extract_integer(args.next())?;
+            match option.as_str() {
+                "rank" if n == 0 => {
+                    return Err(CommandError::InvalidArguments(
+                        "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                    ))
+                }
+                "rank" => position.rank = n,
+                "count" => {
+                    position.count = Some(usize::try_from(n).map_err(|_| {
+                        CommandError::InvalidArguments("COUNT can't be negative".to_string())
+                    })?)
+                }
+                "maxlen" => {
+                    position.maxlen = usize::try_from(n).map_err(|_| {
+                        CommandError::InvalidArguments("MAXLEN can't be negative".to_string())
+                    })?
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(LPos {
+            key,
+            value,
+            position,
+        })
+    }
+}
+
+// LMOVE source destination LEFT | RIGHT LEFT | RIGHT
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LMove {
+            source: extract_key(args.next(), "source")?,
+            destination: extract_key(args.next(), "destination")?,
+            from: extract_list_end(args.next())?,
+            to: extract_list_end(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for RPopLPush {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(RPopLPush {
+            source: extract_key(args.next(), "source")?,
+            destination: extract_key(args.next(), "destination")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{BackendError, BulkString};
+
+    use super::*;
+    use crate::cmd::command;
+
+    #[test]
+    fn test_list_commands() -> Result<()> {
+        let backend = Backend::new();
+
+        let ret = RPush::try_from(command(&["rpush", "list", "a", "b", "c"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(3));
+        let ret = LPop::try_from(command(&["lpop", "list"]))?.execute(&backend);
+        assert_eq!(ret, BulkString::new("a").into());
+        let ret = RPop::try_from(command(&["rpop", "list", "5"]))?.execute(&backend);
+        assert_eq!(
+            ret,
+            RespArray::new(vec![
+                BulkString::new("c").into(),
+                BulkString::new("b").into()
+            ])
+            .into()
+        );
+        let ret = RPop::try_from(command(&["rpop", "list", "5"]))?.execute(&backend);
+        assert_eq!(ret, RespArray::nill_new().into());
+
+        backend.set(b"string", BulkString::new("v").into());
+        let ret = LPush::try_from(command(&["lpush", "string", "a"]))?.execute(&backend);
+        assert_eq!(ret, BackendError::WrongType.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_parse_errors() {
+        assert!(LPop::try_from(command(&["lpop", "list", "-1"])).is_err());
+        assert!(LPos::try_from(command(&["lpos", "list", "a", "RANK", "0"])).is_err());
+        assert!(LInsert::try_from(command(&["linsert", "list", "middle", "a", "b"])).is_err());
+        assert!(LMove::try_from(command(&["lmove", "a", "b", "LEFT", "UP"])).is_err());
+    }
+}
@@ -1,19 +1,40 @@
-use crate::cmd::{extract_args, validate_command, RESP_OK};
-use crate::{Backend, CommandError, CommandExecutor, Get, RespArray, RespFrame, RespNull, Set};
+use crate::cmd::{extract_args, extract_integer, extract_string, SetTtl, RESP_OK};
+use crate::{
+    now_millis, Backend, CommandError, CommandExecutor, Get, RespArray, RespFrame, RespNull, Set,
+    SetCondition, SetExpiry,
+};
 
 impl CommandExecutor for Get {
     fn execute(self, backend: &Backend) -> RespFrame {
         match backend.get(&self.key) {
-            Some(v) => v,
-            None => RespFrame::Null(RespNull),
+            Ok(Some(v)) => v.into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for Set {
     fn execute(self, backend: &Backend) -> RespFrame {
-        backend.set(&self.key, self.value);
-        RESP_OK.clone()
+        // SET without GET overwrites any kind of value
+        if self.get {
+            if let Err(e) = backend.get(&self.key) {
+                return e.into();
+            }
+        }
+
+        let expiry = match self.ttl.map(|ttl| ttl_expiry(ttl, "set")) {
+            None => SetExpiry::Clear,
+            Some(Ok(expiry)) => expiry,
+            Some(Err(e)) => return e,
+        };
+
+        let (written, old) = backend.set_with(&self.key, self.value, self.condition, expiry);
+        match (self.get, written) {
+            (true, _) => old.map_or(RespFrame::Null(RespNull), RespFrame::from),
+            (false, true) => RESP_OK.clone(),
+            (false, false) => RespFrame::Null(RespNull),
+        }
     }
 }
 
@@ -21,32 +42,207 @@ impl TryFrom<RespArray> for Get {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["get"], 1)?;
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(Get {
-                key: String::from_utf8(key.0)?,
-            }),
+            Some(RespFrame::BulkString(key)) => Ok(Get { key: key.0 }),
             _ => Err(CommandError::InvalidArguments("Invalid key".to_string())),
         }
     }
 }
 
+// SET key value [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds
+// | PXAT unix-time-milliseconds | KEEPTTL]
 impl TryFrom<RespArray> for Set {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["set"], 2)?;
-
         let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(Set {
-                key: String::from_utf8(key.0)?,
-                value,
-            }),
-            _ => Err(CommandError::InvalidArguments(
-                "Invalid key or value".to_string(),
-            )),
+        let (key, value) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(value))) => {
+                (key.0, value.into())
+            }
+            _ => {
+                return Err(CommandError::InvalidArguments(
+                    "Invalid key or value".to_string(),
+                ))
+            }
+        };
+
+        let mut condition = SetCondition::Always;
+        let mut ttl = None;
+        let mut get = false;
+        while let Some(arg) = args.next() {
+            let option = extract_string(Some(arg), "option")?.to_ascii_lowercase();
+            match option.as_str() {
+                "nx" | "xx" if condition != SetCondition::Always => return Err(syntax_error()),
+                "nx" => condition = SetCondition::Nx,
+                "xx" => condition = SetCondition::Xx,
+                "get" => get = true,
+                "keepttl" if ttl.is_none() => ttl = Some(SetTtl::Keep),
+                "ex" | "px" | "exat" | "pxat" if ttl.is_none() => {
+                    ttl = Some(extract_ttl(&option, args.next(), "set")?);
+                }
+                _ => return Err(syntax_error()),
+            }
         }
+
+        Ok(Set {
+            key,
+            value,
+            condition,
+            ttl,
+            get,
+        })
+    }
+}
+
+// the time of EX seconds, PX milliseconds, EXAT unix-time-seconds or PXAT
+// unix-time-milliseconds, `option` is the lowercase option name
+pub(super) fn extract_ttl(
+    option: &str,
+    frame: Option<RespFrame>,
+    command: &str,
+) -> Result<SetTtl, CommandError> {
+    let time = extract_integer(frame)?;
+    let unit = if option.starts_with('e') { 1000 } else { 1 };
+    let time = match time.checked_mul(unit) {
+        Some(time) if time > 0 => time,
+        _ => return Err(invalid_expire_time(command)),
+    };
+    Ok(if option.ends_with("at") {
+        SetTtl::Absolute(time)
+    } else {
+        SetTtl::Relative(time)
+    })
+}
+
+// relative times are turned into a deadline now, when the command runs
+pub(super) fn ttl_expiry(ttl: SetTtl, command: &str) -> Result<SetExpiry, RespFrame> {
+    match ttl {
+        SetTtl::Keep => Ok(SetExpiry::Keep),
+        SetTtl::Absolute(deadline) => Ok(SetExpiry::At(deadline)),
+        SetTtl::Relative(ttl) => now_millis()
+            .checked_add(ttl)
+            .map(SetExpiry::At)
+            .ok_or_else(|| invalid_expire_time(command).into()),
+    }
+}
+
+pub(super) fn invalid_expire_time(command: &str) -> CommandError {
+    CommandError::InvalidArguments(format!("invalid expire time in '{}' command", command))
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArguments("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{BackendError, BulkString, Command, HGet, HGetAll, HSet, KeyExpiry, SimpleError};
+
+    use super::*;
+    use crate::cmd::command;
+
+    #[test]
+    fn test_set_parse_options() -> Result<()> {
+        let cmd = Set::try_from(command(&["set", "key", "value", "EX", "10", "NX", "GET"]))?;
+        assert_eq!(cmd.condition, SetCondition::Nx);
+        assert_eq!(cmd.ttl, Some(SetTtl::Relative(10_000)));
+        assert!(cmd.get);
+
+        let cmd = Set::try_from(command(&["set", "key", "value", "pxat", "1700000000000"]))?;
+        assert_eq!(cmd.ttl, Some(SetTtl::Absolute(1_700_000_000_000)));
+
+        assert!(Set::try_from(command(&["set", "key", "value", "NX", "XX"])).is_err());
+        assert!(Set::try_from(command(&["set", "key", "value", "EX", "10", "KEEPTTL"])).is_err());
+        assert!(Set::try_from(command(&["set", "key", "value", "EX", "0"])).is_err());
+        assert!(Set::try_from(command(&["set", "key", "value", "EX"])).is_err());
+        assert!(Set::try_from(command(&["set", "key", "value", "FOO"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_conditional_execute() -> Result<()> {
+        let backend = Backend::new();
+
+        let ret = Set::try_from(command(&["set", "key", "v1", "XX"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Null(RespNull));
+        let ret =
+            Set::try_from(command(&["set", "key", "v1", "NX", "EX", "100"]))?.execute(&backend);
+        assert_eq!(ret, RESP_OK.clone());
+        let ret = Set::try_from(command(&["set", "key", "v2", "NX"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Null(RespNull));
+
+        let ret =
+            Set::try_from(command(&["set", "key", "v2", "KEEPTTL", "GET"]))?.execute(&backend);
+        assert_eq!(ret, BulkString::new("v1").into());
+        assert!(matches!(backend.expiry(b"key"), KeyExpiry::At(_)));
+
+        let ret = Set::try_from(command(&["set", "key", "v3"]))?.execute(&backend);
+        assert_eq!(ret, RESP_OK.clone());
+        assert_eq!(backend.expiry(b"key"), KeyExpiry::Persistent);
+        assert_eq!(backend.get(b"key"), Ok(Some(BulkString::new("v3").into())));
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_keys() -> Result<()> {
+        let backend = Backend::new();
+        let key = b"\xff\x00key\r\n".to_vec();
+        let binary = |args: &[&[u8]]| {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::new(arg.to_vec()).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+        };
+
+        Set::try_from(binary(&[b"set", &key, b"v"]))?.execute(&backend);
+        let ret = Get::try_from(binary(&[b"get", &key]))?.execute(&backend);
+        assert_eq!(ret, BulkString::new("v").into());
+        assert_eq!(backend.get(b"\xff\x00key"), Ok(None));
+
+        let hash = b"\xff\x00hash".to_vec();
+        HSet::try_from(binary(&[b"hset", &hash, b"\xfe", b"v"]))?.execute(&backend);
+        let ret = HGet::try_from(binary(&[b"hget", &hash, b"\xfe"]))?.execute(&backend);
+        assert_eq!(ret, BulkString::new("v").into());
+        let ret = HGetAll::try_from(binary(&[b"hgetall", &hash]))?.execute(&backend);
+        assert_eq!(
+            ret,
+            RespArray::new(vec![
+                BulkString::new(b"\xfe".to_vec()).into(),
+                BulkString::new("v").into()
+            ])
+            .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_error_replies() {
+        let reply = |args: &[&str]| match Command::try_from(command(args)) {
+            Ok(_) => panic!("{:?} should not parse", args),
+            Err(err) => RespFrame::from(err),
+        };
+
+        assert_eq!(
+            reply(&["get"]),
+            SimpleError::new("ERR wrong number of arguments for 'get' command").into()
+        );
+        assert_eq!(
+            reply(&["set", "key", "value", "FOO"]),
+            SimpleError::new("ERR syntax error").into()
+        );
+        assert_eq!(
+            reply(&["set", "key", "value", "EX", "ten"]),
+            SimpleError::new("ERR value is not an integer or out of range").into()
+        );
+        assert_eq!(
+            RespFrame::from(CommandError::from(BackendError::WrongType)),
+            SimpleError::new("WRONGTYPE Operation against a key holding the wrong kind of value")
+                .into()
+        );
     }
 }
@@ -1,33 +1,73 @@
+use bytes::Bytes;
+
+use std::time::Duration;
+
 use anyhow::Result;
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
-use log::info;
 use thiserror::Error;
-use tracing::warn;
 
-use crate::{Backend, RespArray, RespError, RespFrame, SimpleString};
+use crate::{
+    Aggregate, Backend, BackendError, ExpireCondition, ListEnd, ListPosition, RespArray, RespError,
+    RespFrame, ScanOptions, SetCondition, SimpleError, SimpleString, StringValue, Subscriber,
+    ZAddOptions, ZRangeBy,
+};
 
+mod blocking;
+mod command;
+mod connection;
+mod db;
 mod echo;
+mod expire;
 mod hmap;
+mod keys;
+mod list;
 mod map;
+mod persistence;
+mod pubsub;
+mod scan;
+mod set;
+mod string;
+mod transaction;
+mod zset;
+
+pub use command::{is_write_command, lookup_command, CommandSpec, COMMAND_TABLE};
+pub use connection::Client;
+pub use transaction::Transaction;
 
 lazy_static! {
     static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
 }
 
+/// Why a request is not a valid command. The message starts with the redis error code the
+/// client gets, the connection stays open.
 #[derive(Debug, Error)]
 pub enum CommandError {
-    #[error("invalid command: {0}")]
+    #[error("ERR {0}")]
     InvalidCommand(String),
 
-    #[error("invalid arguments: {0}")]
+    #[error("ERR {0}")]
     InvalidArguments(String),
+    #[error("ERR unknown command '{0}', with args beginning with: {1}")]
+    UnknownCommand(String, String),
+    #[error("ERR wrong number of arguments for '{0}' command")]
+    WrongArity(String),
+    #[error("NOPERM {0}")]
+    NoPermission(String),
     #[error("{0}")]
+    Backend(#[from] BackendError),
+    #[error("ERR {0}")]
     RespError(#[from] RespError),
-    #[error("Utf8 error:{0}")]
+    #[error("ERR invalid UTF-8 argument: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 }
 
+impl From<CommandError> for RespFrame {
+    fn from(value: CommandError) -> Self {
+        SimpleError::new(value.to_string()).into()
+    }
+}
+
 #[enum_dispatch]
 pub trait CommandExecutor {
     fn execute(self, backend: &Backend) -> RespFrame;
@@ -38,49 +78,844 @@ pub trait CommandExecutor {
 pub enum Command {
     Get(Get),
     Set(Set),
+    SetNx(SetNx),
+    SetEx(SetEx),
+    PSetEx(PSetEx),
+    GetDel(GetDel),
+    GetEx(GetEx),
+    Incr(Incr),
+    Decr(Decr),
+    IncrBy(IncrBy),
+    DecrBy(DecrBy),
+    IncrByFloat(IncrByFloat),
+    Append(Append),
+    StrLen(StrLen),
+    GetRange(GetRange),
+    SetRange(SetRange),
+    Lcs(Lcs),
+    MGet(MGet),
+    MSet(MSet),
+    MSetNx(MSetNx),
     HGet(HGet),
     HGetAll(HGetAll),
     HSet(HSet),
+    HScan(HScan),
+    Del(Del),
+    Unlink(Unlink),
+    Exists(Exists),
+    Type(Type),
+    Touch(Touch),
+    SwapDb(SwapDb),
+    Move(Move),
+    DbSize(DbSize),
+    FlushDb(FlushDb),
+    FlushAll(FlushAll),
+    Keys(Keys),
+    Scan(Scan),
     Echo(Echo),
-    Unrecognized(Unrecognized),
+    Ping(Ping),
+    Expire(Expire),
+    PExpire(PExpire),
+    ExpireAt(ExpireAt),
+    PExpireAt(PExpireAt),
+    ExpireTime(ExpireTime),
+    Ttl(Ttl),
+    PTtl(PTtl),
+    Persist(Persist),
+    Save(Save),
+    BgSave(BgSave),
+    BgRewriteAof(BgRewriteAof),
+    LPush(LPush),
+    RPush(RPush),
+    LPushX(LPushX),
+    RPushX(RPushX),
+    LPop(LPop),
+    RPop(RPop),
+    LRange(LRange),
+    LLen(LLen),
+    LIndex(LIndex),
+    LSet(LSet),
+    LRem(LRem),
+    LTrim(LTrim),
+    LInsert(LInsert),
+    LPos(LPos),
+    LMove(LMove),
+    RPopLPush(RPopLPush),
+    BLPop(BLPop),
+    BRPop(BRPop),
+    BLMove(BLMove),
+    BLMPop(BLMPop),
+    SAdd(SAdd),
+    SRem(SRem),
+    SMembers(SMembers),
+    SIsMember(SIsMember),
+    SMIsMember(SMIsMember),
+    SCard(SCard),
+    SPop(SPop),
+    SRandMember(SRandMember),
+    SInter(SInter),
+    SUnion(SUnion),
+    SDiff(SDiff),
+    SInterStore(SInterStore),
+    SUnionStore(SUnionStore),
+    SDiffStore(SDiffStore),
+    SInterCard(SInterCard),
+    SMove(SMove),
+    SScan(SScan),
+    ZAdd(ZAdd),
+    ZIncrBy(ZIncrBy),
+    ZRem(ZRem),
+    ZScore(ZScore),
+    ZCard(ZCard),
+    ZRank(ZRank),
+    ZRevRank(ZRevRank),
+    ZRange(ZRange),
+    ZRangeByScore(ZRangeByScore),
+    ZCount(ZCount),
+    ZLexCount(ZLexCount),
+    ZPopMin(ZPopMin),
+    ZPopMax(ZPopMax),
+    ZUnionStore(ZUnionStore),
+    ZInterStore(ZInterStore),
+    ZRandMember(ZRandMember),
+    ZScan(ZScan),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    Publish(Publish),
+    PubSub(PubSub),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    Hello(Hello),
+    Select(Select),
+    Commands(Commands),
 }
 
 #[derive(Debug)]
 pub struct Get {
-    key: String,
+    key: Bytes,
 }
 
 #[derive(Debug)]
 pub struct Set {
-    key: String,
-    value: RespFrame,
+    key: Bytes,
+    value: StringValue,
+    condition: SetCondition,
+    ttl: Option<SetTtl>,
+    get: bool,
+}
+
+// EX / PX / EXAT / PXAT / KEEPTTL of the SET command, all times in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SetTtl {
+    Relative(i64),
+    Absolute(i64),
+    Keep,
+}
+
+#[derive(Debug)]
+pub struct SetNx {
+    key: Bytes,
+    value: StringValue,
+}
+
+#[derive(Debug)]
+pub struct SetEx {
+    key: Bytes,
+    milliseconds: i64,
+    value: StringValue,
+}
+
+#[derive(Debug)]
+pub struct PSetEx {
+    key: Bytes,
+    milliseconds: i64,
+    value: StringValue,
+}
+
+#[derive(Debug)]
+pub struct GetDel {
+    key: Bytes,
+}
+
+// without an option the time to live is left alone
+#[derive(Debug)]
+pub struct GetEx {
+    key: Bytes,
+    ttl: Option<SetTtl>,
+    persist: bool,
+}
+
+#[derive(Debug)]
+pub struct Incr {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct Decr {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct IncrBy {
+    key: Bytes,
+    increment: i64,
+}
+
+#[derive(Debug)]
+pub struct DecrBy {
+    key: Bytes,
+    decrement: i64,
+}
+
+#[derive(Debug)]
+pub struct IncrByFloat {
+    key: Bytes,
+    increment: f64,
+}
+
+#[derive(Debug)]
+pub struct Append {
+    key: Bytes,
+    value: Bytes,
+}
+
+#[derive(Debug)]
+pub struct StrLen {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct GetRange {
+    key: Bytes,
+    start: i64,
+    end: i64,
+}
+
+#[derive(Debug)]
+pub struct SetRange {
+    key: Bytes,
+    offset: usize,
+    value: Bytes,
+}
+
+#[derive(Debug)]
+pub struct Lcs {
+    first: Bytes,
+    second: Bytes,
+    len: bool,
+    idx: bool,
+    min_match_len: usize,
+    with_match_len: bool,
+}
+
+#[derive(Debug)]
+pub struct MGet {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct MSet {
+    pairs: Vec<(Bytes, StringValue)>,
+}
+
+#[derive(Debug)]
+pub struct MSetNx {
+    pairs: Vec<(Bytes, StringValue)>,
 }
 
 #[derive(Debug)]
 pub struct HGet {
-    key: String,
-    field: String,
+    key: Bytes,
+    field: Bytes,
 }
 
 #[derive(Debug)]
 pub struct HGetAll {
-    key: String,
+    key: Bytes,
     sort: bool,
 }
 
 #[derive(Debug)]
 pub struct HSet {
-    key: String,
-    field: String,
+    key: Bytes,
+    field: Bytes,
     value: RespFrame,
 }
 
 #[derive(Debug)]
-pub struct Unrecognized;
+pub struct HScan {
+    key: Bytes,
+    cursor: u64,
+    options: ScanOptions,
+    // NOVALUES, only the fields
+    no_values: bool,
+}
+
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct Unlink {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct Type {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct Touch {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct SwapDb {
+    first: i64,
+    second: i64,
+}
+
+#[derive(Debug)]
+pub struct Move {
+    key: Bytes,
+    db: i64,
+}
+
+#[derive(Debug)]
+pub struct DbSize;
+
+// ASYNC frees the values in the background
+#[derive(Debug)]
+pub struct FlushDb {
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct FlushAll {
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct Keys {
+    pattern: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    options: ScanOptions,
+}
+
+#[derive(Debug)]
+pub struct Expire {
+    key: Bytes,
+    seconds: i64,
+    condition: ExpireCondition,
+}
+
+#[derive(Debug)]
+pub struct PExpire {
+    key: Bytes,
+    milliseconds: i64,
+    condition: ExpireCondition,
+}
+
+#[derive(Debug)]
+pub struct ExpireAt {
+    key: Bytes,
+    timestamp: i64,
+    condition: ExpireCondition,
+}
+
+#[derive(Debug)]
+pub struct PExpireAt {
+    key: Bytes,
+    timestamp: i64,
+    condition: ExpireCondition,
+}
+
+#[derive(Debug)]
+pub struct ExpireTime {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct Ttl {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct PTtl {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct Persist {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct Save;
+
+#[derive(Debug)]
+pub struct BgSave;
+
+#[derive(Debug)]
+pub struct BgRewriteAof;
+
+#[derive(Debug)]
+pub struct LPush {
+    key: Bytes,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPush {
+    key: Bytes,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct LPushX {
+    key: Bytes,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct RPushX {
+    key: Bytes,
+    values: Vec<RespFrame>,
+}
+
+#[derive(Debug)]
+pub struct LPop {
+    key: Bytes,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct RPop {
+    key: Bytes,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: Bytes,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LSet {
+    key: Bytes,
+    index: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LRem {
+    key: Bytes,
+    count: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LTrim {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LInsert {
+    key: Bytes,
+    before: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LPos {
+    key: Bytes,
+    value: RespFrame,
+    position: ListPosition,
+}
+
+#[derive(Debug)]
+pub struct LMove {
+    source: Bytes,
+    destination: Bytes,
+    from: ListEnd,
+    to: ListEnd,
+}
+
+#[derive(Debug)]
+pub struct RPopLPush {
+    source: Bytes,
+    destination: Bytes,
+}
+
+#[derive(Debug)]
+pub struct BLPop {
+    keys: Vec<Bytes>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BRPop {
+    keys: Vec<Bytes>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMove {
+    source: Bytes,
+    destination: Bytes,
+    from: ListEnd,
+    to: ListEnd,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMPop {
+    keys: Vec<Bytes>,
+    end: ListEnd,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct SAdd {
+    key: Bytes,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SRem {
+    key: Bytes,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SMembers {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct SIsMember {
+    key: Bytes,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: Bytes,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SCard {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct SPop {
+    key: Bytes,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct SRandMember {
+    key: Bytes,
+    count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SInter {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct SUnion {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct SDiff {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct SInterStore {
+    destination: Bytes,
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct SUnionStore {
+    destination: Bytes,
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct SDiffStore {
+    destination: Bytes,
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<Bytes>,
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct SMove {
+    source: Bytes,
+    destination: Bytes,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SScan {
+    key: Bytes,
+    cursor: u64,
+    options: ScanOptions,
+}
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: Bytes,
+    options: ZAddOptions,
+    incr: bool,
+    members: Vec<(f64, Vec<u8>)>,
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: Bytes,
+    increment: f64,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: Bytes,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: Bytes,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: Bytes,
+}
+
+#[derive(Debug)]
+pub struct ZRank {
+    key: Bytes,
+    member: Vec<u8>,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRevRank {
+    key: Bytes,
+    member: Vec<u8>,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRange {
+    key: Bytes,
+    by: ZRangeBy,
+    rev: bool,
+    limit: Option<(i64, i64)>,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRangeByScore {
+    key: Bytes,
+    by: ZRangeBy,
+    limit: Option<(i64, i64)>,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: Bytes,
+    by: ZRangeBy,
+}
+
+#[derive(Debug)]
+pub struct ZLexCount {
+    key: Bytes,
+    by: ZRangeBy,
+}
+
+#[derive(Debug)]
+pub struct ZPopMin {
+    key: Bytes,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZPopMax {
+    key: Bytes,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZUnionStore {
+    destination: Bytes,
+    keys: Vec<Bytes>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug)]
+pub struct ZInterStore {
+    destination: Bytes,
+    keys: Vec<Bytes>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug)]
+pub struct ZRandMember {
+    key: Bytes,
+    count: Option<i64>,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZScan {
+    key: Bytes,
+    cursor: u64,
+    options: ScanOptions,
+}
+
+#[derive(Debug)]
+pub struct Subscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Unsubscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: Vec<u8>,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PubSub {
+    subcommand: PubSubSubcommand,
+}
+
+// CHANNELS [pattern] / NUMSUB [channel ...] / NUMPAT
+#[derive(Debug, Clone, PartialEq)]
+enum PubSubSubcommand {
+    Channels(Option<Vec<u8>>),
+    NumSub(Vec<Vec<u8>>),
+    NumPat,
+}
+
+#[derive(Debug)]
+pub struct Multi;
+
+#[derive(Debug)]
+pub struct Exec;
+
+#[derive(Debug)]
+pub struct Discard;
+
+#[derive(Debug)]
+pub struct Watch {
+    keys: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+pub struct Unwatch;
+
+#[derive(Debug)]
+pub struct Hello {
+    protover: Option<i64>,
+    auth: Option<(String, String)>,
+    setname: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Select {
+    index: i64,
+}
+
+// COMMAND, named after what it lists so it does not clash with the `Command` enum
+#[derive(Debug)]
+pub struct Commands {
+    subcommand: CommandsSubcommand,
+}
+
+// no subcommand / COUNT / INFO [name ...] / DOCS [name ...] / GETKEYS command [arg ...]
+#[derive(Debug, Clone, PartialEq)]
+enum CommandsSubcommand {
+    List,
+    Count,
+    Info(Vec<Vec<u8>>),
+    Docs(Vec<Vec<u8>>),
+    GetKeys(Vec<RespFrame>),
+}
 
 #[derive(Debug)]
 pub struct Echo {
-    value: String,
+    value: Bytes,
+}
+
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<Bytes>,
 }
 
 impl TryFrom<RespFrame> for Command {
@@ -95,74 +930,202 @@ impl TryFrom<RespFrame> for Command {
         }
     }
 }
-impl CommandExecutor for Unrecognized {
-    fn execute(self, _backend: &Backend) -> RespFrame {
-        RESP_OK.clone()
+impl Command {
+    /// Whether the command may wait for other clients before replying
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            Command::BLPop(_) | Command::BRPop(_) | Command::BLMove(_) | Command::BLMPop(_)
+        )
     }
-}
 
-impl TryFrom<RespArray> for Command {
-    type Error = CommandError;
+    /// Execute the command, blocking commands wait here until they are served or time out.
+    /// Every other command replies right away like `execute`.
+    pub async fn execute_async(self, backend: &Backend) -> RespFrame {
+        match self {
+            Command::BLPop(cmd) => cmd.execute_blocking(backend).await,
+            Command::BRPop(cmd) => cmd.execute_blocking(backend).await,
+            Command::BLMove(cmd) => cmd.execute_blocking(backend).await,
+            Command::BLMPop(cmd) => cmd.execute_blocking(backend).await,
+            cmd => cmd.execute(backend),
+        }
+    }
 
-    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        match value.first() {
-            Some(RespFrame::BulkString(ref cmd)) => match cmd.as_ref() {
-                b"get" => Ok(Get::try_from(value)?.into()),
-                b"set" => Ok(Set::try_from(value)?.into()),
-                b"hget" => Ok(HGet::try_from(value)?.into()),
-                b"hset" => Ok(HSet::try_from(value)?.into()),
-                b"hgetall" => Ok(HGetAll::try_from(value)?.into()),
-                b"echo" => Ok(Echo::try_from(value)?.into()),
-                b"COMMAND" => {
-                    info!("connect redis server");
-                    Ok(Unrecognized.into())
-                }
-                _ => {
-                    warn!("cmd unrecognized,cmd: {:?}", cmd);
-                    Ok(Unrecognized.into())
-                }
-            },
-            _ => Err(CommandError::InvalidCommand(
-                "Command must have a BulkString as the first argument".to_string(),
-            )),
+    /// Whether the command changes the subscriptions of the connection,
+    /// the only kind of command a RESP2 client may send while subscribed
+    pub fn is_subscription(&self) -> bool {
+        matches!(
+            self,
+            Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::PSubscribe(_)
+                | Command::PUnsubscribe(_)
+        )
+    }
+
+    /// Execute a subscription command for the connection owning `subscriber`,
+    /// there is one reply per channel or pattern
+    pub fn execute_subscription(
+        self,
+        backend: &Backend,
+        subscriber: &mut Subscriber,
+    ) -> Vec<RespFrame> {
+        match self {
+            Command::Subscribe(cmd) => cmd.execute_for(subscriber),
+            Command::Unsubscribe(cmd) => cmd.execute_for(subscriber),
+            Command::PSubscribe(cmd) => cmd.execute_for(subscriber),
+            Command::PUnsubscribe(cmd) => cmd.execute_for(subscriber),
+            cmd => vec![cmd.execute(backend)],
+        }
+    }
+
+    /// Whether the command starts, ends or prepares a transaction,
+    /// those run right away even while the connection queues commands
+    pub fn is_transaction(&self) -> bool {
+        matches!(
+            self,
+            Command::Multi(_)
+                | Command::Exec(_)
+                | Command::Discard(_)
+                | Command::Watch(_)
+                | Command::Unwatch(_)
+        )
+    }
+
+    /// Execute a transaction command for the connection owning `transaction`
+    pub fn execute_transaction(
+        self,
+        backend: &Backend,
+        client: &mut Client,
+        transaction: &mut Transaction,
+    ) -> RespFrame {
+        match self {
+            Command::Multi(cmd) => cmd.execute_for(transaction),
+            Command::Exec(cmd) => cmd.execute_for(backend, client, transaction),
+            Command::Discard(cmd) => cmd.execute_for(transaction),
+            Command::Watch(cmd) => cmd.execute_for(backend, transaction),
+            Command::Unwatch(cmd) => cmd.execute_for(transaction),
+            cmd => cmd.execute(backend),
+        }
+    }
+
+    /// Whether the command changes what the connection negotiated, like its protocol
+    /// or its database
+    pub fn is_connection(&self) -> bool {
+        matches!(self, Command::Hello(_) | Command::Select(_))
+    }
+
+    /// Execute a connection command for the connection described by `client`
+    pub fn execute_connection(self, backend: &Backend, client: &mut Client) -> RespFrame {
+        match self {
+            Command::Hello(cmd) => cmd.execute_for(client),
+            Command::Select(cmd) => cmd.execute_for(backend, client),
+            cmd => cmd.execute(backend),
+        }
+    }
+
+    /// The key whose time to live is given relative to now by this command
+    pub(crate) fn relative_ttl_key(&self) -> Option<&[u8]> {
+        match self {
+            Command::Expire(cmd) => Some(&cmd.key),
+            Command::PExpire(cmd) => Some(&cmd.key),
+            Command::Set(cmd) if matches!(cmd.ttl, Some(SetTtl::Relative(_))) => Some(&cmd.key),
+            Command::SetEx(cmd) => Some(&cmd.key),
+            Command::PSetEx(cmd) => Some(&cmd.key),
+            Command::GetEx(cmd) if matches!(cmd.ttl, Some(SetTtl::Relative(_))) => Some(&cmd.key),
+            _ => None,
         }
     }
 }
 
-fn validate_command(
-    value: &RespArray,
-    names: &[&'static str],
-    n_args: usize,
-) -> Result<(), CommandError> {
-    if value.len() != n_args + names.len() {
-        return Err(CommandError::InvalidArguments(format!(
-            "{} command must have exactly {} argument",
-            names.join(" "),
-            n_args
-        )));
-    }
-
-    for (i, name) in names.iter().enumerate() {
-        match value[i] {
-            RespFrame::BulkString(ref cmd) => {
-                if cmd.as_ref().to_ascii_lowercase() != name.as_bytes() {
-                    return Err(CommandError::InvalidCommand(format!(
-                        "Invalid command: expected {}, got {}",
-                        name,
-                        String::from_utf8_lossy(cmd.as_ref())
-                    )));
-                }
+impl TryFrom<RespArray> for Command {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let spec = match value.first() {
+            Some(RespFrame::BulkString(ref name)) => {
+                lookup_command(name).ok_or_else(|| unknown_command(&value))?
             }
             _ => {
                 return Err(CommandError::InvalidCommand(
                     "Command must have a BulkString as the first argument".to_string(),
-                ));
+                ))
             }
+        };
+        // the commands parse their arguments knowing there are enough of them
+        if !spec.check_arity(value.len()) {
+            return Err(CommandError::WrongArity(spec.name.to_string()));
         }
+        (spec.parse)(value)
     }
-    Ok(())
+}
+
+// redis quotes the start of the arguments to help spotting a typo in the name
+fn unknown_command(value: &RespArray) -> CommandError {
+    let quote = |frame: &RespFrame| match frame {
+        // the message goes out as a simple error, it can not span lines
+        RespFrame::BulkString(arg) => String::from_utf8_lossy(arg).replace(['\r', '\n'], " "),
+        _ => String::new(),
+    };
+    let mut args = String::new();
+    for arg in value.iter().skip(1) {
+        if args.len() >= 128 {
+            break;
+        }
+        args.push_str(&format!("'{}' ", quote(arg)));
+    }
+    CommandError::UnknownCommand(quote(&value[0]), args)
 }
 
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
+
+fn extract_string(frame: Option<RespFrame>, name: &str) -> Result<String, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(value)) => Ok(String::from_utf8(value.0.into())?),
+        _ => Err(CommandError::InvalidArguments(format!("Invalid {}", name))),
+    }
+}
+
+// keys are binary safe, any byte string is a valid key
+fn extract_key(frame: Option<RespFrame>, name: &str) -> Result<Bytes, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(value)) => Ok(value.0),
+        _ => Err(CommandError::InvalidArguments(format!("Invalid {}", name))),
+    }
+}
+
+fn extract_bytes(frame: Option<RespFrame>, name: &str) -> Result<Vec<u8>, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(value)) => Ok(value.0.into()),
+        _ => Err(CommandError::InvalidArguments(format!("Invalid {}", name))),
+    }
+}
+
+fn extract_integer(frame: Option<RespFrame>) -> Result<i64, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(value)) => std::str::from_utf8(&value)
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .ok_or_else(|| {
+                CommandError::InvalidArguments(
+                    "value is not an integer or out of range".to_string(),
+                )
+            }),
+        Some(RespFrame::Integer(value)) => Ok(value),
+        _ => Err(CommandError::InvalidArguments(
+            "value is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+/// A request as a client sends it, for tests
+#[cfg(test)]
+pub(crate) fn command(args: &[&str]) -> RespArray {
+    RespArray::new(
+        args.iter()
+            .map(|arg| crate::BulkString::new(arg.as_bytes().to_vec()).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
@@ -0,0 +1,60 @@
+use crate::cmd::RESP_OK;
+use crate::{
+    rdb, AofError, Backend, BgRewriteAof, BgSave, CommandError, CommandExecutor, RespArray,
+    RespFrame, Save, SimpleError, SimpleString,
+};
+
+impl CommandExecutor for Save {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match rdb::save(backend) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl CommandExecutor for BgSave {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match rdb::bgsave(backend) {
+            Ok(_) => SimpleString::new("Background saving started").into(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl CommandExecutor for BgRewriteAof {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match backend.aof() {
+            Some(aof) => aof.rewrite_background(backend),
+            None => Err(AofError::Disabled),
+        };
+        match ret {
+            Ok(_) => SimpleString::new("Background append only file rewriting started").into(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Save {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Save)
+    }
+}
+
+impl TryFrom<RespArray> for BgSave {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(BgSave)
+    }
+}
+
+impl TryFrom<RespArray> for BgRewriteAof {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(BgRewriteAof)
+    }
+}
@@ -0,0 +1,290 @@
+use crate::cmd::set::extract_members;
+use crate::cmd::PubSubSubcommand;
+use crate::cmd::{extract_args, extract_bytes, extract_string};
+use crate::{
+    Backend, BulkString, CommandError, CommandExecutor, PSubscribe, PUnsubscribe, PubSub, Publish,
+    RespArray, RespFrame, RespPush, SimpleError, Subscribe, Subscriber, Unsubscribe,
+};
+
+// subscriptions belong to a connection, see `Command::execute_subscription`
+fn no_connection(name: &str) -> RespFrame {
+    SimpleError::new(format!("ERR {} needs a client connection", name)).into()
+}
+
+impl CommandExecutor for Subscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("SUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("UNSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for PSubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("PSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for PUnsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("PUNSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.publish(&self.channel, &self.message) as i64)
+    }
+}
+
+impl CommandExecutor for PubSub {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.subcommand {
+            PubSubSubcommand::Channels(pattern) => RespArray::new(
+                backend
+                    .pubsub_channels(pattern.as_deref())
+                    .into_iter()
+                    .map(|channel| BulkString::new(channel).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            PubSubSubcommand::NumSub(channels) => {
+                let counts = backend.pubsub_numsub(&channels);
+                let mut frames = Vec::with_capacity(channels.len() * 2);
+                for (channel, count) in channels.into_iter().zip(counts) {
+                    frames.push(BulkString::new(channel).into());
+                    frames.push(RespFrame::Integer(count as i64));
+                }
+                RespArray::new(frames).into()
+            }
+            PubSubSubcommand::NumPat => RespFrame::Integer(backend.pubsub_numpat() as i64),
+        }
+    }
+}
+
+// [kind, channel, subscription count], a push frame for RESP3 clients
+fn subscription_reply(kind: &'static str, name: Option<Vec<u8>>, count: usize) -> RespFrame {
+    let name = match name {
+        Some(name) => BulkString::new(name),
+        None => BulkString::nill_new(),
+    };
+    RespPush::new(vec![
+        BulkString::new(kind).into(),
+        name.into(),
+        RespFrame::Integer(count as i64),
+    ])
+    .into()
+}
+
+// without arguments every current subscription goes, with a single reply when there is none
+fn unsubscribe_all(
+    kind: &'static str,
+    names: Vec<Vec<u8>>,
+    subscriber: &mut Subscriber,
+    unsubscribe: fn(&mut Subscriber, &[u8]) -> usize,
+) -> Vec<RespFrame> {
+    if names.is_empty() {
+        return vec![subscription_reply(kind, None, subscriber.count())];
+    }
+    names
+        .into_iter()
+        .map(|name| {
+            let count = unsubscribe(subscriber, &name);
+            subscription_reply(kind, Some(name), count)
+        })
+        .collect()
+}
+
+impl Subscribe {
+    pub(crate) fn execute_for(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriber.subscribe(channel.clone());
+                subscription_reply("subscribe", Some(channel), count)
+            })
+            .collect()
+    }
+}
+
+impl Unsubscribe {
+    pub(crate) fn execute_for(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let channels = if self.channels.is_empty() {
+            subscriber.channels()
+        } else {
+            self.channels
+        };
+        unsubscribe_all("unsubscribe", channels, subscriber, Subscriber::unsubscribe)
+    }
+}
+
+impl PSubscribe {
+    pub(crate) fn execute_for(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        self.patterns
+            .into_iter()
+            .map(|pattern| {
+                let count = subscriber.psubscribe(pattern.clone());
+                subscription_reply("psubscribe", Some(pattern), count)
+            })
+            .collect()
+    }
+}
+
+impl PUnsubscribe {
+    pub(crate) fn execute_for(self, subscriber: &mut Subscriber) -> Vec<RespFrame> {
+        let patterns = if self.patterns.is_empty() {
+            subscriber.patterns()
+        } else {
+            self.patterns
+        };
+        unsubscribe_all(
+            "punsubscribe",
+            patterns,
+            subscriber,
+            Subscriber::punsubscribe,
+        )
+    }
+}
+
+// SUBSCRIBE channel [channel ...]
+fn parse_names(value: RespArray) -> Result<Vec<Vec<u8>>, CommandError> {
+    extract_members(extract_args(value, 1)?)
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Subscribe {
+            channels: parse_names(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Unsubscribe {
+            channels: parse_names(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PSubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(PSubscribe {
+            patterns: parse_names(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PUnsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(PUnsubscribe {
+            patterns: parse_names(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Publish {
+            channel: extract_bytes(args.next(), "channel")?,
+            message: extract_bytes(args.next(), "message")?,
+        })
+    }
+}
+
+// PUBSUB CHANNELS [pattern] | NUMSUB [channel ...] | NUMPAT
+impl TryFrom<RespArray> for PubSub {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let name = extract_string(args.next(), "subcommand")?.to_ascii_lowercase();
+        let subcommand = match name.as_str() {
+            "channels" if args.len() <= 1 => PubSubSubcommand::Channels(
+                args.next()
+                    .map(|arg| extract_bytes(Some(arg), "pattern"))
+                    .transpose()?,
+            ),
+            "numsub" => PubSubSubcommand::NumSub(extract_members(args)?),
+            "numpat" if args.len() == 0 => PubSubSubcommand::NumPat,
+            _ => {
+                return Err(CommandError::InvalidArguments(format!(
+                    "unknown subcommand or wrong number of arguments for '{}'",
+                    name
+                )))
+            }
+        };
+        Ok(PubSub { subcommand })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::cmd::command;
+
+    #[test]
+    fn test_subscription_replies() -> Result<()> {
+        let backend = Backend::new();
+        let (mut subscriber, mut messages) = Subscriber::new(backend.clone());
+
+        let replies =
+            Subscribe::try_from(command(&["subscribe", "a", "b"]))?.execute_for(&mut subscriber);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(
+            replies[1],
+            subscription_reply("subscribe", Some(b"b".to_vec()), 2)
+        );
+        // RESP2 clients get arrays
+        assert_eq!(
+            replies[1].clone().into_resp2(),
+            RespArray::new(vec![
+                BulkString::new("subscribe").into(),
+                BulkString::new("b").into(),
+                RespFrame::Integer(2),
+            ])
+            .into()
+        );
+
+        let ret = Publish::try_from(command(&["publish", "a", "hello"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(1));
+        assert!(messages.try_recv().is_some());
+        let ret = PubSub::try_from(command(&["pubsub", "numsub", "a", "c"]))?.execute(&backend);
+        assert_eq!(
+            ret,
+            RespArray::new(vec![
+                BulkString::new("a").into(),
+                RespFrame::Integer(1),
+                BulkString::new("c").into(),
+                RespFrame::Integer(0),
+            ])
+            .into()
+        );
+
+        let replies =
+            Unsubscribe::try_from(command(&["unsubscribe"]))?.execute_for(&mut subscriber);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(subscriber.count(), 0);
+        let replies =
+            PUnsubscribe::try_from(command(&["punsubscribe"]))?.execute_for(&mut subscriber);
+        assert_eq!(replies, vec![subscription_reply("punsubscribe", None, 0)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use bytes::Bytes;
+
+use crate::cmd::list::syntax_error;
+use crate::cmd::{extract_args, extract_bytes, extract_integer, extract_key, extract_string};
+use crate::{
+    Backend, BulkString, CommandError, CommandExecutor, HScan, Keys, RespArray, RespFrame, SScan,
+    Scan, ScanOptions, ZScan,
+};
+
+// the names SCAN ... TYPE accepts
+const TYPE_NAMES: [&str; 5] = ["string", "list", "set", "zset", "hash"];
+
+impl CommandExecutor for Keys {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let keys = backend
+            .keys(&self.pattern)
+            .into_iter()
+            .map(|key| BulkString::new(key).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(keys).into()
+    }
+}
+
+impl CommandExecutor for Scan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, keys) = backend.scan(self.cursor, &self.options);
+        scan_reply(
+            cursor,
+            keys.into_iter().map(|key| BulkString::new(key).into()),
+        )
+    }
+}
+
+impl CommandExecutor for HScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.hscan(&self.key, self.cursor, &self.options) {
+            Ok((cursor, fields)) => {
+                let mut frames = Vec::with_capacity(fields.len() * 2);
+                for (field, value) in fields {
+                    frames.push(BulkString::new(field).into());
+                    if !self.no_values {
+                        frames.push(value);
+                    }
+                }
+                scan_reply(cursor, frames)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.sscan(&self.key, self.cursor, &self.options) {
+            Ok((cursor, members)) => scan_reply(
+                cursor,
+                members
+                    .into_iter()
+                    .map(|member| BulkString::new(member).into()),
+            ),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zscan(&self.key, self.cursor, &self.options) {
+            Ok((cursor, members)) => {
+                let mut frames = Vec::with_capacity(members.len() * 2);
+                for (member, score) in members {
+                    frames.push(BulkString::new(member).into());
+                    // scores are strings here even for RESP3 clients, like in redis
+                    frames.push(BulkString::new(score.to_string()).into());
+                }
+                scan_reply(cursor, frames)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// [next cursor, [elements ...]]
+fn scan_reply(cursor: u64, elements: impl IntoIterator<Item = RespFrame>) -> RespFrame {
+    RespArray::new(vec![
+        BulkString::new(cursor.to_string()).into(),
+        RespArray::new(elements.into_iter().collect::<Vec<RespFrame>>()).into(),
+    ])
+    .into()
+}
+
+// cursor [MATCH pattern] [COUNT count], plus the `extra` options of the command: TYPE type
+// or NOVALUES. The flag is true when NOVALUES was given.
+fn parse_scan(
+    args: Vec<RespFrame>,
+    extra: &[&str],
+) -> Result<(u64, ScanOptions, bool), CommandError> {
+    let mut args = args.into_iter();
+    let cursor = extract_string(args.next(), "cursor")?
+        .parse::<u64>()
+        .map_err(|_| CommandError::InvalidArguments("invalid cursor".to_string()))?;
+    let mut options = ScanOptions::default();
+    let mut no_values = false;
+    while let Some(arg) = args.next() {
+        let option = extract_string(Some(arg), "option")?.to_ascii_lowercase();
+        match option.as_str() {
+            "match" => options.pattern = Some(extract_bytes(args.next(), "pattern")?),
+            "count" => {
+                options.count = usize::try_from(extract_integer(args.next())?)
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(syntax_error)?;
+            }
+            "type" if extra.contains(&"type") => {
+                let kind = extract_string(args.next(), "type")?;
+                if !TYPE_NAMES.contains(&kind.to_ascii_lowercase().as_str()) {
+                    return Err(CommandError::InvalidArguments(format!(
+                        "unknown type name '{}'",
+                        kind
+                    )));
+                }
+                options.kind = Some(kind.to_ascii_lowercase());
+            }
+            "novalues" if extra.contains(&"novalues") => no_values = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok((cursor, options, no_values))
+}
+
+// KEYS pattern
+impl TryFrom<RespArray> for Keys {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(Keys {
+            pattern: extract_bytes(args.next(), "pattern")?,
+        })
+    }
+}
+
+// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (cursor, options, _) = parse_scan(extract_args(value, 1)?, &["type"])?;
+        Ok(Scan { cursor, options })
+    }
+}
+
+// HSCAN key cursor [MATCH pattern] [COUNT count] [NOVALUES]
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, args) = parse_key(value)?;
+        let (cursor, options, no_values) = parse_scan(args, &["novalues"])?;
+        Ok(HScan {
+            key,
+            cursor,
+            options,
+            no_values,
+        })
+    }
+}
+
+// SSCAN key cursor [MATCH pattern] [COUNT count]
+impl TryFrom<RespArray> for SScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, args) = parse_key(value)?;
+        let (cursor, options, _) = parse_scan(args, &[])?;
+        Ok(SScan {
+            key,
+            cursor,
+            options,
+        })
+    }
+}
+
+// ZSCAN key cursor [MATCH pattern] [COUNT count]
+impl TryFrom<RespArray> for ZScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, args) = parse_key(value)?;
+        let (cursor, options, _) = parse_scan(args, &[])?;
+        Ok(ZScan {
+            key,
+            cursor,
+            options,
+        })
+    }
+}
+
+// the key of HSCAN, SSCAN and ZSCAN and the arguments after it
+fn parse_key(value: RespArray) -> Result<(Bytes, Vec<RespFrame>), CommandError> {
+    let mut args = extract_args(value, 1)?;
+    let key = extract_key(Some(args.remove(0)), "key")?;
+    Ok((key, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{BackendError, Command};
+
+    use super::*;
+    use crate::cmd::command;
+
+    fn run(backend: &Backend, args: &[&str]) -> Result<RespFrame> {
+        Ok(Command::try_from(command(args))?.execute(backend))
+    }
+
+    #[test]
+    fn test_scan_commands() -> Result<()> {
+        let backend = Backend::new();
+        run(&backend, &["hset", "hash", "field", "value"])?;
+        run(&backend, &["sadd", "set", "a"])?;
+        run(&backend, &["zadd", "zset", "1.5", "a"])?;
+
+        let ret = run(&backend, &["keys", "h*"])?;
+        assert_eq!(
+            ret,
+            RespArray::new(vec![BulkString::new("hash").into()]).into()
+        );
+        let ret = run(&backend, &["scan", "0", "type", "SET", "count", "100"])?;
+        assert_eq!(ret, scan_reply(0, [BulkString::new("set").into()]));
+
+        let ret = run(&backend, &["hscan", "hash", "0"])?;
+        let fields = [
+            BulkString::new("field").into(),
+            BulkString::new("value").into(),
+        ];
+        assert_eq!(ret, scan_reply(0, fields));
+        let ret = run(&backend, &["hscan", "hash", "0", "novalues"])?;
+        assert_eq!(ret, scan_reply(0, [BulkString::new("field").into()]));
+        let ret = run(&backend, &["sscan", "set", "0", "match", "[ab]"])?;
+        assert_eq!(ret, scan_reply(0, [BulkString::new("a").into()]));
+        let ret = run(&backend, &["zscan", "zset", "0"])?;
+        let members = [BulkString::new("a").into(), BulkString::new("1.5").into()];
+        assert_eq!(ret, scan_reply(0, members));
+        let ret = run(&backend, &["zscan", "missing", "0"])?;
+        assert_eq!(ret, scan_reply(0, []));
+        assert_eq!(
+            run(&backend, &["sscan", "hash", "0"])?,
+            BackendError::WrongType.into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_arguments() {
+        for args in [
+            &["scan", "-1"][..],
+            &["scan", "0", "count", "0"],
+            &["scan", "0", "type", "stream"],
+            &["scan", "0", "novalues"],
+            &["sscan", "set", "0", "type", "set"],
+            &["hscan", "hash", "0", "match"],
+        ] {
+            assert!(Command::try_from(command(args)).is_err(), "{:?}", args);
+        }
+    }
+}
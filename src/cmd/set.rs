@@ -0,0 +1,489 @@
+use bytes::Bytes;
+
+use crate::aof::aof_command;
+use crate::cmd::list::syntax_error;
+use crate::cmd::{extract_args, extract_bytes, extract_integer, extract_key, extract_string};
+use crate::{
+    Backend, BulkString, CommandError, CommandExecutor, RespArray, RespFrame, RespNull, RespSet,
+    SAdd, SCard, SDiff, SDiffStore, SInter, SInterCard, SInterStore, SIsMember, SMIsMember,
+    SMembers, SMove, SPop, SRandMember, SRem, SUnion, SUnionStore, SetOp,
+};
+
+impl CommandExecutor for SAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.sadd(&self.key, self.members) {
+            Ok(added) => RespFrame::Integer(added as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.srem(&self.key, &self.members) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMembers {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.smembers(&self.key) {
+            Ok(members) => set_reply(members),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SIsMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.smismember(&self.key, &[self.member]) {
+            Ok(found) => RespFrame::Integer(found.first().copied().unwrap_or(false) as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMIsMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.smismember(&self.key, &self.members) {
+            Ok(found) => RespArray::new(
+                found
+                    .into_iter()
+                    .map(|found| RespFrame::Integer(found as i64))
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.scard(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_propagated(backend, &mut Vec::new())
+    }
+}
+
+impl SPop {
+    /// The popped members go to the aof as an SREM, so a replay removes the same ones
+    pub(crate) fn execute_propagated(
+        self,
+        backend: &Backend,
+        propagated: &mut Vec<(usize, RespArray)>,
+    ) -> RespFrame {
+        let members = match backend.spop(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        if !members.is_empty() {
+            let mut args = vec![b"srem".to_vec(), self.key.to_vec()];
+            args.extend(members.iter().cloned());
+            propagated.push((backend.db_index(), aof_command(args)));
+        }
+        match (members, self.count) {
+            (members, Some(_)) => set_reply(members),
+            (mut members, None) => members
+                .pop()
+                .map(|member| BulkString::new(member).into())
+                .unwrap_or(RespFrame::Null(RespNull)),
+        }
+    }
+}
+
+impl CommandExecutor for SRandMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match (
+            backend.srandmember(&self.key, self.count.unwrap_or(1)),
+            self.count,
+        ) {
+            (Ok(members), Some(_)) => RespArray::new(
+                members
+                    .into_iter()
+                    .map(|member| BulkString::new(member).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into(),
+            (Ok(mut members), None) => members
+                .pop()
+                .map(|member| BulkString::new(member).into())
+                .unwrap_or(RespFrame::Null(RespNull)),
+            (Err(e), _) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SInter {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        combine(backend, SetOp::Inter, &self.keys)
+    }
+}
+
+impl CommandExecutor for SUnion {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        combine(backend, SetOp::Union, &self.keys)
+    }
+}
+
+impl CommandExecutor for SDiff {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        combine(backend, SetOp::Diff, &self.keys)
+    }
+}
+
+impl CommandExecutor for SInterStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        combine_store(backend, SetOp::Inter, &self.destination, &self.keys)
+    }
+}
+
+impl CommandExecutor for SUnionStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        combine_store(backend, SetOp::Union, &self.destination, &self.keys)
+    }
+}
+
+impl CommandExecutor for SDiffStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        combine_store(backend, SetOp::Diff, &self.destination, &self.keys)
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.sintercard(&self.keys, self.limit) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.smove(&self.source, &self.destination, self.member) {
+            Ok(moved) => RespFrame::Integer(moved as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// a RESP3 set, sent as an array to RESP2 clients
+fn set_reply(members: impl IntoIterator<Item = Vec<u8>>) -> RespFrame {
+    RespSet::new(
+        members
+            .into_iter()
+            .map(|member| BulkString::new(member).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+    .into()
+}
+
+fn combine(backend: &Backend, op: SetOp, keys: &[Bytes]) -> RespFrame {
+    match backend.set_combine(op, keys) {
+        Ok(members) => set_reply(members),
+        Err(e) => e.into(),
+    }
+}
+
+fn combine_store(backend: &Backend, op: SetOp, destination: &[u8], keys: &[Bytes]) -> RespFrame {
+    match backend.set_combine_store(op, destination, keys) {
+        Ok(len) => RespFrame::Integer(len as i64),
+        Err(e) => e.into(),
+    }
+}
+
+pub(super) fn extract_keys(
+    args: impl IntoIterator<Item = RespFrame>,
+) -> Result<Vec<Bytes>, CommandError> {
+    args.into_iter()
+        .map(|arg| extract_key(Some(arg), "key"))
+        .collect()
+}
+
+pub(super) fn extract_members(
+    args: impl IntoIterator<Item = RespFrame>,
+) -> Result<Vec<Vec<u8>>, CommandError> {
+    args.into_iter()
+        .map(|arg| extract_bytes(Some(arg), "member"))
+        .collect()
+}
+
+// SADD key member [member ...]
+pub(super) fn parse_key_members(value: RespArray) -> Result<(Bytes, Vec<Vec<u8>>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    Ok((key, extract_members(args)?))
+}
+
+// SINTER key [key ...]
+fn parse_keys(value: RespArray) -> Result<Vec<Bytes>, CommandError> {
+    extract_keys(extract_args(value, 1)?)
+}
+
+// SINTERSTORE destination key [key ...]
+fn parse_store(value: RespArray) -> Result<(Bytes, Vec<Bytes>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let destination = extract_key(args.next(), "destination")?;
+    Ok((destination, extract_keys(args)?))
+}
+
+// SPOP key [count]
+fn parse_key_count(value: RespArray) -> Result<(Bytes, Option<i64>), CommandError> {
+    if value.len() > 3 {
+        return Err(syntax_error());
+    }
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    let count = match args.next() {
+        Some(frame) => Some(extract_integer(Some(frame))?),
+        None => None,
+    };
+    Ok((key, count))
+}
+
+pub(super) fn parse_key(value: RespArray) -> Result<Bytes, CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    extract_key(args.next(), "key")
+}
+
+impl TryFrom<RespArray> for SAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value)?;
+        Ok(SAdd { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value)?;
+        Ok(SRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value)?;
+        Ok(SMIsMember { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SMembers {
+            key: parse_key(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SCard {
+            key: parse_key(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SIsMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SIsMember {
+            key: extract_key(args.next(), "key")?,
+            member: extract_bytes(args.next(), "member")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(value)?;
+        let count = match count {
+            Some(count) => Some(usize::try_from(count).map_err(|_| {
+                CommandError::InvalidArguments(
+                    "value is out of range, must be positive".to_string(),
+                )
+            })?),
+            None => None,
+        };
+        Ok(SPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_key_count(value)?;
+        Ok(SRandMember { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SInter {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SInter {
+            keys: parse_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SUnion {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SUnion {
+            keys: parse_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SDiff {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SDiff {
+            keys: parse_keys(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInterStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = parse_store(value)?;
+        Ok(SInterStore { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SUnionStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = parse_store(value)?;
+        Ok(SUnionStore { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SDiffStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = parse_store(value)?;
+        Ok(SDiffStore { destination, keys })
+    }
+}
+
+// SINTERCARD numkeys key [key ...] [LIMIT limit]
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let numkeys = usize::try_from(extract_integer(args.next())?)
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                CommandError::InvalidArguments("numkeys should be greater than 0".to_string())
+            })?;
+        if numkeys > args.len() {
+            return Err(CommandError::InvalidArguments(
+                "Number of keys can't be greater than number of args".to_string(),
+            ));
+        }
+        let keys = extract_keys(args.by_ref().take(numkeys))?;
+
+        let limit = match args.next() {
+            None => 0,
+            Some(arg) => {
+                if !extract_string(Some(arg), "option")?.eq_ignore_ascii_case("limit") {
+                    return Err(syntax_error());
+                }
+                usize::try_from(extract_integer(args.next())?).map_err(|_| {
+                    CommandError::InvalidArguments("LIMIT can't be negative".to_string())
+                })?
+            }
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SMove {
+            source: extract_key(args.next(), "source")?,
+            destination: extract_key(args.next(), "destination")?,
+            member: extract_bytes(args.next(), "member")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::cmd::command;
+
+    #[test]
+    fn test_set_commands() -> Result<()> {
+        let backend = Backend::new();
+
+        let ret = SAdd::try_from(command(&["sadd", "s", "a"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(1));
+        let ret = SMembers::try_from(command(&["smembers", "s"]))?.execute(&backend);
+        assert_eq!(ret, RespSet::new(vec![BulkString::new("a").into()]).into());
+        // RESP2 clients get the members as an array
+        assert_eq!(
+            ret.into_resp2(),
+            RespArray::new(vec![BulkString::new("a").into()]).into()
+        );
+
+        let ret = SMIsMember::try_from(command(&["smismember", "s", "a", "b"]))?.execute(&backend);
+        assert_eq!(
+            ret,
+            RespArray::new(vec![RespFrame::Integer(1), RespFrame::Integer(0)]).into()
+        );
+        let ret = SPop::try_from(command(&["spop", "missing"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_parse_errors() {
+        assert!(SPop::try_from(command(&["spop", "s", "-1"])).is_err());
+        assert!(SInterCard::try_from(command(&["sintercard", "0", "a"])).is_err());
+        assert!(SInterCard::try_from(command(&["sintercard", "3", "a", "b"])).is_err());
+        assert!(SInterCard::try_from(command(&["sintercard", "1", "a", "LIMIT", "-1"])).is_err());
+    }
+}
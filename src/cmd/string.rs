@@ -0,0 +1,719 @@
+use bytes::Bytes;
+
+use crate::cmd::list::syntax_error;
+use crate::cmd::map::{extract_ttl, invalid_expire_time, ttl_expiry};
+use crate::cmd::set::extract_keys;
+use crate::cmd::zset::extract_float;
+use crate::cmd::{extract_args, extract_integer, extract_key, extract_string, SetTtl, RESP_OK};
+use crate::{
+    now_millis, Append, Backend, BackendError, BulkString, CommandError, CommandExecutor, Decr,
+    DecrBy, GetDel, GetEx, GetRange, Incr, IncrBy, IncrByFloat, Lcs, MGet, MSet, MSetNx, PSetEx,
+    RespArray, RespFrame, RespMap, RespNull, SetCondition, SetEx, SetExpiry, SetNx, SetRange,
+    StrLen, StringValue,
+};
+
+impl CommandExecutor for SetNx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (written, _) =
+            backend.set_with(&self.key, self.value, SetCondition::Nx, SetExpiry::Clear);
+        RespFrame::Integer(written as i64)
+    }
+}
+
+impl CommandExecutor for SetEx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        set_expiring(backend, self.key, self.value, self.milliseconds, "setex")
+    }
+}
+
+impl CommandExecutor for PSetEx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        set_expiring(backend, self.key, self.value, self.milliseconds, "psetex")
+    }
+}
+
+fn set_expiring(
+    backend: &Backend,
+    key: Bytes,
+    value: StringValue,
+    milliseconds: i64,
+    command: &str,
+) -> RespFrame {
+    let Some(deadline) = now_millis().checked_add(milliseconds) else {
+        return invalid_expire_time(command).into();
+    };
+    backend.set_with(&key, value, SetCondition::Always, SetExpiry::At(deadline));
+    RESP_OK.clone()
+}
+
+impl CommandExecutor for GetDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        string_reply(backend.getdel(&self.key))
+    }
+}
+
+impl CommandExecutor for GetEx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let expiry = match self.ttl.map(|ttl| ttl_expiry(ttl, "getex")) {
+            None if self.persist => SetExpiry::Clear,
+            None => SetExpiry::Keep,
+            Some(Ok(expiry)) => expiry,
+            Some(Err(e)) => return e,
+        };
+        string_reply(backend.getex(&self.key, expiry))
+    }
+}
+
+impl CommandExecutor for Incr {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integer_reply(backend.incr_by(&self.key, 1))
+    }
+}
+
+impl CommandExecutor for Decr {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integer_reply(backend.incr_by(&self.key, -1))
+    }
+}
+
+impl CommandExecutor for IncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integer_reply(backend.incr_by(&self.key, self.increment))
+    }
+}
+
+impl CommandExecutor for DecrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // the decrement is never i64::MIN, parsing rejects it
+        integer_reply(backend.incr_by(&self.key, -self.decrement))
+    }
+}
+
+impl CommandExecutor for IncrByFloat {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.incr_by_float(&self.key, self.increment) {
+            Ok(value) => value.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Append {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integer_reply(backend.append(&self.key, &self.value))
+    }
+}
+
+impl CommandExecutor for StrLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integer_reply(backend.strlen(&self.key))
+    }
+}
+
+impl CommandExecutor for GetRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.getrange(&self.key, self.start, self.end) {
+            Ok(value) => BulkString::new(value).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SetRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integer_reply(backend.setrange(&self.key, self.offset, &self.value))
+    }
+}
+
+impl CommandExecutor for Lcs {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let lcs = match backend.lcs(&self.first, &self.second) {
+            Ok(lcs) => lcs,
+            Err(e) => return e.into(),
+        };
+        if self.len {
+            return RespFrame::Integer(lcs.sequence.len() as i64);
+        }
+        if !self.idx {
+            return BulkString::new(lcs.sequence).into();
+        }
+
+        let range = |(start, end): (usize, usize)| -> RespFrame {
+            RespArray::new(vec![
+                RespFrame::Integer(start as i64),
+                RespFrame::Integer(end as i64),
+            ])
+            .into()
+        };
+        let matches = lcs
+            .matches
+            .into_iter()
+            .filter(|(first, _)| first.1 - first.0 + 1 >= self.min_match_len)
+            .map(|(first, second)| {
+                let mut frames = vec![range(first), range(second)];
+                if self.with_match_len {
+                    frames.push(RespFrame::Integer((first.1 - first.0 + 1) as i64));
+                }
+                RespArray::new(frames).into()
+            })
+            .collect::<Vec<RespFrame>>();
+        let mut reply = RespMap::new();
+        reply.insert("matches".to_string(), RespArray::new(matches).into());
+        reply.insert(
+            "len".to_string(),
+            RespFrame::Integer(lcs.sequence.len() as i64),
+        );
+        reply.into()
+    }
+}
+
+impl CommandExecutor for MGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let values = backend
+            .mget(&self.keys)
+            .into_iter()
+            .map(|value| value.map_or(RespFrame::Null(RespNull), RespFrame::from))
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(values).into()
+    }
+}
+
+impl CommandExecutor for MSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.mset(self.pairs);
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for MSetNx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.msetnx(self.pairs) as i64)
+    }
+}
+
+fn integer_reply(ret: Result<impl TryInto<i64>, BackendError>) -> RespFrame {
+    match ret {
+        Ok(value) => RespFrame::Integer(value.try_into().unwrap_or(i64::MAX)),
+        Err(e) => e.into(),
+    }
+}
+
+fn string_reply(ret: Result<Option<StringValue>, BackendError>) -> RespFrame {
+    match ret {
+        Ok(Some(value)) => value.into(),
+        Ok(None) => RespFrame::Null(RespNull),
+        Err(e) => e.into(),
+    }
+}
+
+// key value
+fn parse_key_value(value: RespArray) -> Result<(Bytes, Bytes), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    let value = extract_key(args.next(), "value")?;
+    Ok((key, value))
+}
+
+// key
+fn parse_key(value: RespArray) -> Result<Bytes, CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    extract_key(args.next(), "key")
+}
+
+// MSET / MSETNX key value [key value ...]
+fn parse_pairs(value: RespArray, command: &str) -> Result<Vec<(Bytes, StringValue)>, CommandError> {
+    let args = extract_args(value, 1)?;
+    if args.len() % 2 != 0 {
+        return Err(CommandError::WrongArity(command.to_string()));
+    }
+    let mut pairs = Vec::with_capacity(args.len() / 2);
+    let mut args = args.into_iter();
+    while let Some(key) = args.next() {
+        let key = extract_key(Some(key), "key")?;
+        pairs.push((key, extract_key(args.next(), "value")?.into()));
+    }
+    Ok(pairs)
+}
+
+// SETEX / PSETEX key time value, the time in milliseconds
+fn parse_expiring(
+    value: RespArray,
+    unit: i64,
+    command: &str,
+) -> Result<(Bytes, i64, StringValue), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    let milliseconds = extract_integer(args.next())?
+        .checked_mul(unit)
+        .filter(|time| *time > 0)
+        .ok_or_else(|| invalid_expire_time(command))?;
+    let value = extract_key(args.next(), "value")?.into();
+    Ok((key, milliseconds, value))
+}
+
+// SETNX key value
+impl TryFrom<RespArray> for SetNx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, value) = parse_key_value(value)?;
+        Ok(SetNx {
+            key,
+            value: value.into(),
+        })
+    }
+}
+
+// SETEX key seconds value
+impl TryFrom<RespArray> for SetEx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, milliseconds, value) = parse_expiring(value, 1000, "setex")?;
+        Ok(SetEx {
+            key,
+            milliseconds,
+            value,
+        })
+    }
+}
+
+// PSETEX key milliseconds value
+impl TryFrom<RespArray> for PSetEx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, milliseconds, value) = parse_expiring(value, 1, "psetex")?;
+        Ok(PSetEx {
+            key,
+            milliseconds,
+            value,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GetDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(GetDel {
+            key: parse_key(value)?,
+        })
+    }
+}
+
+// GETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds
+// | PXAT unix-time-milliseconds | PERSIST]
+impl TryFrom<RespArray> for GetEx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let mut ttl: Option<SetTtl> = None;
+        let mut persist = false;
+        while let Some(arg) = args.next() {
+            let option = extract_string(Some(arg), "option")?.to_ascii_lowercase();
+            match option.as_str() {
+                "persist" if ttl.is_none() && !persist => persist = true,
+                "ex" | "px" | "exat" | "pxat" if ttl.is_none() && !persist => {
+                    ttl = Some(extract_ttl(&option, args.next(), "getex")?);
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(GetEx { key, ttl, persist })
+    }
+}
+
+impl TryFrom<RespArray> for Incr {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Incr {
+            key: parse_key(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Decr {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Decr {
+            key: parse_key(value)?,
+        })
+    }
+}
+
+// INCRBY key increment
+impl TryFrom<RespArray> for IncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        Ok(IncrBy {
+            key,
+            increment: extract_integer(args.next())?,
+        })
+    }
+}
+
+// DECRBY key decrement
+impl TryFrom<RespArray> for DecrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let decrement = extract_integer(args.next())?;
+        if decrement == i64::MIN {
+            return Err(CommandError::InvalidArguments(
+                "decrement would overflow".to_string(),
+            ));
+        }
+        Ok(DecrBy { key, decrement })
+    }
+}
+
+// INCRBYFLOAT key increment
+impl TryFrom<RespArray> for IncrByFloat {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        Ok(IncrByFloat {
+            key,
+            increment: extract_float(args.next(), "value is not a valid float")?,
+        })
+    }
+}
+
+// APPEND key value
+impl TryFrom<RespArray> for Append {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, value) = parse_key_value(value)?;
+        Ok(Append { key, value })
+    }
+}
+
+impl TryFrom<RespArray> for StrLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(StrLen {
+            key: parse_key(value)?,
+        })
+    }
+}
+
+// GETRANGE key start end
+impl TryFrom<RespArray> for GetRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let start = extract_integer(args.next())?;
+        let end = extract_integer(args.next())?;
+        Ok(GetRange { key, start, end })
+    }
+}
+
+// SETRANGE key offset value
+impl TryFrom<RespArray> for SetRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let offset = usize::try_from(extract_integer(args.next())?)
+            .map_err(|_| CommandError::InvalidArguments("offset is out of range".to_string()))?;
+        let value = extract_key(args.next(), "value")?;
+        Ok(SetRange { key, offset, value })
+    }
+}
+
+// LCS key1 key2 [LEN] [IDX] [MINMATCHLEN min-match-len] [WITHMATCHLEN]
+impl TryFrom<RespArray> for Lcs {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut lcs = Lcs {
+            first: extract_key(args.next(), "key")?,
+            second: extract_key(args.next(), "key")?,
+            len: false,
+            idx: false,
+            min_match_len: 0,
+            with_match_len: false,
+        };
+        while let Some(arg) = args.next() {
+            let option = extract_string(Some(arg), "option")?.to_ascii_lowercase();
+            match option.as_str() {
+                "len" => lcs.len = true,
+                "idx" => lcs.idx = true,
+                "withmatchlen" => lcs.with_match_len = true,
+                // a negative length filters nothing, like 0
+                "minmatchlen" => {
+                    lcs.min_match_len = extract_integer(args.next())?.max(0) as usize;
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        if lcs.len && lcs.idx {
+            return Err(CommandError::InvalidArguments(
+                "If you want both the length and indexes, please just use IDX.".to_string(),
+            ));
+        }
+        Ok(lcs)
+    }
+}
+
+// MGET key [key ...]
+impl TryFrom<RespArray> for MGet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(MGet {
+            keys: extract_keys(extract_args(value, 1)?)?,
+        })
+    }
+}
+
+// MSET key value [key value ...]
+impl TryFrom<RespArray> for MSet {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(MSet {
+            pairs: parse_pairs(value, "mset")?,
+        })
+    }
+}
+
+// MSETNX key value [key value ...]
+impl TryFrom<RespArray> for MSetNx {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(MSetNx {
+            pairs: parse_pairs(value, "msetnx")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{Command, SimpleError};
+
+    use super::*;
+    use crate::cmd::command;
+
+    fn run(backend: &Backend, args: &[&str]) -> Result<RespFrame> {
+        Ok(Command::try_from(command(args))?.execute(backend))
+    }
+
+    fn error(message: &str) -> RespFrame {
+        SimpleError::new(message).into()
+    }
+
+    #[test]
+    fn test_multi_key_commands() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["mset", "a", "1", "b", "2"])?,
+            RESP_OK.clone()
+        );
+        assert_eq!(
+            run(&backend, &["mget", "a", "missing", "b"])?,
+            RespArray::new(vec![
+                BulkString::new("1").into(),
+                RespFrame::Null(RespNull),
+                BulkString::new("2").into(),
+            ])
+            .into()
+        );
+        assert_eq!(
+            run(&backend, &["msetnx", "c", "3", "a", "4"])?,
+            RespFrame::Integer(0)
+        );
+        assert_eq!(run(&backend, &["get", "c"])?, RespFrame::Null(RespNull));
+        assert_eq!(
+            run(&backend, &["msetnx", "c", "3", "d", "4"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(run(&backend, &["get", "d"])?, BulkString::new("4").into());
+
+        let err = Command::try_from(command(&["mset", "a", "1", "b"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ERR wrong number of arguments for 'mset' command"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_counters() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(run(&backend, &["incr", "counter"])?, RespFrame::Integer(1));
+        assert_eq!(
+            run(&backend, &["incrby", "counter", "10"])?,
+            RespFrame::Integer(11)
+        );
+        assert_eq!(run(&backend, &["decr", "counter"])?, RespFrame::Integer(10));
+        assert_eq!(
+            run(&backend, &["decrby", "counter", "20"])?,
+            RespFrame::Integer(-10)
+        );
+        assert_eq!(
+            run(&backend, &["get", "counter"])?,
+            BulkString::new("-10").into()
+        );
+
+        run(&backend, &["set", "counter", "9223372036854775807"])?;
+        assert_eq!(
+            run(&backend, &["incr", "counter"])?,
+            error("ERR increment or decrement would overflow")
+        );
+        run(&backend, &["set", "text", "abc"])?;
+        assert_eq!(
+            run(&backend, &["incr", "text"])?,
+            error("ERR value is not an integer or out of range")
+        );
+        assert!(Command::try_from(command(&["incrby", "counter", "1.5"])).is_err());
+        assert!(
+            Command::try_from(command(&["decrby", "counter", "-9223372036854775808"])).is_err()
+        );
+
+        run(&backend, &["set", "float", "10.50"])?;
+        assert_eq!(
+            run(&backend, &["incrbyfloat", "float", "0.1"])?,
+            BulkString::new("10.6").into()
+        );
+        assert_eq!(
+            run(&backend, &["incrbyfloat", "float", "-5.1"])?,
+            BulkString::new("5.5").into()
+        );
+        assert_eq!(
+            run(&backend, &["incrbyfloat", "text", "1"])?,
+            error("ERR value is not a valid float")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_commands() -> Result<()> {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["append", "key", "Hello"])?,
+            RespFrame::Integer(5)
+        );
+        assert_eq!(
+            run(&backend, &["append", "key", " World"])?,
+            RespFrame::Integer(11)
+        );
+        assert_eq!(run(&backend, &["strlen", "key"])?, RespFrame::Integer(11));
+        assert_eq!(
+            run(&backend, &["strlen", "missing"])?,
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            run(&backend, &["getrange", "key", "-5", "-1"])?,
+            BulkString::new("World").into()
+        );
+        assert_eq!(
+            run(&backend, &["setrange", "key", "6", "Redis"])?,
+            RespFrame::Integer(11)
+        );
+        assert!(Command::try_from(command(&["setrange", "key", "-1", "x"])).is_err());
+
+        assert_eq!(
+            run(&backend, &["setnx", "key", "v"])?,
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            run(&backend, &["setnx", "new", "v"])?,
+            RespFrame::Integer(1)
+        );
+        assert_eq!(
+            run(&backend, &["getdel", "key"])?,
+            BulkString::new("Hello Redis").into()
+        );
+        assert_eq!(
+            run(&backend, &["getdel", "key"])?,
+            RespFrame::Null(RespNull)
+        );
+
+        assert_eq!(
+            run(&backend, &["setex", "temp", "100", "v"])?,
+            RESP_OK.clone()
+        );
+        assert!(matches!(run(&backend, &["ttl", "temp"])?, RespFrame::Integer(ttl) if ttl > 90));
+        run(&backend, &["getex", "temp", "persist"])?;
+        assert_eq!(run(&backend, &["ttl", "temp"])?, RespFrame::Integer(-1));
+        assert_eq!(
+            run(&backend, &["getex", "temp", "px", "5000"])?,
+            BulkString::new("v").into()
+        );
+        assert!(matches!(run(&backend, &["pttl", "temp"])?, RespFrame::Integer(ttl) if ttl > 4000));
+        assert!(Command::try_from(command(&["psetex", "temp", "0", "v"])).is_err());
+        assert!(Command::try_from(command(&["getex", "temp", "persist", "ex", "1"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcs() -> Result<()> {
+        let backend = Backend::new();
+        run(&backend, &["set", "key1", "ohmytext"])?;
+        run(&backend, &["set", "key2", "mynewtext"])?;
+        assert_eq!(
+            run(&backend, &["lcs", "key1", "key2"])?,
+            BulkString::new("mytext").into()
+        );
+        assert_eq!(
+            run(&backend, &["lcs", "key1", "key2", "len"])?,
+            RespFrame::Integer(6)
+        );
+
+        let ret = run(
+            &backend,
+            &[
+                "lcs",
+                "key1",
+                "key2",
+                "idx",
+                "minmatchlen",
+                "4",
+                "withmatchlen",
+            ],
+        )?;
+        let range = |start, end| {
+            RespFrame::from(RespArray::new(vec![
+                RespFrame::Integer(start),
+                RespFrame::Integer(end),
+            ]))
+        };
+        let mut expected = RespMap::new();
+        expected.insert(
+            "matches".to_string(),
+            RespArray::new(vec![RespArray::new(vec![
+                range(4, 7),
+                range(5, 8),
+                RespFrame::Integer(4),
+            ])
+            .into()])
+            .into(),
+        );
+        expected.insert("len".to_string(), RespFrame::Integer(6));
+        assert_eq!(ret, expected.into());
+        assert!(Command::try_from(command(&["lcs", "key1", "key2", "len", "idx"])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use crate::cmd::set::extract_keys;
+use crate::cmd::{extract_args, RESP_OK};
+use crate::{
+    Backend, Client, Command, CommandError, CommandExecutor, Discard, Exec, Multi, RespArray,
+    RespFrame, SimpleError, SimpleString, Unwatch, Watch, WatchedKeys,
+};
+
+// transactions belong to a connection, see `Command::execute_transaction`
+fn no_connection(name: &str) -> RespFrame {
+    SimpleError::new(format!("ERR {} needs a client connection", name)).into()
+}
+
+/// The MULTI state of one connection: the queued commands and the WATCHed keys
+#[derive(Debug)]
+pub struct Transaction {
+    // the commands with their database and their frames for the aof, None outside of MULTI
+    queued: Option<Vec<(usize, Command, Option<RespArray>)>>,
+    // a command failed to parse while queuing
+    aborted: bool,
+    watched: WatchedKeys,
+}
+
+impl Transaction {
+    pub fn new(backend: Backend) -> Self {
+        Transaction {
+            queued: None,
+            aborted: false,
+            watched: WatchedKeys::new(backend),
+        }
+    }
+
+    /// Whether the connection is between MULTI and EXEC
+    pub fn is_queuing(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// Queue a command until EXEC, it runs in the database `db` selected now.
+    /// `args` is the frame appended to the aof if it is a write.
+    pub fn queue(&mut self, db: usize, cmd: Command, args: Option<RespArray>) -> RespFrame {
+        match self.queued.as_mut() {
+            Some(queued) => {
+                queued.push((db, cmd, args));
+                SimpleString::new("QUEUED").into()
+            }
+            None => no_connection("MULTI"),
+        }
+    }
+
+    /// Make the next EXEC fail, after a command that could not be queued
+    pub fn abort(&mut self) {
+        if self.is_queuing() {
+            self.aborted = true;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.queued = None;
+        self.aborted = false;
+        self.watched.clear();
+    }
+}
+
+impl CommandExecutor for Multi {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("MULTI")
+    }
+}
+
+impl CommandExecutor for Exec {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("EXEC")
+    }
+}
+
+impl CommandExecutor for Discard {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("DISCARD")
+    }
+}
+
+impl CommandExecutor for Watch {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("WATCH")
+    }
+}
+
+impl CommandExecutor for Unwatch {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        no_connection("UNWATCH")
+    }
+}
+
+impl Multi {
+    pub(crate) fn execute_for(self, transaction: &mut Transaction) -> RespFrame {
+        if transaction.is_queuing() {
+            return SimpleError::new("ERR MULTI calls can not be nested").into();
+        }
+        transaction.queued = Some(Vec::new());
+        RESP_OK.clone()
+    }
+}
+
+impl Exec {
+    /// Run the queue with no other client command in between, or nothing at all if a watched
+    /// key changed since WATCH
+    pub(crate) fn execute_for(
+        self,
+        backend: &Backend,
+        client: &mut Client,
+        transaction: &mut Transaction,
+    ) -> RespFrame {
+        let Some(queued) = transaction.queued.take() else {
+            return SimpleError::new("ERR EXEC without MULTI").into();
+        };
+        let _exclusive = backend.lock_exclusive();
+        let ret = if transaction.aborted {
+            SimpleError::new("EXECABORT Transaction discarded because of previous errors.").into()
+        } else if transaction.watched.is_dirty() {
+            RespArray::nill_new().into()
+        } else {
+            // a queued SELECT switches the database of the commands after it, and of the
+            // connection once EXEC is done
+            let mut selected = None;
+            let queued = queued
+                .into_iter()
+                .map(|(db, cmd, args)| {
+                    if let Command::Select(select) = &cmd {
+                        selected = select.db(backend).or(selected);
+                    }
+                    (selected.unwrap_or(db), cmd, args)
+                })
+                .collect::<Vec<_>>();
+            let replies = match backend.aof() {
+                Some(aof) => aof.execute_transaction(backend, queued),
+                None => queued
+                    .into_iter()
+                    .map(|(db, cmd, _)| cmd.execute(&backend.select(db)))
+                    .collect(),
+            };
+            if let Some(db) = selected {
+                client.select(db);
+            }
+            RespArray::new(replies).into()
+        };
+        transaction.reset();
+        ret
+    }
+}
+
+impl Discard {
+    pub(crate) fn execute_for(self, transaction: &mut Transaction) -> RespFrame {
+        if !transaction.is_queuing() {
+            return SimpleError::new("ERR DISCARD without MULTI").into();
+        }
+        transaction.reset();
+        RESP_OK.clone()
+    }
+}
+
+impl Watch {
+    pub(crate) fn execute_for(self, backend: &Backend, transaction: &mut Transaction) -> RespFrame {
+        if transaction.is_queuing() {
+            return SimpleError::new("ERR WATCH inside MULTI is not allowed").into();
+        }
+        for key in self.keys {
+            transaction.watched.watch(backend.db_index(), key);
+        }
+        RESP_OK.clone()
+    }
+}
+
+impl Unwatch {
+    pub(crate) fn execute_for(self, transaction: &mut Transaction) -> RespFrame {
+        transaction.watched.clear();
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<RespArray> for Multi {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Multi)
+    }
+}
+
+impl TryFrom<RespArray> for Exec {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Exec)
+    }
+}
+
+impl TryFrom<RespArray> for Discard {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Discard)
+    }
+}
+
+impl TryFrom<RespArray> for Watch {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Watch {
+            keys: extract_keys(extract_args(value, 1)?)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unwatch {
+    type Error = CommandError;
+
+    fn try_from(_value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Unwatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::cmd::command;
+    use crate::BulkString;
+
+    fn run(backend: &Backend, transaction: &mut Transaction, args: &[&str]) -> Result<RespFrame> {
+        let cmd = Command::try_from(command(args))?;
+        if cmd.is_transaction() || !transaction.is_queuing() {
+            return Ok(cmd.execute_transaction(backend, &mut Client::new(), transaction));
+        }
+        Ok(transaction.queue(backend.db_index(), cmd, None))
+    }
+
+    #[test]
+    fn test_exec() -> Result<()> {
+        let backend = Backend::new();
+        let mut transaction = Transaction::new(backend.clone());
+
+        run(&backend, &mut transaction, &["multi"])?;
+        let ret = run(&backend, &mut transaction, &["set", "key", "v"])?;
+        assert_eq!(ret, SimpleString::new("QUEUED").into());
+        run(&backend, &mut transaction, &["rpush", "list", "a", "b"])?;
+        assert_eq!(backend.get(b"key"), Ok(None));
+
+        let ret = run(&backend, &mut transaction, &["exec"])?;
+        assert_eq!(
+            ret,
+            RespArray::new(vec![RESP_OK.clone(), RespFrame::Integer(2)]).into()
+        );
+        assert!(!transaction.is_queuing());
+        let ret = run(&backend, &mut transaction, &["exec"])?;
+        assert_eq!(ret, SimpleError::new("ERR EXEC without MULTI").into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_exec_aborted_or_watched() -> Result<()> {
+        let backend = Backend::new();
+        let mut transaction = Transaction::new(backend.clone());
+
+        run(&backend, &mut transaction, &["multi"])?;
+        run(&backend, &mut transaction, &["set", "key", "v"])?;
+        transaction.abort();
+        let ret = run(&backend, &mut transaction, &["exec"])?;
+        assert_eq!(
+            ret,
+            SimpleError::new("EXECABORT Transaction discarded because of previous errors.").into()
+        );
+        assert_eq!(backend.get(b"key"), Ok(None));
+
+        run(&backend, &mut transaction, &["watch", "key"])?;
+        run(&backend, &mut transaction, &["multi"])?;
+        run(&backend, &mut transaction, &["set", "key", "v"])?;
+        // another client changes the watched key
+        backend.set(b"key", BulkString::new("other").into());
+        let ret = run(&backend, &mut transaction, &["exec"])?;
+        assert_eq!(ret, RespArray::nill_new().into());
+        assert_eq!(
+            backend.get(b"key"),
+            Ok(Some(BulkString::new("other").into()))
+        );
+        assert!(backend.watched.is_empty());
+        Ok(())
+    }
+}
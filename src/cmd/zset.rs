@@ -0,0 +1,764 @@
+use bytes::Bytes;
+
+use crate::cmd::list::syntax_error;
+use crate::cmd::set::{extract_keys, parse_key, parse_key_members};
+use crate::cmd::{extract_args, extract_bytes, extract_integer, extract_key};
+use crate::{
+    Aggregate, Backend, BulkString, CommandError, CommandExecutor, LexBound, RespArray, RespFrame,
+    RespNull, ScoreBound, ScoreComparison, ScoredMember, SetCondition, SetOp, ZAdd, ZAddOptions,
+    ZCard, ZCount, ZIncrBy, ZInterStore, ZLexCount, ZPopMax, ZPopMin, ZRandMember, ZRange,
+    ZRangeBy, ZRangeByScore, ZRank, ZRem, ZRevRank, ZScore, ZUnionStore,
+};
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.incr {
+            // the parser only lets a single pair through with INCR
+            let Some((increment, member)) = self.members.into_iter().next() else {
+                return RespFrame::Null(RespNull);
+            };
+            return score_reply(backend.zincrby(&self.key, member, increment, self.options));
+        }
+        match backend.zadd(&self.key, self.members, self.options) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        score_reply(backend.zincrby(
+            &self.key,
+            self.member,
+            self.increment,
+            ZAddOptions::default(),
+        ))
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrem(&self.key, &self.members) {
+            Ok(removed) => RespFrame::Integer(removed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        score_reply(backend.zscore(&self.key, &self.member))
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zcard(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        rank_reply(backend, &self.key, &self.member, false, self.with_score)
+    }
+}
+
+impl CommandExecutor for ZRevRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        rank_reply(backend, &self.key, &self.member, true, self.with_score)
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrange(&self.key, &self.by, self.rev, self.limit) {
+            Ok(members) => scored_reply(members, self.with_scores),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRangeByScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrange(&self.key, &self.by, false, self.limit) {
+            Ok(members) => scored_reply(members, self.with_scores),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zcount(&self.key, &self.by) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZLexCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zcount(&self.key, &self.by) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZPopMin {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zpop(&self.key, self.count.unwrap_or(1), false) {
+            Ok(members) => scored_reply(members, true),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZPopMax {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zpop(&self.key, self.count.unwrap_or(1), true) {
+            Ok(members) => scored_reply(members, true),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZUnionStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend.zstore(
+            SetOp::Union,
+            &self.destination,
+            &self.keys,
+            &self.weights,
+            self.aggregate,
+        );
+        match ret {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZInterStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend.zstore(
+            SetOp::Inter,
+            &self.destination,
+            &self.keys,
+            &self.weights,
+            self.aggregate,
+        );
+        match ret {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRandMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match (
+            backend.zrandmember(&self.key, self.count.unwrap_or(1)),
+            self.count,
+        ) {
+            (Ok(members), Some(_)) => scored_reply(members, self.with_scores),
+            (Ok(mut members), None) => members
+                .pop()
+                .map(|(member, _)| BulkString::new(member).into())
+                .unwrap_or(RespFrame::Null(RespNull)),
+            (Err(e), _) => e.into(),
+        }
+    }
+}
+
+// scores are doubles, sent as bulk strings to RESP2 clients
+fn score_reply(ret: Result<Option<f64>, crate::BackendError>) -> RespFrame {
+    match ret {
+        Ok(Some(score)) => RespFrame::Double(score),
+        Ok(None) => RespFrame::Null(RespNull),
+        Err(e) => e.into(),
+    }
+}
+
+// [member, score, member, score ...] or just the members
+fn scored_reply(members: Vec<ScoredMember>, with_scores: bool) -> RespFrame {
+    let mut frames = Vec::with_capacity(members.len() * if with_scores { 2 } else { 1 });
+    for (member, score) in members {
+        frames.push(BulkString::new(member).into());
+        if with_scores {
+            frames.push(RespFrame::Double(score));
+        }
+    }
+    RespArray::new(frames).into()
+}
+
+fn rank_reply(
+    backend: &Backend,
+    key: &[u8],
+    member: &[u8],
+    rev: bool,
+    with_score: bool,
+) -> RespFrame {
+    match backend.zrank(key, member, rev) {
+        Ok(Some((rank, score))) if with_score => RespArray::new(vec![
+            RespFrame::Integer(rank as i64),
+            RespFrame::Double(score),
+        ])
+        .into(),
+        Ok(Some((rank, _))) => RespFrame::Integer(rank as i64),
+        Ok(None) if with_score => RespArray::nill_new().into(),
+        Ok(None) => RespFrame::Null(RespNull),
+        Err(e) => e.into(),
+    }
+}
+
+// the lowercase option name, if the argument is one
+fn keyword(frame: &RespFrame) -> Option<String> {
+    match frame {
+        RespFrame::BulkString(value) => std::str::from_utf8(value)
+            .ok()
+            .map(|s| s.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+pub(super) fn extract_float(frame: Option<RespFrame>, error: &str) -> Result<f64, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(value)) => std::str::from_utf8(&value)
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|v| !v.is_nan()),
+        _ => None,
+    }
+    .ok_or_else(|| CommandError::InvalidArguments(error.to_string()))
+}
+
+// `1.5`, `(1.5`, `-inf`, `+inf`
+fn extract_score_bound(frame: Option<RespFrame>) -> Result<ScoreBound, CommandError> {
+    let error = "min or max is not a float";
+    let bytes = extract_bytes(frame, "min or max")?;
+    let (exclusive, value) = match bytes.strip_prefix(b"(") {
+        Some(rest) => (true, rest),
+        None => (false, bytes.as_slice()),
+    };
+    let value = extract_float(Some(BulkString::new(value.to_vec()).into()), error)?;
+    Ok(ScoreBound { value, exclusive })
+}
+
+// `[a`, `(a`, `-`, `+`
+fn extract_lex_bound(frame: Option<RespFrame>) -> Result<LexBound, CommandError> {
+    let bytes = extract_bytes(frame, "min or max")?;
+    match bytes.split_first() {
+        Some((b'-', [])) => Ok(LexBound::Min),
+        Some((b'+', [])) => Ok(LexBound::Max),
+        Some((b'[', rest)) => Ok(LexBound::Inclusive(rest.to_vec())),
+        Some((b'(', rest)) => Ok(LexBound::Exclusive(rest.to_vec())),
+        _ => Err(CommandError::InvalidArguments(
+            "min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+// LIMIT offset count
+fn extract_limit(args: &mut impl Iterator<Item = RespFrame>) -> Result<(i64, i64), CommandError> {
+    let offset = extract_integer(args.next())?;
+    let count = extract_integer(args.next())?;
+    Ok((offset, count))
+}
+
+// ZPOPMIN key [count]
+fn parse_pop(value: RespArray) -> Result<(Bytes, Option<usize>), CommandError> {
+    if value.len() > 3 {
+        return Err(syntax_error());
+    }
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    let count = match args.next() {
+        Some(frame) => Some(usize::try_from(extract_integer(Some(frame))?).map_err(|_| {
+            CommandError::InvalidArguments("value is out of range, must be positive".to_string())
+        })?),
+        None => None,
+    };
+    Ok((key, count))
+}
+
+// ZRANK key member [WITHSCORE]
+fn parse_rank(value: RespArray) -> Result<(Bytes, Vec<u8>, bool), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_key(args.next(), "key")?;
+    let member = extract_bytes(args.next(), "member")?;
+    let with_score = match args.next() {
+        None => false,
+        Some(arg) if keyword(&arg).as_deref() == Some("withscore") => true,
+        Some(_) => return Err(syntax_error()),
+    };
+    if args.next().is_some() {
+        return Err(syntax_error());
+    }
+    Ok((key, member, with_score))
+}
+
+// ZUNIONSTORE destination numkeys key [key ...] [WEIGHTS weight [weight ...]]
+// [AGGREGATE SUM | MIN | MAX]
+fn parse_zstore(
+    value: RespArray,
+) -> Result<(Bytes, Vec<Bytes>, Vec<f64>, Aggregate), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let destination = extract_key(args.next(), "destination")?;
+    let numkeys = usize::try_from(extract_integer(args.next())?)
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| {
+            CommandError::InvalidArguments(
+                "at least 1 input key is needed for ZUNIONSTORE/ZINTERSTORE".to_string(),
+            )
+        })?;
+    if numkeys > args.len() {
+        return Err(syntax_error());
+    }
+    let keys = extract_keys(args.by_ref().take(numkeys))?;
+
+    let mut weights = vec![];
+    let mut aggregate = Aggregate::default();
+    while let Some(arg) = args.next() {
+        match keyword(&arg).as_deref() {
+            Some("weights") if args.len() >= numkeys => {
+                weights = args
+                    .by_ref()
+                    .take(numkeys)
+                    .map(|arg| extract_float(Some(arg), "weight value is not a float"))
+                    .collect::<Result<_, _>>()?;
+            }
+            Some("aggregate") => {
+                aggregate = match args.next().as_ref().and_then(keyword).as_deref() {
+                    Some("sum") => Aggregate::Sum,
+                    Some("min") => Aggregate::Min,
+                    Some("max") => Aggregate::Max,
+                    _ => return Err(syntax_error()),
+                };
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok((destination, keys, weights, aggregate))
+}
+
+// ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_key(args.next(), "key")?;
+
+        let mut options = ZAddOptions::default();
+        let (mut nx, mut xx, mut gt, mut lt, mut incr) = (false, false, false, false, false);
+        while let Some(name) = args.peek().and_then(keyword) {
+            match name.as_str() {
+                "nx" => nx = true,
+                "xx" => xx = true,
+                "gt" => gt = true,
+                "lt" => lt = true,
+                "ch" => options.changed = true,
+                "incr" => incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if nx && xx {
+            return Err(CommandError::InvalidArguments(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (gt && lt) || (nx && (gt || lt)) {
+            return Err(CommandError::InvalidArguments(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        options.condition = match (nx, xx) {
+            (true, _) => SetCondition::Nx,
+            (_, true) => SetCondition::Xx,
+            _ => SetCondition::Always,
+        };
+        options.comparison = match (gt, lt) {
+            (true, _) => ScoreComparison::Greater,
+            (_, true) => ScoreComparison::Less,
+            _ => ScoreComparison::Any,
+        };
+
+        let args = args.collect::<Vec<RespFrame>>();
+        if args.is_empty() || args.len() % 2 != 0 {
+            return Err(syntax_error());
+        }
+        if incr && args.len() != 2 {
+            return Err(CommandError::InvalidArguments(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        let mut members = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let Some(score) = args.next() {
+            let score = extract_float(Some(score), "value is not a valid float")?;
+            members.push((score, extract_bytes(args.next(), "member")?));
+        }
+
+        Ok(ZAdd {
+            key,
+            options,
+            incr,
+            members,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZIncrBy {
+            key: extract_key(args.next(), "key")?,
+            increment: extract_float(args.next(), "value is not a valid float")?,
+            member: extract_bytes(args.next(), "member")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = parse_key_members(value)?;
+        Ok(ZRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZScore {
+            key: extract_key(args.next(), "key")?,
+            member: extract_bytes(args.next(), "member")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(ZCard {
+            key: parse_key(value)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRank {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = parse_rank(value)?;
+        Ok(ZRank {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRevRank {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = parse_rank(value)?;
+        Ok(ZRevRank {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+// ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let (start, stop) = (args.next(), args.next());
+
+        let (mut by_score, mut by_lex, mut rev, mut limit, mut with_scores) =
+            (false, false, false, None, false);
+        while let Some(arg) = args.next() {
+            match keyword(&arg).as_deref() {
+                Some("byscore") => by_score = true,
+                Some("bylex") => by_lex = true,
+                Some("rev") => rev = true,
+                Some("limit") => limit = Some(extract_limit(&mut args)?),
+                Some("withscores") => with_scores = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+        if by_score && by_lex {
+            return Err(syntax_error());
+        }
+        if by_lex && with_scores {
+            return Err(CommandError::InvalidArguments(
+                "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+            ));
+        }
+        if limit.is_some() && !by_score && !by_lex {
+            return Err(CommandError::InvalidArguments(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string(),
+            ));
+        }
+
+        // with REV, score and lex ranges are given from max to min
+        let (min, max) = if rev && (by_score || by_lex) {
+            (stop, start)
+        } else {
+            (start, stop)
+        };
+        let by = if by_score {
+            ZRangeBy::Score(extract_score_bound(min)?, extract_score_bound(max)?)
+        } else if by_lex {
+            ZRangeBy::Lex(extract_lex_bound(min)?, extract_lex_bound(max)?)
+        } else {
+            ZRangeBy::Rank(extract_integer(min)?, extract_integer(max)?)
+        };
+        Ok(ZRange {
+            key,
+            by,
+            rev,
+            limit,
+            with_scores,
+        })
+    }
+}
+
+// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+impl TryFrom<RespArray> for ZRangeByScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let by = ZRangeBy::Score(
+            extract_score_bound(args.next())?,
+            extract_score_bound(args.next())?,
+        );
+
+        let (mut limit, mut with_scores) = (None, false);
+        while let Some(arg) = args.next() {
+            match keyword(&arg).as_deref() {
+                Some("limit") => limit = Some(extract_limit(&mut args)?),
+                Some("withscores") => with_scores = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(ZRangeByScore {
+            key,
+            by,
+            limit,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZCount {
+            key: extract_key(args.next(), "key")?,
+            by: ZRangeBy::Score(
+                extract_score_bound(args.next())?,
+                extract_score_bound(args.next())?,
+            ),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZLexCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZLexCount {
+            key: extract_key(args.next(), "key")?,
+            by: ZRangeBy::Lex(
+                extract_lex_bound(args.next())?,
+                extract_lex_bound(args.next())?,
+            ),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMin {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value)?;
+        Ok(ZPopMin { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMax {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = parse_pop(value)?;
+        Ok(ZPopMax { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZUnionStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys, weights, aggregate) = parse_zstore(value)?;
+        Ok(ZUnionStore {
+            destination,
+            keys,
+            weights,
+            aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZInterStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys, weights, aggregate) = parse_zstore(value)?;
+        Ok(ZInterStore {
+            destination,
+            keys,
+            weights,
+            aggregate,
+        })
+    }
+}
+
+// ZRANDMEMBER key [count [WITHSCORES]]
+impl TryFrom<RespArray> for ZRandMember {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        if value.len() > 4 {
+            return Err(syntax_error());
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_key(args.next(), "key")?;
+        let count = match args.next() {
+            Some(frame) => Some(extract_integer(Some(frame))?),
+            None => None,
+        };
+        let with_scores = match args.next() {
+            None => false,
+            Some(arg) if keyword(&arg).as_deref() == Some("withscores") => true,
+            Some(_) => return Err(syntax_error()),
+        };
+        Ok(ZRandMember {
+            key,
+            count,
+            with_scores,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::cmd::command;
+
+    #[test]
+    fn test_zset_commands() -> Result<()> {
+        let backend = Backend::new();
+
+        let cmd = ZAdd::try_from(command(&["zadd", "z", "1", "a", "2", "b", "3", "c"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        let cmd = ZAdd::try_from(command(&["zadd", "z", "XX", "INCR", "1.5", "a"]))?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(2.5));
+
+        let ret = ZRange::try_from(command(&[
+            "zrange",
+            "z",
+            "(3",
+            "-inf",
+            "BYSCORE",
+            "REV",
+            "WITHSCORES",
+        ]))?
+        .execute(&backend);
+        let expected = RespArray::new(vec![
+            BulkString::new("a").into(),
+            RespFrame::Double(2.5),
+            BulkString::new("b").into(),
+            RespFrame::Double(2.0),
+        ]);
+        assert_eq!(ret, expected.into());
+        // RESP2 clients get the scores as bulk strings
+        assert_eq!(
+            ret.into_resp2(),
+            RespArray::new(vec![
+                BulkString::new("a").into(),
+                BulkString::new("2.5").into(),
+                BulkString::new("b").into(),
+                BulkString::new("2").into(),
+            ])
+            .into()
+        );
+
+        let ret =
+            ZRevRank::try_from(command(&["zrevrank", "z", "c", "WITHSCORE"]))?.execute(&backend);
+        assert_eq!(
+            ret,
+            RespArray::new(vec![RespFrame::Integer(0), RespFrame::Double(3.0)]).into()
+        );
+        let ret = ZLexCount::try_from(command(&["zlexcount", "z", "-", "(c"]))?.execute(&backend);
+        assert_eq!(ret, RespFrame::Integer(2));
+        let ret = ZPopMin::try_from(command(&["zpopmin", "z"]))?.execute(&backend);
+        assert_eq!(
+            ret,
+            RespArray::new(vec![BulkString::new("b").into(), RespFrame::Double(2.0)]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zset_parse_errors() {
+        assert!(ZAdd::try_from(command(&["zadd", "z", "NX", "XX", "1", "a"])).is_err());
+        assert!(ZAdd::try_from(command(&["zadd", "z", "GT", "LT", "1", "a"])).is_err());
+        assert!(ZAdd::try_from(command(&["zadd", "z", "INCR", "1", "a", "2", "b"])).is_err());
+        assert!(ZAdd::try_from(command(&["zadd", "z", "nan", "a"])).is_err());
+        assert!(ZRange::try_from(command(&["zrange", "z", "0", "1", "LIMIT", "0", "1"])).is_err());
+        assert!(
+            ZRange::try_from(command(&["zrange", "z", "-", "+", "BYLEX", "WITHSCORES"])).is_err()
+        );
+        assert!(ZRange::try_from(command(&["zrange", "z", "a", "+", "BYLEX"])).is_err());
+        assert!(ZCount::try_from(command(&["zcount", "z", "(x", "1"])).is_err());
+        assert!(ZUnionStore::try_from(command(&["zunionstore", "d", "0", "a"])).is_err());
+        assert!(ZUnionStore::try_from(command(&[
+            "zunionstore",
+            "d",
+            "2",
+            "a",
+            "b",
+            "WEIGHTS",
+            "1"
+        ]))
+        .is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::ProtocolLimits;
+
+/// appendfsync policy of the append only file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AppendFsync {
+    Always,
+    #[default]
+    EverySec,
+    No,
+}
+
+/// client-output-buffer-limit of a class of clients, 0 turns a limit off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimit {
+    /// a client with more bytes waiting to be written is disconnected right away
+    pub hard_limit: usize,
+    /// a client is disconnected once it stays past this for more than `soft_seconds`
+    pub soft_limit: usize,
+    pub soft_seconds: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub port: u16,
+    /// working directory, the rdb and aof files are created inside it
+    pub dir: PathBuf,
+    pub dbfilename: String,
+    pub appendonly: bool,
+    pub appendfilename: String,
+    pub appendfsync: AppendFsync,
+    pub proto_max_bulk_len: usize,
+    pub proto_max_multibulk_len: usize,
+    pub proto_max_nesting_depth: usize,
+    /// a client whose pending request grows past this is disconnected
+    pub client_query_buffer_limit: usize,
+    /// how far published messages may get ahead of a subscriber
+    pub client_output_buffer_limit_pubsub: OutputBufferLimit,
+    /// the number of databases SELECT can switch between
+    pub databases: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let limits = ProtocolLimits::default();
+        Config {
+            port: 6379,
+            dir: PathBuf::from("."),
+            dbfilename: "dump.rdb".to_string(),
+            appendonly: false,
+            appendfilename: "appendonly.aof".to_string(),
+            appendfsync: AppendFsync::default(),
+            proto_max_bulk_len: limits.max_bulk_len,
+            proto_max_multibulk_len: limits.max_multibulk_len,
+            proto_max_nesting_depth: limits.max_nesting_depth,
+            client_query_buffer_limit: 1024 * 1024 * 1024,
+            // same as redis: pubsub 32mb 8mb 60
+            client_output_buffer_limit_pubsub: OutputBufferLimit {
+                hard_limit: 32 * 1024 * 1024,
+                soft_limit: 8 * 1024 * 1024,
+                soft_seconds: 60,
+            },
+            databases: 16,
+        }
+    }
+}
+
+impl FromStr for AppendFsync {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "always" => Ok(AppendFsync::Always),
+            "everysec" => Ok(AppendFsync::EverySec),
+            "no" => Ok(AppendFsync::No),
+            _ => Err(anyhow!("invalid appendfsync value: {}", s)),
+        }
+    }
+}
+
+fn parse_yes_no(s: &str) -> Result<bool> {
+    match s.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(anyhow!("argument must be 'yes' or 'no': {}", s)),
+    }
+}
+
+// a byte count with an optional unit, 1k is 1000 bytes and 1kb 1024 like in redis.conf
+fn parse_memory(s: &str) -> Result<usize> {
+    let s = s.to_ascii_lowercase();
+    let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = match &s[digits.len()..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(anyhow!("invalid memory value: {}", s)),
+    };
+    digits
+        .parse::<usize>()?
+        .checked_mul(unit)
+        .ok_or_else(|| anyhow!("memory value out of range: {}", s))
+}
+
+// `<class> <hard limit> <soft limit> <soft seconds>`, repeated. Only subscribers have
+// their output bounded here.
+fn parse_output_buffer_limits(s: &str, config: &mut Config) -> Result<()> {
+    let tokens = s.split_whitespace().collect::<Vec<_>>();
+    if tokens.is_empty() || tokens.len() % 4 != 0 {
+        return Err(anyhow!("invalid client-output-buffer-limit: {}", s));
+    }
+    for limit in tokens.chunks(4) {
+        if !limit[0].eq_ignore_ascii_case("pubsub") {
+            return Err(anyhow!("unsupported client class: {}", limit[0]));
+        }
+        config.client_output_buffer_limit_pubsub = OutputBufferLimit {
+            hard_limit: parse_memory(limit[1])?,
+            soft_limit: parse_memory(limit[2])?,
+            soft_seconds: limit[3].parse()?,
+        };
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Parse redis-server style options: `--port 6380 --appendonly yes`
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(name) = args.next() {
+            let option = name
+                .strip_prefix("--")
+                .ok_or_else(|| anyhow!("invalid option: {}", name))?
+                .to_ascii_lowercase();
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("missing value for option: {}", name))?;
+
+            match option.as_str() {
+                "port" => config.port = value.parse()?,
+                "dir" => config.dir = PathBuf::from(value),
+                "dbfilename" => config.dbfilename = value,
+                "appendonly" => config.appendonly = parse_yes_no(&value)?,
+                "appendfilename" => config.appendfilename = value,
+                "appendfsync" => config.appendfsync = value.parse()?,
+                "proto-max-bulk-len" => config.proto_max_bulk_len = parse_memory(&value)?,
+                "proto-max-multibulk-len" => config.proto_max_multibulk_len = value.parse()?,
+                "proto-max-nesting-depth" => config.proto_max_nesting_depth = value.parse()?,
+                "client-query-buffer-limit" => {
+                    config.client_query_buffer_limit = parse_memory(&value)?
+                }
+                "client-output-buffer-limit" => parse_output_buffer_limits(&value, &mut config)?,
+                "databases" => match value.parse()? {
+                    0 => return Err(anyhow!("invalid number of databases: {}", value)),
+                    databases => config.databases = databases,
+                },
+                _ => return Err(anyhow!("unknown option: {}", name)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn protocol_limits(&self) -> ProtocolLimits {
+        ProtocolLimits {
+            max_bulk_len: self.proto_max_bulk_len,
+            max_multibulk_len: self.proto_max_multibulk_len,
+            max_nesting_depth: self.proto_max_nesting_depth,
+        }
+    }
+
+    pub fn rdb_path(&self) -> PathBuf {
+        self.dir.join(&self.dbfilename)
+    }
+
+    pub fn aof_path(&self) -> PathBuf {
+        self.dir.join(&self.appendfilename)
+    }
+}
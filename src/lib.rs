@@ -1,11 +1,18 @@
+pub use aof::{Aof, AofError};
 pub use backend::*;
 pub use cmd::*;
+pub use config::*;
 pub use network::*;
 pub use resp::*;
 
 mod backend;
+mod config;
 mod resp;
 
 pub mod cmd;
 
+pub mod aof;
+
 pub mod network;
+
+pub mod rdb;
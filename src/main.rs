@@ -1,17 +1,32 @@
 use anyhow::Result;
 use tracing::info;
 
-use simple_redis::{network, Backend};
+use simple_redis::{aof, network, rdb, Backend, Config};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
-    let addr = "0.0.0.0:6379";
+    let config = Config::from_args(std::env::args().skip(1))?;
+    let addr = format!("0.0.0.0:{}", config.port);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
 
-    let backend = Backend::new();
+    let backend = Backend::with_config(config);
+    let (rdb_path, aof_path) = (backend.config().rdb_path(), backend.config().aof_path());
+    // the aof is more complete than the snapshot, so it wins when both exist
+    if backend.config().appendonly && aof_path.exists() {
+        aof::load(&backend, &aof_path)?;
+        backend.enable_aof()?;
+    } else {
+        if rdb_path.exists() {
+            rdb::load(&backend, &rdb_path)?;
+        }
+        if backend.config().appendonly {
+            backend.enable_aof()?.rewrite(&backend)?;
+        }
+    }
+    backend.spawn_active_expire();
 
     loop {
         let (stream, raddr) = listener.accept().await?;
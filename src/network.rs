@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::ops::Deref;
 
 use anyhow::Result;
@@ -5,12 +6,39 @@ use futures::SinkExt;
 use tokio::net::TcpStream;
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::{Backend, Command, CommandExecutor, RespDecode, RespEncode, RespError, RespFrame};
+use crate::{
+    is_inline, is_write_command, lookup_command, Backend, Client, Command, CommandExecutor, Config,
+    RespArray, RespEncode, RespError, RespFrame, RespParser, RespProtocol, SimpleError, Subscriber,
+    Transaction,
+};
+
+// same as redis, an inline command has to fit in 64 KB
+const PROTO_INLINE_MAX_SIZE: usize = 64 * 1024;
 
 #[derive(Debug)]
-struct RespFrameCodec;
+struct RespFrameCodec {
+    protocol: RespProtocol,
+    parser: RespParser,
+    max_query_buffer: usize,
+    // bytes consumed for the frame being decoded
+    consumed: usize,
+    // bytes the last decoded frame took in the stream
+    frame_len: usize,
+}
+
+impl RespFrameCodec {
+    fn new(config: &Config) -> Self {
+        RespFrameCodec {
+            protocol: RespProtocol::default(),
+            parser: RespParser::new(config.protocol_limits()),
+            max_query_buffer: config.client_query_buffer_limit,
+            consumed: 0,
+            frame_len: 0,
+        }
+    }
+}
 
 #[derive(Debug)]
 struct RedisRequest {
@@ -18,57 +46,240 @@ struct RedisRequest {
     backend: Backend,
 }
 
+// the state connection-aware commands work on
+#[derive(Debug)]
+struct Connection {
+    client: Client,
+    subscriber: Subscriber,
+    transaction: Transaction,
+}
+
 #[derive(Debug)]
 struct RedisResponse {
-    frame: RespFrame,
+    // subscription commands reply once per channel
+    frames: Vec<RespFrame>,
 }
 
 impl Deref for RedisResponse {
-    type Target = RespFrame;
+    type Target = [RespFrame];
 
     fn deref(&self) -> &Self::Target {
-        &self.frame
+        &self.frames
     }
 }
 
 pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
-    let mut framed = Framed::new(stream, RespFrameCodec);
+    let mut framed = Framed::new(stream, RespFrameCodec::new(backend.config()));
+    // published messages for the channels this connection subscribed to
+    let (subscriber, mut messages) = Subscriber::new(backend.clone());
+    let mut connection = Connection {
+        client: Client::new(),
+        subscriber,
+        transaction: Transaction::new(backend.clone()),
+    };
+    // frames pipelined while a blocking command was waiting, with their size. They are
+    // still part of the query buffer and count against its limit.
+    let mut pending = VecDeque::new();
+    let mut pending_len = 0;
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
-                let req = RedisRequest {
-                    frame,
-                    backend: backend.clone(),
-                };
-
-                let resp = request_handle(req).await?;
-                info!("resp:{:?}", resp.frame);
-                framed.send(resp.frame).await?;
+        let frame = match pending.pop_front() {
+            Some((frame, len)) => {
+                pending_len -= len;
+                frame
+            }
+            None => tokio::select! {
+                next = framed.next() => match next {
+                    Some(Ok(frame)) => frame,
+                    Some(Err(err)) => return protocol_error(&mut framed, err).await,
+                    None => return Ok(()),
+                },
+                message = messages.recv() => match message {
+                    Some(message) => {
+                        framed.send(message).await?;
+                        continue;
+                    }
+                    None => {
+                        warn!("subscriber closed for overcoming of output buffer limits");
+                        return Ok(());
+                    }
+                },
+            },
+        };
+        let req = RedisRequest {
+            frame,
+            // the commands run in the database the connection selected
+            backend: backend.select(connection.client.db()),
+        };
+
+        let resp = {
+            // keep reading while the command is blocked, so a client that goes away stops waiting
+            let handle = request_handle(req, &mut connection);
+            tokio::pin!(handle);
+            loop {
+                tokio::select! {
+                    biased;
+                    resp = &mut handle => break resp?,
+                    next = framed.next() => match next {
+                        Some(Ok(frame)) => {
+                            let len = framed.codec().frame_len;
+                            pending_len += len;
+                            if pending_len > framed.codec().max_query_buffer {
+                                let err = RespError::Protocol(
+                                    "query buffer limit exceeded".to_string(),
+                                );
+                                return protocol_error(&mut framed, err.into()).await;
+                            }
+                            pending.push_back((frame, len));
+                        }
+                        Some(Err(err)) => return protocol_error(&mut framed, err).await,
+                        None => return Ok(()),
+                    },
+                }
             }
-            Some(Err(err)) => return Err(err),
-            None => return Ok(()),
+        };
+        // HELLO replies with the protocol it switched to
+        framed.codec_mut().protocol = connection.client.protocol();
+        info!("resp:{:?}", resp.frames);
+        for frame in resp.frames {
+            framed.feed(frame).await?;
         }
+        framed.flush().await?;
+    }
+}
+
+// the stream can not be resynchronized after a malformed request: tell the client why
+// and close the connection, like redis does
+async fn protocol_error(
+    framed: &mut Framed<TcpStream, RespFrameCodec>,
+    err: anyhow::Error,
+) -> Result<()> {
+    if let Some(err) = err.downcast_ref::<RespError>() {
+        framed
+            .send(SimpleError::new(format!("ERR {}", err)).into())
+            .await?;
     }
+    Err(err)
 }
 
-async fn request_handle(req: RedisRequest) -> Result<RedisResponse> {
+async fn request_handle(req: RedisRequest, connection: &mut Connection) -> Result<RedisResponse> {
     let (frame, backend) = (req.frame, req.backend);
-    let cmd = Command::try_from(frame)?;
+    let Connection {
+        client,
+        subscriber,
+        transaction,
+    } = connection;
+    let name = command_name(&frame);
+    // keep the original frame around when write commands have to be appended to the aof
+    let args = match (backend.aof(), &frame) {
+        (Some(_), RespFrame::Array(args)) if is_write_command(args) => Some(args.clone()),
+        _ => None,
+    };
+    let cmd = match Command::try_from(frame) {
+        Ok(cmd) => cmd,
+        // the client gets the error and may go on, inside MULTI the EXEC fails later
+        Err(err) => {
+            transaction.abort();
+            return Ok(RedisResponse {
+                frames: vec![err.into()],
+            });
+        }
+    };
 
     info!("execute cmd: {:?}", cmd);
-    let response_frame = cmd.execute(&backend);
+    // RESP2 has no way to tell replies from messages, so a subscribed client only manages
+    // its subscriptions. RESP3 clients get messages as push frames and may run anything.
+    if client.protocol() == RespProtocol::Resp2 && subscriber.count() > 0 {
+        match cmd {
+            // the keepalive of subscribed clients
+            Command::Ping(ping) => {
+                return Ok(RedisResponse {
+                    frames: vec![ping.execute_subscribed()],
+                })
+            }
+            ref cmd if !cmd.is_subscription() => {
+                let error = format!(
+                    "ERR Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context",
+                    name
+                );
+                return Ok(RedisResponse {
+                    frames: vec![SimpleError::new(error).into()],
+                });
+            }
+            _ => {}
+        }
+    }
+    if cmd.is_subscription() {
+        return Ok(RedisResponse {
+            frames: cmd.execute_subscription(&backend, subscriber),
+        });
+    }
+    if cmd.is_transaction() {
+        let response_frame = cmd.execute_transaction(&backend, client, transaction);
+        backend.serve_blocked();
+        return Ok(RedisResponse {
+            frames: vec![response_frame],
+        });
+    }
+    // a SELECT is queued as well, it switches the database when EXEC runs it
+    if transaction.is_queuing() && !matches!(cmd, Command::Hello(_)) {
+        let response_frame =
+            if lookup_command(name.as_bytes()).is_some_and(|spec| spec.is_no_multi()) {
+                transaction.abort();
+                SimpleError::new("ERR Command not allowed inside a transaction").into()
+            } else {
+                transaction.queue(backend.db_index(), cmd, args)
+            };
+        return Ok(RedisResponse {
+            frames: vec![response_frame],
+        });
+    }
+    if cmd.is_connection() {
+        return Ok(RedisResponse {
+            frames: vec![cmd.execute_connection(&backend, client)],
+        });
+    }
+    let response_frame = match (backend.aof(), args) {
+        // blocking commands append what they pop themselves, they are not replayable as is
+        _ if cmd.is_blocking() => cmd.execute_async(&backend).await,
+        (Some(aof), Some(args)) => {
+            let _shared = backend.lock_shared();
+            aof.execute(cmd, args, &backend)
+        }
+        _ => {
+            let _shared = backend.lock_shared();
+            cmd.execute(&backend)
+        }
+    };
+    // hand the elements pushed by the command to the clients blocked on them
+    backend.serve_blocked();
 
     Ok(RedisResponse {
-        frame: response_frame,
+        frames: vec![response_frame],
     })
 }
 
+// lowercase name of the command for error messages
+fn command_name(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::Array(args) => match args.first() {
+            Some(RespFrame::BulkString(name)) => String::from_utf8_lossy(name).to_lowercase(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
 impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
-        let encode = item.encode();
-        dst.extend_from_slice(&encode);
+        let item = match self.protocol {
+            // attributes are only meant for RESP3 clients
+            RespProtocol::Resp2 if matches!(item, RespFrame::Attribute(_)) => return Ok(()),
+            RespProtocol::Resp2 => item.into_resp2(),
+            RespProtocol::Resp3 => item,
+        };
+        item.encode_to(dst);
 
         Ok(())
     }
@@ -79,11 +290,173 @@ impl Decoder for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let res = RespFrame::decode(src);
-        match res {
-            Ok(frame) => Ok(Some(frame)),
-            Err(RespError::NotComplete) => Ok(None),
-            Err(err) => Err(err.into()),
+        let len = src.len();
+        let ret = self.decode_frame(src);
+        self.consumed += len - src.len();
+        if let Ok(Some(_)) = ret {
+            self.frame_len = std::mem::take(&mut self.consumed);
+        }
+        ret
+    }
+}
+
+impl RespFrameCodec {
+    fn decode_frame(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
+        // anything but a RESP frame is a command typed in telnet or nc
+        if self.parser.is_idle() && is_inline(src) {
+            match RespArray::decode_inline(src) {
+                Ok(frame) => return Ok(Some(frame.into())),
+                // the blank lines before a RESP frame were skipped
+                Err(RespError::NotComplete) if !is_inline(src) => {}
+                Err(RespError::NotComplete) if src.len() > PROTO_INLINE_MAX_SIZE => {
+                    return Err(RespError::Protocol("too big inline request".to_string()).into())
+                }
+                Err(RespError::NotComplete) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            }
         }
+        match self.parser.parse(src)? {
+            Some(frame) => Ok(Some(frame)),
+            // what is buffered for a request has to stay below the limit until it is complete
+            None if self.parser.pending_len() + src.len() > self.max_query_buffer => {
+                Err(RespError::Protocol("query buffer limit exceeded".to_string()).into())
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::*;
+
+    fn connection(backend: &Backend) -> Connection {
+        let (subscriber, _messages) = Subscriber::new(backend.clone());
+        Connection {
+            client: Client::new(),
+            subscriber,
+            transaction: Transaction::new(backend.clone()),
+        }
+    }
+
+    // decode and execute the requests in `input` like the connection would, returns what
+    // the client reads back
+    async fn send(backend: &Backend, connection: &mut Connection, input: &str) -> Result<BytesMut> {
+        let mut codec = RespFrameCodec::new(backend.config());
+        let mut src = BytesMut::from(input);
+        let mut out = BytesMut::new();
+        while let Some(frame) = codec.decode(&mut src)? {
+            let req = RedisRequest {
+                frame,
+                backend: backend.select(connection.client.db()),
+            };
+            for frame in request_handle(req, connection).await?.frames {
+                codec.encode(frame, &mut out)?;
+            }
+        }
+        Ok(out)
+    }
+
+    // what `nc` sends: an inline PING, decoded and executed like any other request
+    #[tokio::test]
+    async fn test_inline_ping() -> Result<()> {
+        let backend = Backend::new();
+        let mut connection = connection(&backend);
+        let out = send(&backend, &mut connection, "PING\r\nping hello\r\n").await?;
+        assert_eq!(&out[..], b"+PONG\r\n$5\r\nhello\r\n");
+        // a stray CRLF before a RESP frame
+        let out = send(&backend, &mut connection, "\r\n*1\r\n$4\r\nPING\r\n").await?;
+        assert_eq!(&out[..], b"+PONG\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_ping() -> Result<()> {
+        let backend = Backend::new();
+        let mut connection = connection(&backend);
+        send(&backend, &mut connection, "SUBSCRIBE news\r\n").await?;
+
+        let out = send(&backend, &mut connection, "PING\r\nPING hi\r\n").await?;
+        assert_eq!(
+            &out[..],
+            b"*2\r\n$4\r\npong\r\n$0\r\n\r\n*2\r\n$4\r\npong\r\n$2\r\nhi\r\n"
+        );
+        let out = send(&backend, &mut connection, "GET key\r\n").await?;
+        assert!(out.starts_with(b"-ERR Can't execute 'get'"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multi_select() -> Result<()> {
+        let backend = Backend::new();
+        let mut connection = connection(&backend);
+        let out = send(
+            &backend,
+            &mut connection,
+            "MULTI\r\nSELECT 1\r\nSET key v\r\nSELECT 99\r\nEXEC\r\nGET key\r\n",
+        )
+        .await?;
+        assert_eq!(
+            &out[..],
+            b"+OK\r\n+QUEUED\r\n+QUEUED\r\n+QUEUED\r\n\
+              *3\r\n+OK\r\n+OK\r\n-ERR DB index is out of range\r\n$1\r\nv\r\n"
+        );
+        assert!(!backend.contains_key(b"key"));
+        assert_eq!(connection.client.db(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multi_no_multi_commands() -> Result<()> {
+        let backend = Backend::new();
+        let mut connection = connection(&backend);
+        for command in ["SAVE", "BGSAVE", "BGREWRITEAOF"] {
+            let input = format!("MULTI\r\nSET key v\r\n{}\r\nEXEC\r\n", command);
+            let out = send(&backend, &mut connection, &input).await?;
+            assert_eq!(
+                &out[..],
+                b"+OK\r\n+QUEUED\r\n-ERR Command not allowed inside a transaction\r\n\
+                  -EXECABORT Transaction discarded because of previous errors.\r\n"
+            );
+        }
+        assert!(!backend.contains_key(b"key"));
+        Ok(())
+    }
+
+    // frames queued behind a blocked command are part of the query buffer
+    #[tokio::test]
+    async fn test_pending_frames_limit() -> Result<()> {
+        use tokio::net::TcpListener;
+        use tokio_util::codec::BytesCodec;
+
+        let backend = Backend::with_config(Config {
+            client_query_buffer_limit: 1024,
+            ..Default::default()
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let stream = TcpStream::connect(listener.local_addr()?).await?;
+        let mut client = Framed::new(stream, BytesCodec::new());
+        let (stream, _) = listener.accept().await?;
+        let server = tokio::spawn(stream_handler(stream, backend));
+
+        client.send(Bytes::from("BLPOP list 0\r\n")).await?;
+        // 110 bytes each, the tenth goes over the limit. Nothing is left unread by then, so
+        // the server closes the connection cleanly after the error.
+        let set = format!("SET key {}\r\n", "v".repeat(100));
+        for _ in 0..10 {
+            client.send(Bytes::from(set.clone())).await?;
+        }
+        let mut reply = BytesMut::new();
+        while let Some(read) = client.next().await {
+            reply.extend_from_slice(&read?);
+        }
+        assert_eq!(
+            &reply[..],
+            b"-ERR Protocol error: query buffer limit exceeded\r\n"
+        );
+        assert!(server.await?.is_err());
+        Ok(())
     }
 }
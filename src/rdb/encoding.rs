@@ -0,0 +1,214 @@
+use crate::rdb::RdbError;
+
+// compact encodings that redis embeds as a single string inside the rdb file
+
+fn invalid(what: &str) -> RdbError {
+    RdbError::InvalidFormat(format!("corrupted {}", what))
+}
+
+fn take<'a>(buf: &'a [u8], pos: &mut usize, n: usize, what: &str) -> Result<&'a [u8], RdbError> {
+    let data = buf.get(*pos..*pos + n).ok_or_else(|| invalid(what))?;
+    *pos += n;
+    Ok(data)
+}
+
+fn int_le(data: &[u8]) -> i64 {
+    // sign extend little endian integers of 1..=8 bytes
+    let mut bytes = [0u8; 8];
+    bytes[..data.len()].copy_from_slice(data);
+    let shift = 64 - data.len() * 8;
+    (i64::from_le_bytes(bytes) << shift) >> shift
+}
+
+// <zlbytes><zltail><zllen><entry>...<entry><zlend>
+pub(crate) fn decode_ziplist(buf: &[u8]) -> Result<Vec<Vec<u8>>, RdbError> {
+    const WHAT: &str = "ziplist";
+    let mut pos = 10;
+    let mut entries = Vec::new();
+
+    loop {
+        match buf.get(pos) {
+            Some(0xff) => break,
+            Some(0xfe) => pos += 5,
+            Some(_) => pos += 1,
+            None => return Err(invalid(WHAT)),
+        }
+
+        let encoding = *buf.get(pos).ok_or_else(|| invalid(WHAT))?;
+        pos += 1;
+        let entry = match encoding >> 6 {
+            0b00 => take(buf, &mut pos, (encoding & 0x3f) as usize, WHAT)?.to_vec(),
+            0b01 => {
+                let low = take(buf, &mut pos, 1, WHAT)?[0] as usize;
+                let len = (((encoding & 0x3f) as usize) << 8) | low;
+                take(buf, &mut pos, len, WHAT)?.to_vec()
+            }
+            0b10 => {
+                let len = take(buf, &mut pos, 4, WHAT)?;
+                let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+                take(buf, &mut pos, len, WHAT)?.to_vec()
+            }
+            _ => {
+                let value = match encoding {
+                    0xc0 => int_le(take(buf, &mut pos, 2, WHAT)?),
+                    0xd0 => int_le(take(buf, &mut pos, 4, WHAT)?),
+                    0xe0 => int_le(take(buf, &mut pos, 8, WHAT)?),
+                    0xf0 => int_le(take(buf, &mut pos, 3, WHAT)?),
+                    0xfe => int_le(take(buf, &mut pos, 1, WHAT)?),
+                    0xf1..=0xfd => (encoding & 0x0f) as i64 - 1,
+                    _ => return Err(invalid(WHAT)),
+                };
+                value.to_string().into_bytes()
+            }
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+// <total-bytes><num-elements><element>...<element><end>
+pub(crate) fn decode_listpack(buf: &[u8]) -> Result<Vec<Vec<u8>>, RdbError> {
+    const WHAT: &str = "listpack";
+    let mut pos = 6;
+    let mut entries = Vec::new();
+
+    loop {
+        let start = pos;
+        let encoding = *buf.get(pos).ok_or_else(|| invalid(WHAT))?;
+        pos += 1;
+
+        let entry = if encoding == 0xff {
+            break;
+        } else if encoding & 0x80 == 0 {
+            (encoding & 0x7f).to_string().into_bytes()
+        } else if encoding & 0xc0 == 0x80 {
+            take(buf, &mut pos, (encoding & 0x3f) as usize, WHAT)?.to_vec()
+        } else if encoding & 0xe0 == 0xc0 {
+            let low = take(buf, &mut pos, 1, WHAT)?[0] as i64;
+            let value = (((encoding & 0x1f) as i64) << 8) | low;
+            let value = if value >= 1 << 12 {
+                value - (1 << 13)
+            } else {
+                value
+            };
+            value.to_string().into_bytes()
+        } else if encoding & 0xf0 == 0xe0 {
+            let low = take(buf, &mut pos, 1, WHAT)?[0] as usize;
+            let len = (((encoding & 0x0f) as usize) << 8) | low;
+            take(buf, &mut pos, len, WHAT)?.to_vec()
+        } else {
+            match encoding {
+                0xf0 => {
+                    let len = take(buf, &mut pos, 4, WHAT)?;
+                    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+                    take(buf, &mut pos, len, WHAT)?.to_vec()
+                }
+                0xf1 => int_le(take(buf, &mut pos, 2, WHAT)?)
+                    .to_string()
+                    .into_bytes(),
+                0xf2 => int_le(take(buf, &mut pos, 3, WHAT)?)
+                    .to_string()
+                    .into_bytes(),
+                0xf3 => int_le(take(buf, &mut pos, 4, WHAT)?)
+                    .to_string()
+                    .into_bytes(),
+                0xf4 => int_le(take(buf, &mut pos, 8, WHAT)?)
+                    .to_string()
+                    .into_bytes(),
+                _ => return Err(invalid(WHAT)),
+            }
+        };
+
+        // skip the back length, its size depends on the size of encoding + data
+        let len = pos - start;
+        pos += match len {
+            0..=127 => 1,
+            128..=16382 => 2,
+            16383..=2097150 => 3,
+            2097151..=268435454 => 4,
+            _ => 5,
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+// <encoding><length><contents>, sorted integers of 2, 4 or 8 bytes
+pub(crate) fn decode_intset(buf: &[u8]) -> Result<Vec<Vec<u8>>, RdbError> {
+    const WHAT: &str = "intset";
+    let mut pos = 0;
+    let header = take(buf, &mut pos, 8, WHAT)?;
+    let encoding = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    if !matches!(encoding, 2 | 4 | 8) {
+        return Err(invalid(WHAT));
+    }
+
+    (0..len)
+        .map(|_| {
+            let data = take(buf, &mut pos, encoding, WHAT)?;
+            Ok(int_le(data).to_string().into_bytes())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_listpack() -> Result<(), RdbError> {
+        // ["a", 5, -1, "hello"]
+        let data = [
+            0x17, 0, 0, 0, 4, 0, // header
+            0x81, b'a', 2, // 6 bit string
+            0x05, 1, // 7 bit uint
+            0xdf, 0xff, 2, // 13 bit int
+            0x85, b'h', b'e', b'l', b'l', b'o', 6, // 6 bit string
+            0xff,
+        ];
+        let entries = decode_listpack(&data)?;
+        assert_eq!(
+            entries,
+            vec![
+                b"a".to_vec(),
+                b"5".to_vec(),
+                b"-1".to_vec(),
+                b"hello".to_vec()
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_ziplist() -> Result<(), RdbError> {
+        // ["ab", 12, 300]
+        let data = [
+            0, 0, 0, 0, 0, 0, 0, 0, 3, 0, // header
+            0x00, 0x02, b'a', b'b', // string
+            0x04, 0xfd, // immediate int 12
+            0x02, 0xc0, 0x2c, 0x01, // int16 300
+            0xff,
+        ];
+        let entries = decode_ziplist(&data)?;
+        assert_eq!(
+            entries,
+            vec![b"ab".to_vec(), b"12".to_vec(), b"300".to_vec()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_intset() -> Result<(), RdbError> {
+        // [-2, 70000] with 4 byte integers
+        let data = [
+            4, 0, 0, 0, 2, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff, 0x70, 0x11, 0x01, 0,
+        ];
+        let entries = decode_intset(&data)?;
+        assert_eq!(entries, vec![b"-2".to_vec(), b"70000".to_vec()]);
+        assert!(decode_intset(&data[..12]).is_err());
+        Ok(())
+    }
+}
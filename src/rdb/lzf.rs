@@ -0,0 +1,74 @@
+use crate::rdb::RdbError;
+
+// a back reference of 3 bytes expands to at most 264
+const LZF_MAX_EXPANSION: usize = 88;
+
+// LZF decompression, redis compresses strings longer than 20 bytes with it
+pub(crate) fn decompress(input: &[u8], expect_len: usize) -> Result<Vec<u8>, RdbError> {
+    // the length comes from the file, a corrupt one must not reserve more than the input holds
+    let mut out: Vec<u8> =
+        Vec::with_capacity(expect_len.min(input.len().saturating_mul(LZF_MAX_EXPANSION)));
+    let mut ip = 0;
+
+    while ip < input.len() {
+        let ctrl = input[ip] as usize;
+        ip += 1;
+
+        if ctrl < 32 {
+            // literal run of ctrl + 1 bytes
+            let len = ctrl + 1;
+            let literal = input
+                .get(ip..ip + len)
+                .ok_or_else(|| RdbError::InvalidFormat("lzf literal overflow".to_string()))?;
+            out.extend_from_slice(literal);
+            ip += len;
+        } else {
+            // back reference
+            let mut len = ctrl >> 5;
+            if len == 7 {
+                len += *input
+                    .get(ip)
+                    .ok_or_else(|| RdbError::InvalidFormat("lzf length overflow".to_string()))?
+                    as usize;
+                ip += 1;
+            }
+            let low = *input
+                .get(ip)
+                .ok_or_else(|| RdbError::InvalidFormat("lzf offset overflow".to_string()))?
+                as usize;
+            ip += 1;
+
+            let offset = ((ctrl & 0x1f) << 8) + low + 1;
+            if offset > out.len() {
+                return Err(RdbError::InvalidFormat("lzf back reference".to_string()));
+            }
+            let start = out.len() - offset;
+            // the reference may overlap with the bytes being written
+            for i in 0..len + 2 {
+                out.push(out[start + i]);
+            }
+        }
+    }
+
+    if out.len() != expect_len {
+        return Err(RdbError::InvalidFormat(format!(
+            "lzf expect {} bytes, got {}",
+            expect_len,
+            out.len()
+        )));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lzf_decompress() -> Result<(), RdbError> {
+        // 15 times 'a': a literal 'a', a back reference repeating it 13 times, a literal 'a'
+        let data = [0x00, b'a', 0xe0, 0x04, 0x00, 0x00, b'a'];
+        assert_eq!(decompress(&data, 15)?, b"aaaaaaaaaaaaaaa");
+        Ok(())
+    }
+}
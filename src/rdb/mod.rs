@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::rdb::writer::DbSnapshot;
+use crate::Backend;
+
+pub use reader::load;
+
+mod crc64;
+mod encoding;
+mod lzf;
+mod reader;
+mod writer;
+
+// snapshot in the redis rdb format, see https://rdb.fnordig.de/file_format.html
+const RDB_MAGIC: &[u8] = b"REDIS";
+const RDB_VERSION: u32 = 9;
+
+const RDB_OPCODE_FUNCTION2: u8 = 0xf5;
+const RDB_OPCODE_IDLE: u8 = 0xf8;
+const RDB_OPCODE_FREQ: u8 = 0xf9;
+const RDB_OPCODE_AUX: u8 = 0xfa;
+const RDB_OPCODE_RESIZEDB: u8 = 0xfb;
+const RDB_OPCODE_EXPIRETIME_MS: u8 = 0xfc;
+const RDB_OPCODE_EXPIRETIME: u8 = 0xfd;
+const RDB_OPCODE_SELECTDB: u8 = 0xfe;
+const RDB_OPCODE_EOF: u8 = 0xff;
+
+const RDB_TYPE_STRING: u8 = 0;
+const RDB_TYPE_LIST: u8 = 1;
+const RDB_TYPE_SET: u8 = 2;
+const RDB_TYPE_ZSET: u8 = 3;
+const RDB_TYPE_HASH: u8 = 4;
+const RDB_TYPE_ZSET_2: u8 = 5;
+const RDB_TYPE_LIST_ZIPLIST: u8 = 10;
+const RDB_TYPE_SET_INTSET: u8 = 11;
+const RDB_TYPE_ZSET_ZIPLIST: u8 = 12;
+const RDB_TYPE_HASH_ZIPLIST: u8 = 13;
+const RDB_TYPE_LIST_QUICKLIST: u8 = 14;
+const RDB_TYPE_HASH_LISTPACK: u8 = 16;
+const RDB_TYPE_ZSET_LISTPACK: u8 = 17;
+const RDB_TYPE_LIST_QUICKLIST_2: u8 = 18;
+const RDB_TYPE_SET_LISTPACK: u8 = 20;
+
+// quicklist 2 node containers
+const QUICKLIST_NODE_PLAIN: u64 = 1;
+const QUICKLIST_NODE_PACKED: u64 = 2;
+
+// special string encodings, flagged by the two high bits of the length byte
+const RDB_ENCVAL: u8 = 3;
+const RDB_ENC_INT8: u8 = 0;
+const RDB_ENC_INT16: u8 = 1;
+const RDB_ENC_INT32: u8 = 2;
+const RDB_ENC_LZF: u8 = 3;
+
+#[derive(Debug, Error)]
+pub enum RdbError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid rdb format: {0}")]
+    InvalidFormat(String),
+    #[error("unsupported rdb version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("unsupported rdb value type: {0}")]
+    UnsupportedType(u8),
+    #[error("wrong rdb checksum, expected {expected:#x}, got {actual:#x}")]
+    ChecksumMismatch { expected: u64, actual: u64 },
+    #[error("Background save already in progress")]
+    SaveInProgress,
+    #[error("value can not be stored in an rdb file: {0}")]
+    UnsupportedValue(String),
+}
+
+/// SAVE: write the snapshot synchronously
+pub fn save(backend: &Backend) -> Result<(), RdbError> {
+    // SAVE and BGSAVE never write a dump at the same time, one of them would rename its
+    // file over the newer one
+    claim_save(backend)?;
+    let ret = save_to(writer::snapshot(backend), &backend.config.rdb_path());
+    backend.rdb_saving.store(false, Ordering::Release);
+    ret
+}
+
+/// BGSAVE: write the snapshot on a dedicated thread, the caller returns immediately
+pub fn bgsave(backend: &Backend) -> Result<(), RdbError> {
+    claim_save(backend)?;
+
+    // the copy is taken before returning, writing it is left to the thread
+    let snapshot = writer::snapshot(backend);
+    let backend = backend.clone();
+    std::thread::spawn(move || {
+        match save_to(snapshot, &backend.config.rdb_path()) {
+            Ok(_) => info!("Background saving terminated with success"),
+            Err(e) => warn!("Background saving error: {}", e),
+        }
+        backend.rdb_saving.store(false, Ordering::Release);
+    });
+    Ok(())
+}
+
+fn claim_save(backend: &Backend) -> Result<(), RdbError> {
+    backend
+        .rdb_saving
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .map(|_| ())
+        .map_err(|_| RdbError::SaveInProgress)
+}
+
+// write to a temp file first, so a crash never leaves a half written dump behind. Every save
+// has a temp file of its own.
+fn save_to(snapshot: Vec<DbSnapshot>, path: &Path) -> Result<(), RdbError> {
+    static SAVES: AtomicU64 = AtomicU64::new(0);
+    let tmp = path.with_file_name(format!(
+        "temp-{}-{}.rdb",
+        std::process::id(),
+        SAVES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let write = || -> Result<(), RdbError> {
+        let file = File::create(&tmp)?;
+        let mut writer = writer::RdbWriter::new(BufWriter::new(file));
+        writer.write_snapshot(&snapshot)?;
+        writer.into_inner()?.sync_all()?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    std::fs::rename(&tmp, path)?;
+    info!("DB saved on disk: {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn test_save_in_progress() -> Result<(), RdbError> {
+        let dir = std::env::temp_dir().join(format!("simple-redis-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let backend = Backend::with_config(Config {
+            dir: dir.clone(),
+            ..Default::default()
+        });
+
+        backend.rdb_saving.store(true, Ordering::Release);
+        assert!(matches!(save(&backend), Err(RdbError::SaveInProgress)));
+        assert!(matches!(bgsave(&backend), Err(RdbError::SaveInProgress)));
+
+        backend.rdb_saving.store(false, Ordering::Release);
+        save(&backend)?;
+        assert!(!backend.rdb_saving.load(Ordering::Acquire));
+        assert!(backend.config.rdb_path().exists());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,510 @@
+use bytes::Bytes;
+use std::path::Path;
+
+use tracing::{info, warn};
+
+use crate::rdb::crc64::crc64;
+use crate::rdb::encoding::{decode_intset, decode_listpack, decode_ziplist};
+use crate::rdb::*;
+use crate::{now_millis, Backend, BulkString, RespFrame, SortedSet, Value};
+
+enum Length {
+    Len(u64),
+    Encoded(u8),
+}
+
+type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+
+enum RdbValue {
+    String(Vec<u8>),
+    Hash(Pairs),
+    List(Vec<Vec<u8>>),
+    Set(Vec<Vec<u8>>),
+    ZSet(Vec<(Vec<u8>, f64)>),
+}
+
+struct RdbReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RdbReader<'a> {
+    fn read_exact(&mut self, n: usize) -> Result<&'a [u8], RdbError> {
+        let data = self
+            .pos
+            .checked_add(n)
+            .and_then(|end| self.buf.get(self.pos..end))
+            .ok_or_else(|| RdbError::InvalidFormat("unexpected end of file".to_string()))?;
+        self.pos += n;
+        Ok(data)
+    }
+
+    // room for `len` elements read from the file, each takes at least a byte so a corrupt
+    // length never reserves more than what is left
+    fn capacity(&self, len: u64) -> usize {
+        len.min((self.buf.len() - self.pos) as u64) as usize
+    }
+
+    fn read_u8(&mut self) -> Result<u8, RdbError> {
+        Ok(self.read_exact(1)?[0])
+    }
+
+    fn read_length_encoding(&mut self) -> Result<Length, RdbError> {
+        let first = self.read_u8()?;
+        match first >> 6 {
+            0b00 => Ok(Length::Len((first & 0x3f) as u64)),
+            0b01 => {
+                let low = self.read_u8()? as u64;
+                Ok(Length::Len((((first & 0x3f) as u64) << 8) | low))
+            }
+            0b11 => Ok(Length::Encoded(first & 0x3f)),
+            _ => match first {
+                0x80 => {
+                    let data = self.read_exact(4)?;
+                    Ok(Length::Len(
+                        u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64,
+                    ))
+                }
+                0x81 => {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(self.read_exact(8)?);
+                    Ok(Length::Len(u64::from_be_bytes(bytes)))
+                }
+                _ => Err(RdbError::InvalidFormat(format!(
+                    "unknown length encoding {:#x}",
+                    first
+                ))),
+            },
+        }
+    }
+
+    fn read_length(&mut self) -> Result<u64, RdbError> {
+        match self.read_length_encoding()? {
+            Length::Len(len) => Ok(len),
+            Length::Encoded(_) => Err(RdbError::InvalidFormat(
+                "unexpected encoded length".to_string(),
+            )),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Vec<u8>, RdbError> {
+        match self.read_length_encoding()? {
+            Length::Len(len) => Ok(self.read_exact(len as usize)?.to_vec()),
+            Length::Encoded(RDB_ENC_INT8) => Ok((self.read_u8()? as i8).to_string().into_bytes()),
+            Length::Encoded(RDB_ENC_INT16) => {
+                let data = self.read_exact(2)?;
+                Ok(i16::from_le_bytes([data[0], data[1]])
+                    .to_string()
+                    .into_bytes())
+            }
+            Length::Encoded(RDB_ENC_INT32) => {
+                let data = self.read_exact(4)?;
+                Ok(i32::from_le_bytes([data[0], data[1], data[2], data[3]])
+                    .to_string()
+                    .into_bytes())
+            }
+            Length::Encoded(RDB_ENC_LZF) => {
+                let compressed = self.read_length()? as usize;
+                let len = self.read_length()? as usize;
+                let data = self.read_exact(compressed)?;
+                lzf::decompress(data, len)
+            }
+            Length::Encoded(enc) => Err(RdbError::InvalidFormat(format!(
+                "unknown string encoding {}",
+                enc
+            ))),
+        }
+    }
+
+    // scores of the old zset type: a length byte then ascii, 253 to 255 flag nan and infinities
+    fn read_string_score(&mut self) -> Result<f64, RdbError> {
+        match self.read_u8()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => parse_score(self.read_exact(len as usize)?),
+        }
+    }
+
+    fn read_value(&mut self, kind: u8) -> Result<RdbValue, RdbError> {
+        match kind {
+            RDB_TYPE_STRING => Ok(RdbValue::String(self.read_string()?)),
+            RDB_TYPE_HASH => {
+                let len = self.read_length()?;
+                let mut fields = Vec::with_capacity(self.capacity(len));
+                for _ in 0..len {
+                    fields.push((self.read_string()?, self.read_string()?));
+                }
+                Ok(RdbValue::Hash(fields))
+            }
+            RDB_TYPE_HASH_ZIPLIST => Ok(RdbValue::Hash(pairs(decode_ziplist(
+                &self.read_string()?,
+            )?)?)),
+            RDB_TYPE_HASH_LISTPACK => Ok(RdbValue::Hash(pairs(decode_listpack(
+                &self.read_string()?,
+            )?)?)),
+            RDB_TYPE_LIST => {
+                let len = self.read_length()?;
+                let mut values = Vec::with_capacity(self.capacity(len));
+                for _ in 0..len {
+                    values.push(self.read_string()?);
+                }
+                Ok(RdbValue::List(values))
+            }
+            RDB_TYPE_LIST_ZIPLIST => Ok(RdbValue::List(decode_ziplist(&self.read_string()?)?)),
+            RDB_TYPE_LIST_QUICKLIST => {
+                let nodes = self.read_length()?;
+                let mut values = Vec::new();
+                for _ in 0..nodes {
+                    values.extend(decode_ziplist(&self.read_string()?)?);
+                }
+                Ok(RdbValue::List(values))
+            }
+            RDB_TYPE_LIST_QUICKLIST_2 => {
+                let nodes = self.read_length()?;
+                let mut values = Vec::new();
+                for _ in 0..nodes {
+                    match self.read_length()? {
+                        QUICKLIST_NODE_PLAIN => values.push(self.read_string()?),
+                        QUICKLIST_NODE_PACKED => {
+                            values.extend(decode_listpack(&self.read_string()?)?)
+                        }
+                        container => {
+                            return Err(RdbError::InvalidFormat(format!(
+                                "unknown quicklist container {}",
+                                container
+                            )))
+                        }
+                    }
+                }
+                Ok(RdbValue::List(values))
+            }
+            RDB_TYPE_SET => {
+                let len = self.read_length()?;
+                let mut members = Vec::with_capacity(self.capacity(len));
+                for _ in 0..len {
+                    members.push(self.read_string()?);
+                }
+                Ok(RdbValue::Set(members))
+            }
+            RDB_TYPE_SET_INTSET => Ok(RdbValue::Set(decode_intset(&self.read_string()?)?)),
+            RDB_TYPE_SET_LISTPACK => Ok(RdbValue::Set(decode_listpack(&self.read_string()?)?)),
+            RDB_TYPE_ZSET | RDB_TYPE_ZSET_2 => {
+                let len = self.read_length()?;
+                let mut members = Vec::with_capacity(self.capacity(len));
+                for _ in 0..len {
+                    let member = self.read_string()?;
+                    let score = if kind == RDB_TYPE_ZSET_2 {
+                        let mut bytes = [0u8; 8];
+                        bytes.copy_from_slice(self.read_exact(8)?);
+                        f64::from_le_bytes(bytes)
+                    } else {
+                        self.read_string_score()?
+                    };
+                    members.push((member, score));
+                }
+                Ok(RdbValue::ZSet(members))
+            }
+            RDB_TYPE_ZSET_ZIPLIST => Ok(RdbValue::ZSet(scored(decode_ziplist(
+                &self.read_string()?,
+            )?)?)),
+            RDB_TYPE_ZSET_LISTPACK => Ok(RdbValue::ZSet(scored(decode_listpack(
+                &self.read_string()?,
+            )?)?)),
+            _ => Err(RdbError::UnsupportedType(kind)),
+        }
+    }
+}
+
+fn pairs(entries: Vec<Vec<u8>>) -> Result<Pairs, RdbError> {
+    if !entries.len().is_multiple_of(2) {
+        return Err(RdbError::InvalidFormat(
+            "odd number of pair entries".to_string(),
+        ));
+    }
+    let mut iter = entries.into_iter();
+    let mut pairs = Vec::with_capacity(iter.len() / 2);
+    while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+        pairs.push((k, v));
+    }
+    Ok(pairs)
+}
+
+fn parse_score(bytes: &[u8]) -> Result<f64, RdbError> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or_else(|| RdbError::InvalidFormat("invalid zset score".to_string()))
+}
+
+// member, score, member, score ... of the ziplist and listpack encodings
+fn scored(entries: Vec<Vec<u8>>) -> Result<Vec<(Vec<u8>, f64)>, RdbError> {
+    pairs(entries)?
+        .into_iter()
+        .map(|(member, score)| Ok((member, parse_score(&score)?)))
+        .collect()
+}
+
+/// Load the rdb file into `backend`, returns the number of keys loaded
+pub fn load(backend: &Backend, path: &Path) -> Result<usize, RdbError> {
+    let data = std::fs::read(path)?;
+    let loaded = load_from(backend, &data)?;
+    info!("DB loaded from disk: {} keys", loaded);
+    Ok(loaded)
+}
+
+pub(crate) fn load_from(backend: &Backend, data: &[u8]) -> Result<usize, RdbError> {
+    let mut reader = RdbReader { buf: data, pos: 0 };
+
+    if reader.read_exact(RDB_MAGIC.len())? != RDB_MAGIC {
+        return Err(RdbError::InvalidFormat("wrong signature".to_string()));
+    }
+    let version = std::str::from_utf8(reader.read_exact(4)?)
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .ok_or_else(|| RdbError::InvalidFormat("wrong version".to_string()))?;
+    if !(1..=12).contains(&version) {
+        return Err(RdbError::UnsupportedVersion(version));
+    }
+
+    let now = now_millis();
+    let mut db = 0;
+    let mut deadline = None;
+    let mut loaded = 0;
+
+    loop {
+        let opcode = reader.read_u8()?;
+        match opcode {
+            RDB_OPCODE_EOF => break,
+            RDB_OPCODE_AUX => {
+                reader.read_string()?;
+                reader.read_string()?;
+            }
+            RDB_OPCODE_FUNCTION2 => {
+                warn!("functions in rdb are not supported, skipped");
+                reader.read_string()?;
+            }
+            RDB_OPCODE_RESIZEDB => {
+                reader.read_length()?;
+                reader.read_length()?;
+            }
+            RDB_OPCODE_SELECTDB => db = reader.read_length()?,
+            RDB_OPCODE_EXPIRETIME_MS => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(reader.read_exact(8)?);
+                deadline = Some(i64::from_le_bytes(bytes));
+            }
+            RDB_OPCODE_EXPIRETIME => {
+                let data = reader.read_exact(4)?;
+                deadline =
+                    Some(i32::from_le_bytes([data[0], data[1], data[2], data[3]]) as i64 * 1000);
+            }
+            RDB_OPCODE_IDLE => {
+                reader.read_length()?;
+            }
+            RDB_OPCODE_FREQ => {
+                reader.read_u8()?;
+            }
+            kind => {
+                let key = reader.read_string()?;
+                let value = reader.read_value(kind)?;
+                let deadline = deadline.take();
+
+                if db >= backend.databases() as u64 {
+                    warn!("db {} is out of range, skip key", db);
+                    continue;
+                }
+                // keys already expired are not loaded
+                if deadline.is_some_and(|deadline| deadline <= now) {
+                    continue;
+                }
+                let key = Bytes::from(key);
+
+                let value = match value {
+                    RdbValue::String(value) => Value::String(value.into()),
+                    RdbValue::Hash(fields) => Value::Hash(
+                        fields
+                            .into_iter()
+                            .map(|(field, value)| {
+                                (
+                                    Bytes::from(field),
+                                    RespFrame::BulkString(BulkString::new(value)),
+                                )
+                            })
+                            .collect(),
+                    ),
+                    RdbValue::List(values) => Value::List(
+                        values
+                            .into_iter()
+                            .map(|v| RespFrame::BulkString(BulkString::new(v)))
+                            .collect(),
+                    ),
+                    RdbValue::Set(members) => Value::Set(members.into_iter().collect()),
+                    RdbValue::ZSet(members) => {
+                        let mut zset = SortedSet::default();
+                        for (member, score) in members {
+                            zset.insert(member, score);
+                        }
+                        Value::ZSet(zset)
+                    }
+                };
+                let target = backend.select(db as usize);
+                target.db().keyspace.insert(key.clone(), value);
+                if let Some(deadline) = deadline {
+                    target.db().expires.insert(key, deadline);
+                }
+                loaded += 1;
+            }
+        }
+    }
+
+    // rdb version 5 added the checksum, zero means it was disabled when saving
+    if version >= 5 {
+        let end = reader.pos;
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(reader.read_exact(8)?);
+        let expected = u64::from_le_bytes(bytes);
+        let actual = crc64(0, &data[..end]);
+        if expected != 0 && expected != actual {
+            return Err(RdbError::ChecksumMismatch { expected, actual });
+        }
+    }
+
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdb::writer::{snapshot, RdbWriter};
+    use crate::{ExpireCondition, ListEnd, RespArray, StringValue, ZRangeBy};
+
+    #[test]
+    fn test_rdb_round_trip() -> Result<(), RdbError> {
+        let backend = Backend::new();
+        backend.set(b"string", BulkString::new("value").into());
+        backend.set(b"number", StringValue::Int(42));
+        backend
+            .hset(
+                Bytes::from("hash"),
+                Bytes::from("field"),
+                BulkString::new("v").into(),
+            )
+            .unwrap();
+        backend.expire_at(b"hash", now_millis() + 60_000, ExpireCondition::Always);
+        backend
+            .list_push(
+                b"list",
+                ListEnd::Right,
+                vec![BulkString::new("a").into(), BulkString::new("b").into()],
+                false,
+            )
+            .unwrap();
+        backend.sadd(b"set", vec![b"m".to_vec()]).unwrap();
+        backend
+            .zadd(
+                b"zset",
+                vec![(1.5, b"a".to_vec()), (f64::INFINITY, b"b".to_vec())],
+                Default::default(),
+            )
+            .unwrap();
+        backend
+            .select(5)
+            .set(b"string", BulkString::new("db5").into());
+
+        let mut buf = Vec::new();
+        RdbWriter::new(&mut buf).write_snapshot(&snapshot(&backend))?;
+        assert!(buf.starts_with(b"REDIS0009"));
+
+        let restored = Backend::new();
+        assert_eq!(load_from(&restored, &buf)?, 7);
+        assert_eq!(
+            restored.select(5).get(b"string"),
+            Ok(Some(BulkString::new("db5").into()))
+        );
+        assert_eq!(restored.select(5).dbsize(), 1);
+        assert_eq!(
+            restored.get(b"string"),
+            Ok(Some(BulkString::new("value").into()))
+        );
+        assert_eq!(
+            restored.get(b"number"),
+            Ok(Some(BulkString::new("42").into()))
+        );
+        assert_eq!(
+            restored.hget(b"hash", b"field"),
+            Ok(Some(BulkString::new("v").into()))
+        );
+        assert_eq!(restored.expiry(b"hash"), backend.expiry(b"hash"));
+        assert_eq!(
+            restored.lrange(b"list", 0, -1).unwrap(),
+            vec![BulkString::new("a").into(), BulkString::new("b").into()]
+        );
+        assert_eq!(restored.smembers(b"set").unwrap(), vec![b"m".to_vec()]);
+        assert_eq!(
+            restored
+                .zrange(b"zset", &ZRangeBy::Rank(0, -1), false, None)
+                .unwrap(),
+            vec![(b"a".to_vec(), 1.5), (b"b".to_vec(), f64::INFINITY)]
+        );
+
+        // flip a byte in the payload to break the checksum
+        let len = buf.len();
+        buf[len - 12] ^= 0xff;
+        assert!(load_from(&Backend::new(), &buf).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_value() {
+        let backend = Backend::new();
+        backend
+            .hset(
+                Bytes::from("hash"),
+                Bytes::from("field"),
+                RespArray::new(vec![1.into()]).into(),
+            )
+            .unwrap();
+        let mut buf = Vec::new();
+        let ret = RdbWriter::new(&mut buf).write_snapshot(&snapshot(&backend));
+        assert!(matches!(ret, Err(RdbError::UnsupportedValue(_))));
+    }
+
+    // lengths close to u64::MAX fail the load instead of aborting the process
+    #[test]
+    fn test_corrupt_lengths() {
+        let huge = [0x81, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        for kind in [
+            RDB_TYPE_STRING,
+            RDB_TYPE_HASH,
+            RDB_TYPE_LIST,
+            RDB_TYPE_SET,
+            RDB_TYPE_ZSET_2,
+        ] {
+            let mut data = b"REDIS0009".to_vec();
+            data.extend_from_slice(&[RDB_OPCODE_SELECTDB, 0, kind, 1, b'k']);
+            data.extend_from_slice(&huge);
+            assert!(load_from(&Backend::new(), &data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_load_redis_dump() -> Result<(), RdbError> {
+        // `SET a 1` + `HSET h f v` saved by redis 7 without checksum
+        let mut data = b"REDIS0011".to_vec();
+        data.extend_from_slice(&[RDB_OPCODE_SELECTDB, 0, RDB_OPCODE_RESIZEDB, 2, 0]);
+        data.extend_from_slice(&[RDB_TYPE_STRING, 1, b'a', 0xc0, 1]);
+        data.extend_from_slice(&[RDB_TYPE_HASH_LISTPACK, 1, b'h', 13]);
+        data.extend_from_slice(&[13, 0, 0, 0, 2, 0, 0x81, b'f', 2, 0x81, b'v', 2, 0xff]);
+        data.extend_from_slice(&[RDB_OPCODE_EOF, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let backend = Backend::new();
+        assert_eq!(load_from(&backend, &data)?, 2);
+        assert_eq!(backend.get(b"a"), Ok(Some(BulkString::new("1").into())));
+        assert_eq!(
+            backend.hget(b"h", b"f"),
+            Ok(Some(BulkString::new("v").into()))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::rdb::crc64::crc64;
+use crate::rdb::*;
+use bytes::Bytes;
+
+use crate::{now_millis, Backend, RespFrame, StringValue, Value};
+
+pub(crate) struct RdbWriter<W: Write> {
+    inner: W,
+    crc: u64,
+}
+
+impl<W: Write> RdbWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        RdbWriter { inner, crc: 0 }
+    }
+
+    fn write_raw(&mut self, buf: &[u8]) -> Result<(), RdbError> {
+        self.crc = crc64(self.crc, buf);
+        self.inner.write_all(buf)?;
+        Ok(())
+    }
+
+    fn write_u8(&mut self, v: u8) -> Result<(), RdbError> {
+        self.write_raw(&[v])
+    }
+
+    fn write_length(&mut self, len: u64) -> Result<(), RdbError> {
+        if len < 1 << 6 {
+            self.write_u8(len as u8)
+        } else if len < 1 << 14 {
+            self.write_raw(&[0x40 | (len >> 8) as u8, len as u8])
+        } else if len <= u32::MAX as u64 {
+            self.write_u8(0x80)?;
+            self.write_raw(&(len as u32).to_be_bytes())
+        } else {
+            self.write_u8(0x81)?;
+            self.write_raw(&len.to_be_bytes())
+        }
+    }
+
+    fn write_string(&mut self, s: &[u8]) -> Result<(), RdbError> {
+        self.write_length(s.len() as u64)?;
+        self.write_raw(s)
+    }
+
+    // integers that fit 32 bits are stored in binary like redis does
+    fn write_int_string(&mut self, value: i64) -> Result<(), RdbError> {
+        let encoded = RDB_ENCVAL << 6;
+        if let Ok(value) = i8::try_from(value) {
+            self.write_u8(encoded | RDB_ENC_INT8)?;
+            self.write_raw(&value.to_le_bytes())
+        } else if let Ok(value) = i16::try_from(value) {
+            self.write_u8(encoded | RDB_ENC_INT16)?;
+            self.write_raw(&value.to_le_bytes())
+        } else if let Ok(value) = i32::try_from(value) {
+            self.write_u8(encoded | RDB_ENC_INT32)?;
+            self.write_raw(&value.to_le_bytes())
+        } else {
+            self.write_string(value.to_string().as_bytes())
+        }
+    }
+
+    fn write_aux(&mut self, key: &str, value: &str) -> Result<(), RdbError> {
+        self.write_u8(RDB_OPCODE_AUX)?;
+        self.write_string(key.as_bytes())?;
+        self.write_string(value.as_bytes())
+    }
+
+    fn write_key_header(
+        &mut self,
+        key: &[u8],
+        deadline: Option<i64>,
+        kind: u8,
+    ) -> Result<(), RdbError> {
+        if let Some(deadline) = deadline {
+            self.write_u8(RDB_OPCODE_EXPIRETIME_MS)?;
+            self.write_raw(&(deadline as u64).to_le_bytes())?;
+        }
+        self.write_u8(kind)?;
+        self.write_string(key)
+    }
+
+    fn write_db(&mut self, db: &DbSnapshot) -> Result<(), RdbError> {
+        self.write_u8(RDB_OPCODE_SELECTDB)?;
+        self.write_length(db.index as u64)?;
+        self.write_u8(RDB_OPCODE_RESIZEDB)?;
+        self.write_length(db.keys.len() as u64)?;
+        let expires = db.keys.iter().filter(|(_, _, deadline)| deadline.is_some());
+        self.write_length(expires.count() as u64)?;
+
+        for (key, value, deadline) in &db.keys {
+            match value {
+                Value::String(value) => {
+                    self.write_key_header(key, *deadline, RDB_TYPE_STRING)?;
+                    match value {
+                        StringValue::Int(value) => self.write_int_string(*value)?,
+                        StringValue::Raw(value) => self.write_string(value)?,
+                    }
+                }
+                Value::Hash(hash) => {
+                    self.write_key_header(key, *deadline, RDB_TYPE_HASH)?;
+                    self.write_length(hash.len() as u64)?;
+                    for (field, value) in hash {
+                        self.write_string(field)?;
+                        self.write_string(&frame_to_bytes(value)?)?;
+                    }
+                }
+                Value::List(list) => {
+                    self.write_key_header(key, *deadline, RDB_TYPE_LIST)?;
+                    self.write_length(list.len() as u64)?;
+                    for value in list {
+                        self.write_string(&frame_to_bytes(value)?)?;
+                    }
+                }
+                Value::Set(set) => {
+                    self.write_key_header(key, *deadline, RDB_TYPE_SET)?;
+                    self.write_length(set.len() as u64)?;
+                    for member in set {
+                        self.write_string(member)?;
+                    }
+                }
+                Value::ZSet(zset) => {
+                    // scores as binary doubles
+                    self.write_key_header(key, *deadline, RDB_TYPE_ZSET_2)?;
+                    self.write_length(zset.len() as u64)?;
+                    for (member, score) in zset.iter() {
+                        self.write_string(member)?;
+                        self.write_raw(&score.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write_snapshot(&mut self, snapshot: &[DbSnapshot]) -> Result<(), RdbError> {
+        self.write_raw(RDB_MAGIC)?;
+        self.write_raw(format!("{:04}", RDB_VERSION).as_bytes())?;
+        self.write_aux("redis-ver", "7.0.0")?;
+        self.write_aux("redis-bits", "64")?;
+        self.write_aux("ctime", &(now_millis() / 1000).to_string())?;
+
+        for db in snapshot {
+            self.write_db(db)?;
+        }
+
+        self.write_u8(RDB_OPCODE_EOF)?;
+        // the checksum itself is not part of the checksum
+        let crc = self.crc;
+        self.inner.write_all(&crc.to_le_bytes())?;
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+/// The keys of one database with their deadlines
+pub(crate) struct DbSnapshot {
+    index: usize,
+    keys: Vec<(Bytes, Value, Option<i64>)>,
+}
+
+/// Copy the databases to dump, one shard at a time under its read lock. The file is written
+/// from the copy afterwards, without holding any lock of the keyspace.
+pub(crate) fn snapshot(backend: &Backend) -> Vec<DbSnapshot> {
+    let now = now_millis();
+    (0..backend.databases())
+        .filter_map(|index| {
+            let db = backend.select(index);
+            let keys = db
+                .db()
+                .keyspace
+                .iter()
+                .filter_map(|entry| {
+                    let deadline = db.db().expires.get(entry.key()).map(|v| *v.value());
+                    if deadline.is_some_and(|deadline| deadline <= now) {
+                        return None;
+                    }
+                    Some((entry.key().clone(), entry.value().clone(), deadline))
+                })
+                .collect::<Vec<_>>();
+            // empty databases are left out
+            (!keys.is_empty()).then_some(DbSnapshot { index, keys })
+        })
+        .collect()
+}
+
+impl RdbWriter<BufWriter<File>> {
+    pub(crate) fn into_inner(self) -> Result<File, RdbError> {
+        self.inner
+            .into_inner()
+            .map_err(|e| RdbError::Io(e.into_error()))
+    }
+}
+
+// rdb strings are plain bytes, values of other frame types are stored with their textual form
+fn frame_to_bytes(frame: &RespFrame) -> Result<Vec<u8>, RdbError> {
+    match frame {
+        RespFrame::BulkString(s) if !s.1 => Ok(s.0.to_vec()),
+        RespFrame::SimpleString(s) => Ok(s.0.clone().into_bytes()),
+        RespFrame::Integer(v) => Ok(v.to_string().into_bytes()),
+        RespFrame::Double(v) => Ok(v.to_string().into_bytes()),
+        frame => Err(RdbError::UnsupportedValue(format!("{:?}", frame))),
+    }
+}
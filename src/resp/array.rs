@@ -1,29 +1,24 @@
+use std::fmt::Write;
 use std::ops::Deref;
 
 use bytes::{Buf, BytesMut};
 
-use crate::resp::{
-    calc_total_length, parse_length, parse_length_for_nullable, BUF_CAP, CRLF_LENGTH,
-};
+use crate::resp::{calc_total_length, parse_length, parse_length_for_nullable, CRLF_LENGTH};
 use crate::{RespDecode, RespEncode, RespError, RespFrame};
 
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct RespArray(pub(crate) Vec<RespFrame>, pub(crate) bool);
 
 impl RespEncode for RespArray {
-    fn encode(self) -> Vec<u8> {
+    fn encode_to(self, buf: &mut BytesMut) {
         if self.1 {
-            b"*-1\r\n".to_vec()
+            buf.extend_from_slice(b"*-1\r\n");
         } else {
-            let mut buf = Vec::with_capacity(BUF_CAP);
-
-            buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
+            let _ = write!(buf, "*{}\r\n", self.0.len());
 
             for frame in self.0 {
-                buf.extend_from_slice(&frame.encode());
+                frame.encode_to(buf);
             }
-
-            buf
         }
     }
 }
@@ -37,7 +32,7 @@ impl RespDecode for RespArray {
         // "*-1\r\n 兼容 空数组
         let (end, len) = parse_length_for_nullable(buf.as_ref(), Self::PREFIX)?;
         if len == -1 {
-            // let frames = vec![b"*-1\r\n"];
+            buf.advance(end + CRLF_LENGTH);
             Ok(RespArray::nill_new())
         } else {
             let total = calc_total_length(buf.as_ref(), end, len as usize, Self::PREFIX)?;
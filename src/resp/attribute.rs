@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::{Deref, DerefMut};
+
+use bytes::{Buf, BytesMut};
+
+use crate::resp::{calc_total_length, parse_length, CRLF_LENGTH};
+use crate::{RespDecode, RespEncode, RespError, RespFrame, SimpleString};
+
+// |<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>
+// auxiliary data like a map, sent right before the reply it describes
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RespAttribute(pub(crate) BTreeMap<String, RespFrame>);
+
+impl RespEncode for RespAttribute {
+    fn encode_to(self, buf: &mut BytesMut) {
+        let _ = write!(buf, "|{}\r\n", self.len());
+
+        for (key, value) in self.0 {
+            SimpleString::new(key).encode_to(buf);
+            value.encode_to(buf);
+        }
+    }
+}
+
+impl RespDecode for RespAttribute {
+    const PREFIX: &'static str = "|";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+        buf.advance(end + CRLF_LENGTH);
+
+        let mut attributes = RespAttribute::new();
+        for _ in 0..len {
+            let key = SimpleString::decode(buf)?;
+            let value = RespFrame::decode(buf)?;
+            attributes.insert(key.0, value);
+        }
+        Ok(attributes)
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+impl Deref for RespAttribute {
+    type Target = BTreeMap<String, RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for RespAttribute {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl RespAttribute {
+    pub fn new() -> Self {
+        RespAttribute(BTreeMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_attribute_encode_decode() -> Result<()> {
+        let mut attribute = RespAttribute::new();
+        attribute.insert("ttl".to_string(), RespFrame::Integer(3600));
+        attribute.insert("popularity".to_string(), 0.5.into());
+        let encoded = attribute.clone().encode();
+        assert_eq!(encoded, b"|2\r\n+popularity\r\n,+0.5\r\n+ttl\r\n:3600\r\n");
+
+        let mut buf = BytesMut::from(&encoded[..encoded.len() - 1]);
+        assert_eq!(RespAttribute::decode(&mut buf), Err(RespError::NotComplete));
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(RespAttribute::expect_length(&buf)?, encoded.len());
+        assert_eq!(RespAttribute::decode(&mut buf)?, attribute);
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use bytes::BytesMut;
+
+use crate::resp::{extract_simple_frame_data, CRLF_LENGTH};
+use crate::{RespDecode, RespEncode, RespError};
+
+// (<big number>\r\n
+// an integer outside of the signed 64 bit range, kept as its decimal digits
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BigNumber(pub(crate) String);
+
+impl BigNumber {
+    pub fn new(s: impl Into<String>) -> Self {
+        BigNumber(s.into())
+    }
+}
+
+impl RespEncode for BigNumber {
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(self.0.len() + 3);
+        buf.extend_from_slice(b"(");
+        buf.extend_from_slice(self.0.as_bytes());
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+impl RespDecode for BigNumber {
+    const PREFIX: &'static str = "(";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
+        let digits = &buf[Self::PREFIX.len()..end];
+        let unsigned = match digits.first() {
+            Some(b'+') | Some(b'-') => &digits[1..],
+            _ => digits,
+        };
+        if unsigned.is_empty() || !unsigned.iter().all(u8::is_ascii_digit) {
+            return Err(RespError::InvalidFrameType(format!(
+                "invalid big number: {:?}",
+                String::from_utf8_lossy(digits)
+            )));
+        }
+
+        let data = buf.split_to(end + CRLF_LENGTH);
+        let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
+        Ok(BigNumber(s.into()))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
+        Ok(end + CRLF_LENGTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_big_number_encode_decode() -> Result<()> {
+        let frame = BigNumber::new("-3492890328409238509324850943850943825024385");
+        let encoded = frame.clone().encode();
+        assert_eq!(
+            encoded,
+            b"(-3492890328409238509324850943850943825024385\r\n"
+        );
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(BigNumber::expect_length(&buf)?, encoded.len());
+        assert_eq!(BigNumber::decode(&mut buf)?, frame);
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("(12a\r\n");
+        assert!(BigNumber::decode(&mut buf).is_err());
+        Ok(())
+    }
+}
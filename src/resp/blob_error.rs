@@ -0,0 +1,79 @@
+use std::fmt::Write;
+use std::ops::Deref;
+
+use bytes::{Buf, BytesMut};
+
+use crate::resp::{parse_length, CRLF_LENGTH};
+use crate::{RespDecode, RespEncode, RespError};
+
+// !<length>\r\n<error>\r\n
+// an error that may hold any byte, new lines included
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobError(pub(crate) Vec<u8>);
+
+impl BlobError {
+    pub fn new(s: impl Into<Vec<u8>>) -> Self {
+        BlobError(s.into())
+    }
+}
+
+impl RespEncode for BlobError {
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(self.0.len() + 16);
+        let _ = write!(buf, "!{}\r\n", self.0.len());
+        buf.extend_from_slice(&self.0);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+impl RespDecode for BlobError {
+    const PREFIX: &'static str = "!";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        if buf.len() < end + CRLF_LENGTH + len + CRLF_LENGTH {
+            return Err(RespError::NotComplete);
+        }
+
+        buf.advance(end + CRLF_LENGTH);
+        let data = buf.split_to(len + CRLF_LENGTH);
+        Ok(BlobError(data[..len].to_vec()))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        Ok(end + CRLF_LENGTH + len + CRLF_LENGTH)
+    }
+}
+
+impl Deref for BlobError {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_blob_error_encode_decode() -> Result<()> {
+        let frame = BlobError::new("SYNTAX invalid\r\nsyntax");
+        let encoded = frame.clone().encode();
+        assert_eq!(encoded, b"!22\r\nSYNTAX invalid\r\nsyntax\r\n");
+
+        let mut buf = BytesMut::from(&encoded[..20]);
+        assert_eq!(BlobError::decode(&mut buf), Err(RespError::NotComplete));
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(BlobError::expect_length(&buf)?, encoded.len());
+        assert_eq!(BlobError::decode(&mut buf)?, frame);
+        assert!(buf.is_empty());
+        Ok(())
+    }
+}
@@ -23,7 +23,7 @@ impl RespDecode for bool {
 }
 
 impl RespEncode for bool {
-    fn encode(self) -> Vec<u8> {
-        format!("#{}\r\n", if self { "t" } else { "f" }).into_bytes()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(if self { b"#t\r\n" } else { b"#f\r\n" });
     }
 }
@@ -1,30 +1,29 @@
+use std::fmt::Write;
 use std::ops::Deref;
 use std::str::FromStr;
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 
 use crate::resp::{parse_length, parse_length_for_nullable, CRLF_LENGTH};
 use crate::{RespDecode, RespEncode, RespError};
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct BulkString(pub(crate) Vec<u8>, pub(crate) bool);
+pub struct BulkString(pub(crate) Bytes, pub(crate) bool);
 impl RespEncode for BulkString {
-    fn encode(self) -> Vec<u8> {
+    fn encode_to(self, buf: &mut BytesMut) {
         //$<length>\r\n<data>\r\n
 
         if self.1 {
             //nill bulk string
-            b"$-1\r\n".to_vec()
+            buf.extend_from_slice(b"$-1\r\n");
         } else {
-            let mut buf = Vec::with_capacity(self.0.len() + 16);
+            buf.reserve(self.0.len() + 16);
 
-            buf.extend_from_slice(&format!("${}\r\n", self.len()).into_bytes());
+            let _ = write!(buf, "${}\r\n", self.len());
 
             buf.extend_from_slice(&self);
 
             buf.extend_from_slice(b"\r\n");
-
-            buf
         }
     }
 }
@@ -38,6 +37,7 @@ impl RespDecode for BulkString {
         let remained = &buf[end + CRLF_LENGTH..];
 
         if len == -1 {
+            buf.advance(end + CRLF_LENGTH);
             Ok(BulkString::nill_new())
         } else {
             if remained.len() < len as usize + CRLF_LENGTH {
@@ -46,8 +46,10 @@ impl RespDecode for BulkString {
 
             buf.advance(end + CRLF_LENGTH);
 
-            let data = buf.split_to(len as usize + CRLF_LENGTH);
-            Ok(BulkString::new(data[..len as usize].to_vec()))
+            // the value shares the read buffer instead of being copied out of it
+            let data = buf.split_to(len as usize).freeze();
+            buf.advance(CRLF_LENGTH);
+            Ok(BulkString::new(data))
         }
     }
 
@@ -58,12 +60,12 @@ impl RespDecode for BulkString {
 }
 
 impl BulkString {
-    pub fn new(s: impl Into<Vec<u8>>) -> Self {
+    pub fn new(s: impl Into<Bytes>) -> Self {
         BulkString(s.into(), false)
     }
     //nill 类型类型
     pub fn nill_new() -> Self {
-        BulkString(vec![], true)
+        BulkString(Bytes::new(), true)
     }
 }
 
@@ -74,7 +76,7 @@ impl AsRef<[u8]> for BulkString {
 }
 
 impl Deref for BulkString {
-    type Target = Vec<u8>;
+    type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -85,7 +87,7 @@ impl FromStr for BulkString {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(BulkString::new(s.as_bytes().to_vec()))
+        Ok(BulkString::new(s.to_string()))
     }
 }
 
@@ -95,8 +97,38 @@ impl From<String> for BulkString {
     }
 }
 
+impl From<Bytes> for BulkString {
+    fn from(value: Bytes) -> Self {
+        BulkString::new(value)
+    }
+}
+
 impl<const N: usize> From<&[u8; N]> for BulkString {
     fn from(value: &[u8; N]) -> Self {
         BulkString::new(value.to_vec())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_bulk_string_decode_shares_buffer() -> Result<()> {
+        let mut buf = BytesMut::from(&b"$5\r\nhello\r\n$-1\r\n"[..]);
+        let start = buf.as_ptr() as usize;
+
+        let frame = BulkString::decode(&mut buf)?;
+        assert_eq!(frame, BulkString::new("hello"));
+        assert_eq!(frame.0.as_ptr() as usize, start + 4);
+        assert_eq!(BulkString::decode(&mut buf)?, BulkString::nill_new());
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("+OK\r\n");
+        BulkString::new("hello").encode_to(&mut buf);
+        assert_eq!(&buf[..], b"+OK\r\n$5\r\nhello\r\n");
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::Write;
+
 use bytes::BytesMut;
 
 use crate::resp::{extract_simple_frame_data, CRLF_LENGTH};
@@ -5,19 +7,14 @@ use crate::{RespDecode, RespEncode, RespError};
 
 //,[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n
 impl RespEncode for f64 {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32);
-
-        let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
-            format!(",{:+e}\r\n", self)
+    fn encode_to(self, buf: &mut BytesMut) {
+        let _ = if self.abs() > 1e+8 || self.abs() < 1e-8 {
+            write!(buf, ",{:+e}\r\n", self)
         } else {
             let sign = if self < 0.0 { "" } else { "+" };
 
-            format!(",{}{}\r\n", sign, self)
+            write!(buf, ",{}{}\r\n", sign, self)
         };
-
-        buf.extend_from_slice(&ret.into_bytes());
-        buf
     }
 }
 
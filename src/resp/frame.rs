@@ -3,12 +3,17 @@ use enum_dispatch::enum_dispatch;
 use log::{info, warn};
 
 use crate::resp::array::RespArray;
+use crate::resp::attribute::RespAttribute;
+use crate::resp::big_number::BigNumber;
+use crate::resp::blob_error::BlobError;
 use crate::resp::bulk_string::BulkString;
 use crate::resp::map::RespMap;
 use crate::resp::null::RespNull;
+use crate::resp::push::RespPush;
 use crate::resp::set::RespSet;
 use crate::resp::simple_error::SimpleError;
 use crate::resp::simple_string::SimpleString;
+use crate::resp::verbatim_string::VerbatimString;
 use crate::{RespDecode, RespError};
 
 #[enum_dispatch(RespEncode)]
@@ -24,6 +29,11 @@ pub enum RespFrame {
     Bool(bool),
     Integer(i64),
     Set(RespSet),
+    Push(RespPush),
+    BigNumber(BigNumber),
+    VerbatimString(VerbatimString),
+    BlobError(BlobError),
+    Attribute(RespAttribute),
 }
 
 impl RespDecode for RespFrame {
@@ -89,6 +99,26 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'(') => {
+                let frame = BigNumber::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'=') => {
+                let frame = VerbatimString::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'!') => {
+                let frame = BlobError::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'|') => {
+                let frame = RespAttribute::decode(buf)?;
+                Ok(frame.into())
+            }
 
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
@@ -111,6 +141,11 @@ impl RespDecode for RespFrame {
             Some(b'#') => bool::expect_length(buf),
             Some(b',') => f64::expect_length(buf),
             Some(b'_') => RespNull::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
+            Some(b'(') => BigNumber::expect_length(buf),
+            Some(b'=') => VerbatimString::expect_length(buf),
+            Some(b'!') => BlobError::expect_length(buf),
+            Some(b'|') => RespAttribute::expect_length(buf),
 
             _ => {
                 warn!("expect_length: may be not complete: {:?}", buf);
@@ -120,3 +155,127 @@ impl RespDecode for RespFrame {
         }
     }
 }
+
+impl RespFrame {
+    /// Replace the frames RESP2 lacks with the closest RESP2 frame: sets and push frames
+    /// become arrays, maps flat arrays of keys and values, a double a bulk string,
+    /// a boolean an integer and null the null bulk string. Big numbers and verbatim strings
+    /// become bulk strings and a blob error a simple error. Attributes have no RESP2
+    /// counterpart, the encoder leaves them out.
+    pub fn into_resp2(self) -> RespFrame {
+        let downgrade = |frames: Vec<RespFrame>| {
+            frames
+                .into_iter()
+                .map(RespFrame::into_resp2)
+                .collect::<Vec<RespFrame>>()
+        };
+        match self {
+            RespFrame::Set(set) => RespArray::new(downgrade(set.0)).into(),
+            RespFrame::Push(push) => RespArray::new(downgrade(push.0)).into(),
+            RespFrame::Double(value) => BulkString::new(value.to_string()).into(),
+            RespFrame::Array(array) if !array.1 => RespArray::new(downgrade(array.0)).into(),
+            RespFrame::Map(map) => {
+                let mut frames = Vec::with_capacity(map.len() * 2);
+                for (key, value) in map.0 {
+                    frames.push(BulkString::new(key).into());
+                    frames.push(value.into_resp2());
+                }
+                RespArray::new(frames).into()
+            }
+            RespFrame::Bool(value) => RespFrame::Integer(value as i64),
+            RespFrame::Null(_) => BulkString::nill_new().into(),
+            RespFrame::BigNumber(number) => BulkString::new(number.0).into(),
+            RespFrame::VerbatimString(string) => BulkString::new(string.data).into(),
+            RespFrame::BlobError(error) => {
+                // a simple error ends at the first new line
+                let error = String::from_utf8_lossy(&error).replace(['\r', '\n'], " ");
+                SimpleError::new(error).into()
+            }
+            frame => frame,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::RespEncode;
+
+    #[test]
+    fn test_into_resp2() {
+        let mut map = RespMap::new();
+        map.insert("proto".to_string(), RespFrame::Integer(2));
+        map.insert("flag".to_string(), true.into());
+        map.insert("score".to_string(), 1.5.into());
+        map.insert("missing".to_string(), RespFrame::Null(RespNull));
+        let frame: RespFrame = map.into();
+        assert_eq!(
+            frame.into_resp2().encode(),
+            b"*8\r\n$4\r\nflag\r\n:1\r\n$7\r\nmissing\r\n$-1\r\n$5\r\nproto\r\n:2\r\n$5\r\nscore\r\n$3\r\n1.5\r\n"
+        );
+
+        let frame: RespFrame = RespArray::new(vec![
+            false.into(),
+            RespFrame::Null(RespNull),
+            BigNumber::new("123456789012345678901234567890").into(),
+            VerbatimString::text("a\r\nb").into(),
+            BlobError::new("ERR bad\r\nthing").into(),
+        ])
+        .into();
+        assert_eq!(
+            frame.into_resp2(),
+            RespArray::new(vec![
+                RespFrame::Integer(0),
+                BulkString::nill_new().into(),
+                BulkString::new("123456789012345678901234567890").into(),
+                BulkString::new("a\r\nb").into(),
+                SimpleError::new("ERR bad  thing").into(),
+            ])
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_resp3_frames_decode() -> Result<()> {
+        let mut attribute = RespAttribute::new();
+        attribute.insert("key-popularity".to_string(), 0.1923.into());
+        let frames: Vec<RespFrame> = vec![
+            BigNumber::new("3492890328409238509324850943850943825024385").into(),
+            VerbatimString::text("Some string").into(),
+            BlobError::new("SYNTAX invalid syntax").into(),
+            RespPush::new(vec![
+                BulkString::new("message").into(),
+                BulkString::new("ch").into(),
+                BulkString::new("hi").into(),
+            ])
+            .into(),
+            attribute.into(),
+            // nested in an aggregate, which needs their length up front
+            RespArray::new(vec![
+                BigNumber::new("-1").into(),
+                VerbatimString::new(*b"mkd", "# title").into(),
+                BlobError::new("ERR x").into(),
+            ])
+            .into(),
+        ];
+
+        let mut buf = BytesMut::new();
+        for frame in &frames {
+            buf.extend_from_slice(&frame.clone().encode());
+        }
+        for frame in frames {
+            assert_eq!(
+                RespFrame::expect_length(&buf)?,
+                frame.clone().encode().len()
+            );
+            assert_eq!(RespFrame::decode(&mut buf)?, frame);
+        }
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("*2\r\n(1\r\n=15\r\ntxt:Some");
+        assert_eq!(RespFrame::decode(&mut buf), Err(RespError::NotComplete));
+        Ok(())
+    }
+}
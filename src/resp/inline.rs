@@ -0,0 +1,188 @@
+use bytes::BytesMut;
+
+use crate::{BulkString, RespArray, RespError, RespFrame};
+
+// PING\r\n
+// SET key "hello world"\r\n
+// a command typed by hand: space separated arguments on one line, as sent by telnet or nc
+
+/// Whether `buf` starts an inline command rather than a RESP frame
+pub fn is_inline(buf: &[u8]) -> bool {
+    !matches!(
+        buf.first(),
+        None | Some(
+            b'+' | b'-'
+                | b':'
+                | b'$'
+                | b'*'
+                | b'_'
+                | b'#'
+                | b','
+                | b'%'
+                | b'~'
+                | b'>'
+                | b'('
+                | b'='
+                | b'!'
+                | b'|'
+        )
+    )
+}
+
+impl RespArray {
+    /// Decode the next inline command into the array of bulk strings a client would send.
+    /// Blank lines are skipped, NotComplete is returned when a RESP frame follows them so the
+    /// caller decodes it as such.
+    pub fn decode_inline(buf: &mut BytesMut) -> Result<Self, RespError> {
+        loop {
+            let end = buf
+                .iter()
+                .position(|&b| b == b'\n')
+                .ok_or(RespError::NotComplete)?;
+            let line = buf.split_to(end + 1);
+            let line = line.strip_suffix(b"\n").unwrap_or(&line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            let args = split_args(line)?;
+            if !args.is_empty() {
+                return Ok(RespArray::new(
+                    args.into_iter()
+                        .map(|arg| BulkString::new(arg).into())
+                        .collect::<Vec<RespFrame>>(),
+                ));
+            }
+            if !is_inline(buf) {
+                return Err(RespError::NotComplete);
+            }
+        }
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|v| v as u8)
+}
+
+// split like redis-cli does: "double quotes" understand \n \r \t \b \a \\ \" and \xHH,
+// 'single quotes' only \', a closing quote has to end the argument
+fn split_args(line: &[u8]) -> Result<Vec<Vec<u8>>, RespError> {
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = Vec::new();
+        let quote = match line[i] {
+            q @ (b'"' | b'\'') => {
+                i += 1;
+                Some(q)
+            }
+            _ => None,
+        };
+        loop {
+            let Some(&c) = line.get(i) else {
+                if quote.is_some() {
+                    return Err(RespError::UnbalancedQuotes);
+                }
+                break;
+            };
+            match quote {
+                None if c.is_ascii_whitespace() => break,
+                None => arg.push(c),
+                Some(q) if c == q => {
+                    // the closing quote must be followed by a space or nothing
+                    if line.get(i + 1).is_some_and(|c| !c.is_ascii_whitespace()) {
+                        return Err(RespError::UnbalancedQuotes);
+                    }
+                    i += 1;
+                    break;
+                }
+                Some(b'"') if c == b'\\' && i + 1 < line.len() => {
+                    i += 1;
+                    let escaped = match line[i] {
+                        b'x' if i + 2 < line.len() => {
+                            match (hex_digit(line[i + 1]), hex_digit(line[i + 2])) {
+                                (Some(high), Some(low)) => {
+                                    i += 2;
+                                    high * 16 + low
+                                }
+                                _ => b'x',
+                            }
+                        }
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'b' => 0x08,
+                        b'a' => 0x07,
+                        c => c,
+                    };
+                    arg.push(escaped);
+                }
+                Some(b'\'') if c == b'\\' && line.get(i + 1) == Some(&b'\'') => {
+                    i += 1;
+                    arg.push(b'\'');
+                }
+                Some(_) => arg.push(c),
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    fn array(args: &[&[u8]]) -> RespArray {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::new(arg.to_vec()).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    #[test]
+    fn test_decode_inline() -> Result<()> {
+        assert!(is_inline(b"PING\r\n"));
+        assert!(!is_inline(b"*1\r\n$4\r\nPING\r\n"));
+
+        let mut buf = BytesMut::from("\r\nPING\r\nset  key \"a \\\"b\\\"\\x41\\n\" 'it\\'s'\nget");
+        assert_eq!(RespArray::decode_inline(&mut buf)?, array(&[b"PING"]));
+        assert_eq!(
+            RespArray::decode_inline(&mut buf)?,
+            array(&[b"set", b"key", b"a \"b\"A\n", b"it's"])
+        );
+        assert_eq!(
+            RespArray::decode_inline(&mut buf),
+            Err(RespError::NotComplete)
+        );
+        assert_eq!(&buf[..], b"get");
+
+        // a stray CRLF before a RESP frame
+        let mut buf = BytesMut::from("\r\n*1\r\n$4\r\nPING\r\n");
+        assert_eq!(
+            RespArray::decode_inline(&mut buf),
+            Err(RespError::NotComplete)
+        );
+        assert_eq!(&buf[..], b"*1\r\n$4\r\nPING\r\n");
+
+        let mut buf = BytesMut::from("set key \"value\r\n");
+        assert_eq!(
+            RespArray::decode_inline(&mut buf),
+            Err(RespError::UnbalancedQuotes)
+        );
+        let mut buf = BytesMut::from("set key 'a'b\r\n");
+        assert_eq!(
+            RespArray::decode_inline(&mut buf),
+            Err(RespError::UnbalancedQuotes)
+        );
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
+use std::fmt::Write;
+
 use bytes::BytesMut;
 
 use crate::resp::{extract_simple_frame_data, CRLF_LENGTH};
 use crate::{RespDecode, RespEncode, RespError};
 
 impl RespEncode for i64 {
-    fn encode(self) -> Vec<u8> {
-        let sign = if self < 0 { "-" } else { "+" };
-        format!("{}{}\r\n", sign, self.abs()).into_bytes()
+    fn encode_to(self, buf: &mut BytesMut) {
+        let _ = write!(buf, ":{}\r\n", self);
     }
 }
 
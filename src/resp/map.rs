@@ -1,9 +1,10 @@
 use std::collections::BTreeMap;
+use std::fmt::Write;
 use std::ops::{Deref, DerefMut};
 
 use bytes::{Buf, BytesMut};
 
-use crate::resp::{calc_total_length, parse_length, BUF_CAP, CRLF_LENGTH};
+use crate::resp::{calc_total_length, parse_length, CRLF_LENGTH};
 use crate::{RespDecode, RespEncode, RespError, RespFrame, SimpleString};
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -12,17 +13,13 @@ pub struct RespMap(pub(crate) BTreeMap<String, RespFrame>);
 //%<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>
 
 impl RespEncode for RespMap {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-
-        buf.extend_from_slice(&format!("%{}\r\n", self.len()).into_bytes());
+    fn encode_to(self, buf: &mut BytesMut) {
+        let _ = write!(buf, "%{}\r\n", self.len());
 
         for (key, value) in self.0 {
-            buf.extend_from_slice(&SimpleString::new(key).encode());
-            buf.extend_from_slice(&value.encode())
+            SimpleString::new(key).encode_to(buf);
+            value.encode_to(buf);
         }
-
-        buf
     }
 }
 
@@ -5,28 +5,54 @@ use thiserror::Error;
 pub use frame::*;
 
 pub use self::{
-    array::*, bulk_string::*, map::*, null::RespNull, set::*, simple_error::*, simple_string::*,
+    array::*, attribute::*, big_number::*, blob_error::*, bulk_string::*, inline::*, map::*,
+    null::RespNull, parser::*, push::*, set::*, simple_error::*, simple_string::*,
+    verbatim_string::*,
 };
 
 mod array;
+mod attribute;
+mod big_number;
+mod blob_error;
 mod bool;
 mod bulk_string;
 mod double;
 mod frame;
+mod inline;
 mod integer;
 mod map;
 mod null;
+mod parser;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
+mod verbatim_string;
 
-const BUF_CAP: usize = 4096;
 const CRLF: &[u8] = b"\r\n";
 const CRLF_LENGTH: usize = CRLF.len();
 
+/// RESP version spoken on a connection, RESP2 unless the client negotiated RESP3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RespProtocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
 #[enum_dispatch]
 pub trait RespEncode {
-    fn encode(self) -> Vec<u8>;
+    /// Append the encoded frame to `buf`, nested frames are written in place
+    fn encode_to(self, buf: &mut BytesMut);
+
+    fn encode(self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        let mut buf = BytesMut::new();
+        self.encode_to(&mut buf);
+        buf.to_vec()
+    }
 }
 
 pub trait RespDecode: Sized {
@@ -49,6 +75,10 @@ pub enum RespError {
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("Parse float error: {0}")]
     ParseFloatError(#[from] std::num::ParseFloatError),
+    #[error("Protocol error: unbalanced quotes in request")]
+    UnbalancedQuotes,
+    #[error("Protocol error: {0}")]
+    Protocol(String),
 }
 
 fn extract_fixed_data(
@@ -122,16 +152,19 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
     let mut data = &buf[total..];
 
     match prefix {
-        "*" | "~" => {
+        "*" | "~" | ">" => {
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
+                if len > data.len() {
+                    return Err(RespError::NotComplete);
+                }
 
                 data = &data[len..];
                 total += len;
             }
             Ok(total)
         }
-        "%" => {
+        "%" | "|" => {
             for _ in 0..len {
                 let len = SimpleString::expect_length(data)?;
 
@@ -139,6 +172,9 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
                 total += len;
 
                 let len = RespFrame::expect_length(data)?;
+                if len > data.len() {
+                    return Err(RespError::NotComplete);
+                }
                 data = &data[len..];
                 total += len;
             }
@@ -7,8 +7,8 @@ use crate::{RespDecode, RespEncode, RespError};
 pub struct RespNull;
 
 impl RespEncode for RespNull {
-    fn encode(self) -> Vec<u8> {
-        b"_\r\n".to_vec()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.extend_from_slice(b"_\r\n");
     }
 }
 
@@ -0,0 +1,494 @@
+use std::collections::BTreeMap;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::resp::{CRLF, CRLF_LENGTH};
+use crate::{
+    BigNumber, BlobError, BulkString, RespArray, RespAttribute, RespError, RespFrame, RespMap,
+    RespNull, RespPush, RespSet, SimpleError, SimpleString, VerbatimString,
+};
+
+// the length of "txt:" at the start of a verbatim string
+const FORMAT_LENGTH: usize = 4;
+
+/// Bounds on what a peer may announce, checked before anything is buffered for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolLimits {
+    /// largest bulk string, proto-max-bulk-len in redis
+    pub max_bulk_len: usize,
+    /// most elements in one aggregate
+    pub max_multibulk_len: usize,
+    /// most aggregates nested in each other
+    pub max_nesting_depth: usize,
+}
+
+impl Default for ProtocolLimits {
+    fn default() -> Self {
+        ProtocolLimits {
+            max_bulk_len: 512 * 1024 * 1024,
+            max_multibulk_len: 1024 * 1024,
+            max_nesting_depth: 32,
+        }
+    }
+}
+
+/// Incremental RESP decoder for a stream that arrives in pieces.
+///
+/// Every complete line and bulk payload is consumed from the buffer as soon as it is read, and
+/// the aggregates still waiting for elements are kept here until the next call, so each byte is
+/// looked at once however the frame is split across reads.
+#[derive(Debug, Default)]
+pub struct RespParser {
+    limits: ProtocolLimits,
+    // aggregates whose elements are still arriving, the innermost last
+    stack: Vec<Aggregate>,
+    // prefix and length of a bulk like frame whose payload has not fully arrived
+    payload: Option<(u8, usize)>,
+    // bytes at the start of the buffer already searched for the end of the current line
+    scanned: usize,
+    // bytes consumed for the frame in progress
+    consumed: usize,
+}
+
+#[derive(Debug)]
+struct Aggregate {
+    prefix: u8,
+    // elements still expected, a map entry counts as two
+    remaining: usize,
+    items: Vec<RespFrame>,
+}
+
+impl Aggregate {
+    fn is_map(&self) -> bool {
+        matches!(self.prefix, b'%' | b'|')
+    }
+
+    fn into_frame(self) -> RespFrame {
+        match self.prefix {
+            b'~' => RespSet::new(self.items).into(),
+            b'>' => RespPush::new(self.items).into(),
+            b'%' => RespMap(into_pairs(self.items)).into(),
+            b'|' => RespAttribute(into_pairs(self.items)).into(),
+            _ => RespArray::new(self.items).into(),
+        }
+    }
+}
+
+fn into_pairs(items: Vec<RespFrame>) -> BTreeMap<String, RespFrame> {
+    let mut pairs = BTreeMap::new();
+    let mut items = items.into_iter();
+    while let (Some(RespFrame::SimpleString(key)), Some(value)) = (items.next(), items.next()) {
+        pairs.insert(key.0, value);
+    }
+    pairs
+}
+
+// what a line turned out to be
+enum Line {
+    Frame(RespFrame),
+    // an aggregate header or a bulk header, the rest comes next
+    Pending,
+}
+
+impl RespParser {
+    pub fn new(limits: ProtocolLimits) -> Self {
+        RespParser {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    /// Bytes already consumed from the buffer for the frame still being parsed
+    pub fn pending_len(&self) -> usize {
+        self.consumed
+    }
+
+    /// Whether the start of the buffer is the start of a top level frame
+    pub fn is_idle(&self) -> bool {
+        self.stack.is_empty() && self.payload.is_none()
+    }
+
+    /// Consume `buf` up to the end of the next complete frame and return it, or None when
+    /// more data is needed. What was consumed so far is remembered for the next call.
+    pub fn parse(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        let ret = self.parse_frame(buf);
+        if ret.is_err() {
+            *self = RespParser::new(self.limits);
+        }
+        ret
+    }
+
+    fn parse_frame(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        loop {
+            let frame = match self.payload {
+                Some((prefix, len)) => {
+                    if buf.len() < len + CRLF_LENGTH {
+                        return Ok(None);
+                    }
+                    if &buf[len..len + CRLF_LENGTH] != CRLF {
+                        return Err(RespError::Protocol("expected CRLF after bulk".to_string()));
+                    }
+                    let data = buf.split_to(len).freeze();
+                    buf.advance(CRLF_LENGTH);
+                    self.consumed += len + CRLF_LENGTH;
+                    self.payload = None;
+                    payload_frame(prefix, data)?
+                }
+                None => {
+                    let Some(end) = self.find_line(buf) else {
+                        return Ok(None);
+                    };
+                    let line = self.parse_line(&buf[..end]);
+                    buf.advance(end + CRLF_LENGTH);
+                    self.consumed += end + CRLF_LENGTH;
+                    match line? {
+                        Line::Frame(frame) => frame,
+                        Line::Pending => continue,
+                    }
+                }
+            };
+
+            if let Some(frame) = self.complete(frame) {
+                self.consumed = 0;
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    // hand a finished frame to the aggregate waiting for it, returns the top level frame once
+    // nothing is waiting anymore
+    fn complete(&mut self, mut frame: RespFrame) -> Option<RespFrame> {
+        loop {
+            let Some(aggregate) = self.stack.last_mut() else {
+                return Some(frame);
+            };
+            aggregate.items.push(frame);
+            aggregate.remaining -= 1;
+            if aggregate.remaining > 0 {
+                return None;
+            }
+            frame = self.stack.pop()?.into_frame();
+        }
+    }
+
+    // position of the CRLF ending the line at the start of `buf`, the search resumes where the
+    // previous call stopped
+    fn find_line(&mut self, buf: &[u8]) -> Option<usize> {
+        // the prefix byte is never part of the CRLF
+        let from = self.scanned.max(1);
+        let found = buf
+            .get(from..)
+            .and_then(|rest| rest.windows(CRLF_LENGTH).position(|w| w == CRLF));
+        match found {
+            Some(offset) => {
+                self.scanned = 0;
+                Some(from + offset)
+            }
+            None => {
+                // a trailing \r may be completed by the next read
+                if buf.len() > from {
+                    self.scanned = buf.len() - 1;
+                }
+                None
+            }
+        }
+    }
+
+    fn parse_line(&mut self, line: &[u8]) -> Result<Line, RespError> {
+        let (prefix, data) = (line[0], &line[1..]);
+        if self
+            .stack
+            .last()
+            .is_some_and(|aggregate| aggregate.is_map() && aggregate.items.len() % 2 == 0)
+            && prefix != b'+'
+        {
+            return Err(invalid("map key must be a simple string", line));
+        }
+
+        let frame: RespFrame = match prefix {
+            b'+' => SimpleString::new(String::from_utf8_lossy(data)).into(),
+            b'-' => SimpleError::new(String::from_utf8_lossy(data)).into(),
+            b':' => parse_number::<i64>(data)?.into(),
+            b',' => parse_number::<f64>(data)?.into(),
+            b'_' if data.is_empty() => RespNull.into(),
+            b'#' if data == b"t" => true.into(),
+            b'#' if data == b"f" => false.into(),
+            b'(' => parse_big_number(data)?.into(),
+            b'$' | b'=' | b'!' => match parse_length(data) {
+                Some(-1) if prefix == b'$' => BulkString::nill_new().into(),
+                Some(len) if len >= 0 && len as usize <= self.limits.max_bulk_len => {
+                    return Ok(self.expect_payload(prefix, len as usize))
+                }
+                _ => return Err(RespError::Protocol("invalid bulk length".to_string())),
+            },
+            b'*' | b'~' | b'>' | b'%' | b'|' => match parse_length(data) {
+                Some(-1) if prefix == b'*' => RespArray::nill_new().into(),
+                Some(len) if len >= 0 && len as usize <= self.limits.max_multibulk_len => {
+                    return self.expect_elements(prefix, len as usize)
+                }
+                _ => return Err(RespError::Protocol("invalid multibulk length".to_string())),
+            },
+            _ => return Err(invalid("unknown frame type", line)),
+        };
+        Ok(Line::Frame(frame))
+    }
+
+    fn expect_payload(&mut self, prefix: u8, len: usize) -> Line {
+        self.payload = Some((prefix, len));
+        Line::Pending
+    }
+
+    fn expect_elements(&mut self, prefix: u8, len: usize) -> Result<Line, RespError> {
+        let aggregate = Aggregate {
+            prefix,
+            remaining: if matches!(prefix, b'%' | b'|') {
+                len * 2
+            } else {
+                len
+            },
+            items: Vec::with_capacity(len.min(1024)),
+        };
+        if aggregate.remaining == 0 {
+            return Ok(Line::Frame(aggregate.into_frame()));
+        }
+        if self.stack.len() >= self.limits.max_nesting_depth {
+            return Err(RespError::Protocol("too deeply nested request".to_string()));
+        }
+        self.stack.push(aggregate);
+        Ok(Line::Pending)
+    }
+}
+
+fn payload_frame(prefix: u8, data: Bytes) -> Result<RespFrame, RespError> {
+    match prefix {
+        b'=' => {
+            if data.len() < FORMAT_LENGTH || data[FORMAT_LENGTH - 1] != b':' {
+                return Err(invalid("verbatim string without format", &data));
+            }
+            Ok(VerbatimString::new([data[0], data[1], data[2]], &data[FORMAT_LENGTH..]).into())
+        }
+        b'!' => Ok(BlobError::new(data.to_vec()).into()),
+        _ => Ok(BulkString::new(data).into()),
+    }
+}
+
+fn parse_number<T>(data: &[u8]) -> Result<T, RespError>
+where
+    T: std::str::FromStr,
+    RespError: From<T::Err>,
+{
+    Ok(String::from_utf8_lossy(data).parse::<T>()?)
+}
+
+fn parse_length(data: &[u8]) -> Option<i64> {
+    std::str::from_utf8(data).ok()?.parse().ok()
+}
+
+fn parse_big_number(data: &[u8]) -> Result<BigNumber, RespError> {
+    let unsigned = match data.first() {
+        Some(b'+') | Some(b'-') => &data[1..],
+        _ => data,
+    };
+    if unsigned.is_empty() || !unsigned.iter().all(u8::is_ascii_digit) {
+        return Err(invalid("invalid big number", data));
+    }
+    Ok(BigNumber::new(String::from_utf8_lossy(data)))
+}
+
+fn invalid(reason: &str, data: &[u8]) -> RespError {
+    RespError::InvalidFrameType(format!("{}: {:?}", reason, String::from_utf8_lossy(data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::{RespDecode, RespEncode};
+
+    fn frames() -> Vec<RespFrame> {
+        let mut map = RespMap::new();
+        map.insert("hello".to_string(), BulkString::new("world").into());
+        map.insert("nested".to_string(), RespSet::new(vec![1.5.into()]).into());
+        let mut attribute = RespAttribute::new();
+        attribute.insert("ttl".to_string(), 3600.into());
+
+        vec![
+            SimpleString::new("OK").into(),
+            SimpleError::new("ERR unknown command").into(),
+            (-42).into(),
+            RespNull.into(),
+            true.into(),
+            false.into(),
+            (-2.5).into(),
+            BigNumber::new("-3492890328409238509324850943850943825024385").into(),
+            BulkString::new("").into(),
+            BulkString::new(b"binary\r\n\x00\xff".to_vec()).into(),
+            BulkString::nill_new().into(),
+            RespArray::nill_new().into(),
+            RespArray::new(vec![]).into(),
+            VerbatimString::text("Some string").into(),
+            BlobError::new("SYNTAX invalid syntax").into(),
+            map.into(),
+            attribute.into(),
+            RespMap::new().into(),
+            RespPush::new(vec![BulkString::new("message").into()]).into(),
+            RespArray::new(vec![
+                BulkString::new("set").into(),
+                RespArray::new(vec![RespArray::new(vec![1.into()]).into()]).into(),
+                RespSet::new(vec![]).into(),
+            ])
+            .into(),
+        ]
+    }
+
+    // the frames one after the other, as a pipelining client would send them
+    fn stream() -> BytesMut {
+        let mut buf = BytesMut::new();
+        for frame in frames() {
+            frame.encode_to(&mut buf);
+        }
+        buf
+    }
+
+    // feed `input` to the parser in the given reads, one after the other
+    fn parse_reads<'a>(
+        reads: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Result<Vec<RespFrame>, RespError> {
+        let mut parser = RespParser::default();
+        let mut buf = BytesMut::new();
+        let mut frames = Vec::new();
+        for read in reads {
+            buf.extend_from_slice(read);
+            while let Some(frame) = parser.parse(&mut buf)? {
+                frames.push(frame);
+            }
+        }
+        assert!(buf.is_empty() && parser.is_idle());
+        Ok(frames)
+    }
+
+    // feed `input` to the parser in pieces of `chunk` bytes
+    fn parse_chunked(input: &[u8], chunk: usize) -> Result<Vec<RespFrame>, RespError> {
+        parse_reads(input.chunks(chunk))
+    }
+
+    #[test]
+    fn test_parser_matches_decoder() -> Result<()> {
+        let input = stream();
+
+        let mut buf = input.clone();
+        let mut expected = Vec::new();
+        while !buf.is_empty() {
+            expected.push(RespFrame::decode(&mut buf)?);
+        }
+        assert_eq!(expected, frames());
+
+        for chunk in [1, 2, 3, 7, 64, input.len()] {
+            assert_eq!(parse_chunked(&input, chunk)?, expected);
+        }
+        // a frame split in two reads at every possible position
+        for split in 1..input.len() {
+            let (first, second) = input.split_at(split);
+            assert_eq!(
+                parse_reads([first, second])?,
+                expected,
+                "split at {}",
+                split
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_rejects_what_decoder_rejects() {
+        let invalid: [&[u8]; 8] = [
+            b"?oops\r\n",
+            b":12a\r\n",
+            b"#x\r\n",
+            b"(12.5\r\n",
+            b"=3\r\ntxt\r\n",
+            b"%1\r\n:1\r\n:2\r\n",
+            b"*1\r\n$-2\r\n",
+            b"~-1\r\n",
+        ];
+        for input in invalid {
+            assert!(RespFrame::decode(&mut BytesMut::from(input)).is_err());
+            assert!(parse_chunked(input, 1).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parser_checks_bulk_terminator() {
+        for input in [
+            &b"$3\r\nfooXY"[..],
+            b"*1\r\n$3\r\nfoo\rX",
+            b"=7\r\ntxt:abc\n\n",
+        ] {
+            assert_eq!(
+                parse_chunked(input, input.len()),
+                Err(RespError::Protocol("expected CRLF after bulk".to_string())),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_parser_keeps_position() -> Result<()> {
+        let mut parser = RespParser::default();
+        let mut buf = BytesMut::from("*2\r\n$5\r\nhel");
+        assert_eq!(parser.parse(&mut buf)?, None);
+        // the array header and the bulk header were consumed
+        assert_eq!(&buf[..], b"hel");
+        assert!(!parser.is_idle());
+
+        buf.extend_from_slice(b"lo\r\n:1");
+        assert_eq!(parser.parse(&mut buf)?, None);
+        assert_eq!(&buf[..], b":1");
+        buf.extend_from_slice(b"0\r\n+OK\r\n");
+        assert_eq!(
+            parser.parse(&mut buf)?,
+            Some(RespArray::new(vec![BulkString::new("hello").into(), 10.into()]).into())
+        );
+        assert!(parser.is_idle());
+        assert_eq!(
+            parser.parse(&mut buf)?,
+            Some(SimpleString::new("OK").into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_limits() -> Result<()> {
+        let limits = ProtocolLimits {
+            max_bulk_len: 5,
+            max_multibulk_len: 3,
+            max_nesting_depth: 2,
+        };
+        let mut parser = RespParser::new(limits);
+        let error = |reason: &str| Err(RespError::Protocol(reason.to_string()));
+
+        // rejected from the header, before the payload is buffered
+        assert_eq!(
+            parser.parse(&mut BytesMut::from("$9999999999\r\n")),
+            error("invalid bulk length")
+        );
+        assert_eq!(
+            parser.parse(&mut BytesMut::from("*100000000\r\n")),
+            error("invalid multibulk length")
+        );
+        assert_eq!(
+            parser.parse(&mut BytesMut::from("*1\r\n*1\r\n*1\r\n:1\r\n")),
+            error("too deeply nested request")
+        );
+        assert!(parser.is_idle());
+
+        let mut buf = BytesMut::from("*3\r\n$5\r\nhello\r\n*1\r\n$0\r\n\r\n:1\r\n");
+        assert!(parser.parse(&mut buf)?.is_some());
+        buf.extend_from_slice(b"*2\r\n$5\r\nhel");
+        assert_eq!(parser.parse(&mut buf)?, None);
+        assert_eq!(parser.pending_len(), 8);
+        Ok(())
+    }
+}
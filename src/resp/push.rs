@@ -0,0 +1,88 @@
+use std::fmt::Write;
+use std::ops::Deref;
+
+use bytes::{Buf, BytesMut};
+
+use crate::resp::{calc_total_length, parse_length, CRLF_LENGTH};
+use crate::{RespDecode, RespEncode, RespError, RespFrame};
+
+// ><number-of-elements>\r\n<element-1>...<element-n>
+// out of band data sent by the server, e.g. pub/sub messages
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RespPush(pub(crate) Vec<RespFrame>);
+
+impl RespEncode for RespPush {
+    fn encode_to(self, buf: &mut BytesMut) {
+        let _ = write!(buf, ">{}\r\n", self.0.len());
+
+        for frame in self.0 {
+            frame.encode_to(buf);
+        }
+    }
+}
+
+impl RespDecode for RespPush {
+    const PREFIX: &'static str = ">";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+
+        if total_len > buf.len() {
+            return Err(RespError::NotComplete);
+        }
+
+        buf.advance(end + CRLF_LENGTH);
+        let mut frames = Vec::with_capacity(len);
+        for _ in 0..len {
+            frames.push(RespFrame::decode(buf)?);
+        }
+        Ok(RespPush(frames))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl RespPush {
+    pub(crate) fn new(s: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(s.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_push_encode_decode() -> Result<()> {
+        let push = RespPush::new(vec![
+            BulkString::new("message").into(),
+            BulkString::new("ch").into(),
+        ]);
+        let encoded = push.clone().encode();
+        assert_eq!(encoded, b">2\r\n$7\r\nmessage\r\n$2\r\nch\r\n");
+
+        let mut buf = BytesMut::from(&encoded[..encoded.len() - 3]);
+        assert_eq!(RespPush::decode(&mut buf), Err(RespError::NotComplete));
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(RespPush::expect_length(&buf)?, encoded.len());
+        assert_eq!(RespPush::decode(&mut buf)?, push);
+        Ok(())
+    }
+}
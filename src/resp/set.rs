@@ -1,8 +1,9 @@
+use std::fmt::Write;
 use std::ops::{Deref, DerefMut};
 
 use bytes::{Buf, BytesMut};
 
-use crate::resp::{calc_total_length, parse_length, BUF_CAP, CRLF_LENGTH};
+use crate::resp::{calc_total_length, parse_length, CRLF_LENGTH};
 use crate::{RespDecode, RespEncode, RespError, RespFrame};
 
 //~<number-of-elements>\r\n<element-1>...<element-n>
@@ -11,14 +12,12 @@ use crate::{RespDecode, RespEncode, RespError, RespFrame};
 pub struct RespSet(pub(crate) Vec<RespFrame>);
 
 impl RespEncode for RespSet {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("~{}", self.0.len()).into_bytes());
+    fn encode_to(self, buf: &mut BytesMut) {
+        let _ = write!(buf, "~{}\r\n", self.0.len());
 
         for frame in self.0 {
-            buf.extend_from_slice(&frame.encode())
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
@@ -34,7 +33,7 @@ impl RespDecode for RespSet {
         }
 
         buf.advance(end + CRLF_LENGTH);
-        let mut set = RespSet::new();
+        let mut set = RespSet::new(Vec::with_capacity(len));
         for _ in 0..len {
             let frame = RespFrame::decode(buf)?;
             set.push(frame);
@@ -65,7 +64,26 @@ impl DerefMut for RespSet {
 }
 
 impl RespSet {
-    fn new() -> Self {
-        Self(Vec::new())
+    pub(crate) fn new(s: impl Into<Vec<RespFrame>>) -> Self {
+        RespSet(s.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_set_encode_decode() -> Result<()> {
+        let set = RespSet::new(vec![BulkString::new("a").into(), RespFrame::Integer(1)]);
+        let encoded = set.clone().encode();
+        assert_eq!(encoded, b"~2\r\n$1\r\na\r\n:1\r\n");
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(RespSet::decode(&mut buf)?, set);
+        Ok(())
     }
 }
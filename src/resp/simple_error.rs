@@ -6,9 +6,18 @@ use crate::{RespDecode, RespEncode, RespError};
 #[derive(Debug, Clone, PartialEq)]
 pub struct SimpleError(pub(crate) String);
 
+impl SimpleError {
+    pub fn new(s: impl Into<String>) -> Self {
+        SimpleError(s.into())
+    }
+}
+
 impl RespEncode for SimpleError {
-    fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(self.0.len() + 3);
+        buf.extend_from_slice(b"-");
+        buf.extend_from_slice(self.0.as_bytes());
+        buf.extend_from_slice(b"\r\n");
     }
 }
 
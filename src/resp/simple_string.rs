@@ -13,8 +13,11 @@ impl SimpleString {
 }
 
 impl RespEncode for SimpleString {
-    fn encode(self) -> Vec<u8> {
-        format!("+{}+\r\n", self.0).into()
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(self.0.len() + 3);
+        buf.extend_from_slice(b"+");
+        buf.extend_from_slice(self.0.as_bytes());
+        buf.extend_from_slice(b"\r\n");
     }
 }
 
@@ -49,7 +52,7 @@ mod test {
     fn test_simple_string_encode() -> Result<()> {
         let frame: RespFrame = SimpleString::new("OK".to_string()).into();
 
-        assert_eq!(frame.encode(), b"+OK+\r\n");
+        assert_eq!(frame.encode(), b"+OK\r\n");
 
         Ok(())
     }
@@ -0,0 +1,104 @@
+use bytes::{Buf, BytesMut};
+use std::fmt::Write;
+
+use crate::resp::{parse_length, CRLF_LENGTH};
+use crate::{RespDecode, RespEncode, RespError};
+
+// =<length>\r\n<format>:<data>\r\n
+// a string with a three letters format, like txt or mkd, the length counts the format too
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerbatimString {
+    pub(crate) format: [u8; 3],
+    pub(crate) data: Vec<u8>,
+}
+
+const FORMAT_LENGTH: usize = 4;
+
+impl VerbatimString {
+    pub fn new(format: [u8; 3], data: impl Into<Vec<u8>>) -> Self {
+        VerbatimString {
+            format,
+            data: data.into(),
+        }
+    }
+
+    /// A plain text string, the format of INFO like output
+    pub fn text(data: impl Into<Vec<u8>>) -> Self {
+        Self::new(*b"txt", data)
+    }
+
+    pub fn format(&self) -> &[u8] {
+        &self.format
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl RespEncode for VerbatimString {
+    fn encode_to(self, buf: &mut BytesMut) {
+        buf.reserve(self.data.len() + 20);
+        let _ = write!(buf, "={}\r\n", self.data.len() + FORMAT_LENGTH);
+        buf.extend_from_slice(&self.format);
+        buf.extend_from_slice(b":");
+        buf.extend_from_slice(&self.data);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+impl RespDecode for VerbatimString {
+    const PREFIX: &'static str = "=";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        if buf.len() < end + CRLF_LENGTH + len + CRLF_LENGTH {
+            return Err(RespError::NotComplete);
+        }
+        let content = &buf[end + CRLF_LENGTH..end + CRLF_LENGTH + len];
+        if len < FORMAT_LENGTH || content[FORMAT_LENGTH - 1] != b':' {
+            return Err(RespError::InvalidFrameType(format!(
+                "verbatim string without format: {:?}",
+                String::from_utf8_lossy(content)
+            )));
+        }
+
+        buf.advance(end + CRLF_LENGTH);
+        let data = buf.split_to(len + CRLF_LENGTH);
+        Ok(VerbatimString {
+            format: [data[0], data[1], data[2]],
+            data: data[FORMAT_LENGTH..len].to_vec(),
+        })
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        Ok(end + CRLF_LENGTH + len + CRLF_LENGTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_verbatim_string_encode_decode() -> Result<()> {
+        let frame = VerbatimString::text("Some string");
+        let encoded = frame.clone().encode();
+        assert_eq!(encoded, b"=15\r\ntxt:Some string\r\n");
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(VerbatimString::expect_length(&buf)?, encoded.len());
+        let decoded = VerbatimString::decode(&mut buf)?;
+        assert_eq!(decoded, frame);
+        assert_eq!(decoded.format(), b"txt");
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("=3\r\ntxt\r\n");
+        assert!(VerbatimString::decode(&mut buf).is_err());
+        Ok(())
+    }
+}